@@ -6,13 +6,18 @@ use crate::state::history::curve::{CurveHistory, ExtendedCurveHistory};
 use crate::state::history::deposit::DepositHistory;
 use crate::state::history::funding_rate::FundingRateHistory;
 use crate::state::history::liquidation::LiquidationHistory;
+use crate::state::history::oracle::OracleHistory;
 use crate::state::history::order_history::OrderHistory;
 use crate::state::history::{funding_payment::FundingPaymentHistory, trade::TradeHistory};
 use crate::state::market::Markets;
+use crate::state::oracle_basket::OracleBasket;
 use crate::state::order_state::OrderState;
 use crate::state::state::State;
 use crate::state::user::{User, UserPositions};
-use crate::state::user_orders::{OrderTriggerCondition, OrderType, UserOrders};
+use crate::state::user_orders::{
+    OrderTriggerCondition, OrderTriggerPriceSource, OrderType, TrailingStopOffsetType, UserOrders,
+    UserOrdersLoader,
+};
 
 #[derive(Accounts)]
 #[instruction(
@@ -103,6 +108,18 @@ pub struct InitializeOrderState<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct InitializeOracleHistory<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(zero)]
+    pub oracle_history: AccountLoader<'info, OracleHistory>,
+}
+
 #[derive(Accounts)]
 #[instruction(user_nonce: u8)]
 pub struct InitializeUser<'info> {
@@ -153,6 +170,7 @@ pub struct InitializeUserWithExplicitPayer<'info> {
 pub struct InitializeUserOrders<'info> {
     #[account(
         has_one = authority,
+        constraint = &user.user_orders.eq(&Pubkey::default())
     )]
     pub user: Box<Account<'info, User>>,
     #[account(
@@ -173,6 +191,7 @@ pub struct InitializeUserOrders<'info> {
 pub struct InitializeUserOrdersWithExplicitPayer<'info> {
     #[account(
         has_one = authority,
+        constraint = &user.user_orders.eq(&Pubkey::default())
     )]
     pub user: Box<Account<'info, User>>,
     #[account(
@@ -190,6 +209,51 @@ pub struct InitializeUserOrdersWithExplicitPayer<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct ResizeUserOrders<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        constraint = &user_orders.load_orders()?.user.eq(&user.key()),
+        close = authority
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(zero)]
+    pub new_user_orders: AccountLoader<'info, UserOrders>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateUserOrders<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+    )]
+    pub user: Box<Account<'info, User>>,
+    // Orders that were never moved out of the user orders pda can only be found by its seeds
+    #[account(
+        mut,
+        constraint = if user.user_orders.eq(&Pubkey::default()) {
+            user_orders.key().eq(&Pubkey::find_program_address(
+                &[b"user_orders", user.key().as_ref()],
+                program_id,
+            ).0)
+        } else {
+            user.user_orders.eq(&user_orders.key())
+        }
+    )]
+    pub user_orders: AccountInfo<'info>,
+    #[account(zero)]
+    pub new_user_orders: AccountLoader<'info, UserOrders>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 pub struct DeleteUser<'info> {
     #[account(
@@ -473,7 +537,7 @@ pub struct FillOrder<'info> {
     pub user_positions: AccountLoader<'info, UserPositions>,
     #[account(
         mut,
-        has_one = user
+        constraint = &user_orders.load_orders()?.user.eq(&user.key())
     )]
     pub user_orders: AccountLoader<'info, UserOrders>,
     #[account(
@@ -504,6 +568,112 @@ pub struct FillOrder<'info> {
     pub oracle: AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+pub struct FillOrders<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        constraint = &state.order_state.eq(&order_state.key())
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub filler: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        constraint = &state.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        constraint = &state.trade_history.eq(&trade_history.key())
+    )]
+    pub trade_history: AccountLoader<'info, TradeHistory>,
+    #[account(
+        mut,
+        constraint = &state.funding_payment_history.eq(&funding_payment_history.key())
+    )]
+    pub funding_payment_history: AccountLoader<'info, FundingPaymentHistory>,
+    #[account(
+        mut,
+        constraint = &state.funding_rate_history.eq(&funding_rate_history.key())
+    )]
+    pub funding_rate_history: AccountLoader<'info, FundingRateHistory>,
+    #[account(
+        mut,
+        constraint = &order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+    pub oracle: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MatchOrders<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        constraint = &state.order_state.eq(&order_state.key())
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub filler: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        constraint = &taker.positions.eq(&taker_positions.key())
+    )]
+    pub taker: Box<Account<'info, User>>,
+    #[account(mut)]
+    pub taker_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        constraint = &taker_orders.load_orders()?.user.eq(&taker.key())
+    )]
+    pub taker_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        mut,
+        constraint = &maker.positions.eq(&maker_positions.key())
+    )]
+    pub maker: Box<Account<'info, User>>,
+    #[account(mut)]
+    pub maker_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        constraint = &maker_orders.load_orders()?.user.eq(&maker.key())
+    )]
+    pub maker_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        mut,
+        constraint = &state.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        constraint = &state.trade_history.eq(&trade_history.key())
+    )]
+    pub trade_history: AccountLoader<'info, TradeHistory>,
+    #[account(
+        mut,
+        constraint = &state.funding_payment_history.eq(&funding_payment_history.key())
+    )]
+    pub funding_payment_history: AccountLoader<'info, FundingPaymentHistory>,
+    #[account(
+        mut,
+        constraint = &state.funding_rate_history.eq(&funding_rate_history.key())
+    )]
+    pub funding_rate_history: AccountLoader<'info, FundingRateHistory>,
+    #[account(
+        mut,
+        constraint = &order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+    pub oracle: AccountInfo<'info>,
+}
+
 #[derive(Accounts)]
 pub struct PlaceOrder<'info> {
     pub state: Box<Account<'info, State>>,
@@ -528,7 +698,7 @@ pub struct PlaceOrder<'info> {
     pub user_positions: AccountLoader<'info, UserPositions>,
     #[account(
         mut,
-        has_one = user
+        constraint = &user_orders.load_orders()?.user.eq(&user.key())
     )]
     pub user_orders: AccountLoader<'info, UserOrders>,
     #[account(
@@ -541,6 +711,7 @@ pub struct PlaceOrder<'info> {
         constraint = &order_state.order_history.eq(&order_history.key())
     )]
     pub order_history: AccountLoader<'info, OrderHistory>,
+    pub oracle: AccountInfo<'info>,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
@@ -555,11 +726,18 @@ pub struct OrderParams {
     pub reduce_only: bool,
     pub post_only: bool,
     pub immediate_or_cancel: bool,
+    pub fill_or_kill: bool,
+    pub max_ts: i64,
     pub trigger_price: u128,
     pub trigger_condition: OrderTriggerCondition,
     pub optional_accounts: OrderParamsOptionalAccounts,
     pub position_limit: u128,
-    pub oracle_price_offset: u128,
+    pub oracle_price_offset: i128,
+    pub trailing_stop_offset: u128,
+    pub trailing_stop_offset_type: TrailingStopOffsetType,
+    pub twap_slice_count: u64,
+    pub twap_interval: i64,
+    pub trigger_price_source: OrderTriggerPriceSource,
     pub padding0: bool,
     pub padding1: bool,
 }
@@ -571,6 +749,14 @@ impl Default for OrderType {
     }
 }
 
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct ModifyOrderParams {
+    pub base_asset_amount: u128,
+    pub price: u128,
+    pub trigger_price: u128,
+    pub reduce_only: bool,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
 pub struct OrderParamsOptionalAccounts {
     pub discount_token: bool,
@@ -603,7 +789,7 @@ pub struct PlaceAndFillOrder<'info> {
     pub user_positions: AccountLoader<'info, UserPositions>,
     #[account(
         mut,
-        has_one = user
+        constraint = &user_orders.load_orders()?.user.eq(&user.key())
     )]
     pub user_orders: AccountLoader<'info, UserOrders>,
     #[account(
@@ -656,10 +842,83 @@ pub struct CancelOrder<'info> {
         has_one = user
     )]
     pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        constraint = &user_orders.load_orders()?.user.eq(&user.key())
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        mut,
+        constraint = &state.funding_payment_history.eq(&funding_payment_history.key())
+    )]
+    pub funding_payment_history: AccountLoader<'info, FundingPaymentHistory>,
+    #[account(
+        mut,
+        constraint = &order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+}
+
+#[derive(Accounts)]
+pub struct ModifyOrder<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        constraint = &state.order_state.eq(&order_state.key())
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        has_one = authority
+    )]
+    pub user: Box<Account<'info, User>>,
+    pub authority: Signer<'info>,
+    #[account(
+        constraint = &state.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        constraint = &user_orders.load_orders()?.user.eq(&user.key())
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        mut,
+        constraint = &order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+    pub oracle: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExpireOrder<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        constraint = &state.order_state.eq(&order_state.key())
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub filler: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        constraint = &user.positions.eq(&user_positions.key())
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        constraint = &state.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
     #[account(
         mut,
         has_one = user
     )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        constraint = &user_orders.load_orders()?.user.eq(&user.key())
+    )]
     pub user_orders: AccountLoader<'info, UserOrders>,
     #[account(
         mut,
@@ -796,6 +1055,45 @@ pub struct SettleFunding<'info> {
     pub funding_payment_history: AccountLoader<'info, FundingPaymentHistory>,
 }
 
+#[derive(Accounts)]
+pub struct InitializeOracleBasket<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(zero)]
+    pub oracle_basket: AccountLoader<'info, OracleBasket>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct OracleBasketComponentParams {
+    pub oracle: Pubkey,
+    pub weight: u128,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOracleBasket<'info> {
+    #[account(mut)]
+    pub oracle_basket: AccountLoader<'info, OracleBasket>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateOracleStatus<'info> {
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        constraint = &state.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    pub oracle: AccountInfo<'info>,
+    #[account(
+        mut,
+        constraint = &state.oracle_history.eq(&oracle_history.key())
+    )]
+    pub oracle_history: AccountLoader<'info, OracleHistory>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateFundingRate<'info> {
     pub state: Box<Account<'info, State>>,
@@ -905,6 +1203,26 @@ pub struct AdminUpdateMarket<'info> {
     pub markets: AccountLoader<'info, Markets>,
 }
 
+#[derive(Accounts)]
+pub struct UpdateOrderHistory<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: Box<Account<'info, State>>,
+    #[account(
+        mut,
+        constraint = &state.order_state.eq(&order_state.key())
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        constraint = &order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountInfo<'info>,
+    #[account(zero)]
+    pub new_order_history: AccountLoader<'info, OrderHistory>,
+}
+
 #[derive(Accounts)]
 pub struct UpdateCurveHistory<'info> {
     pub admin: Signer<'info>,
@@ -3,6 +3,7 @@ use std::cmp::{max, min};
 
 use anchor_lang::prelude::*;
 
+use crate::controller;
 use crate::error::*;
 use crate::math::amm;
 use crate::math::amm::normalise_oracle_price;
@@ -18,7 +19,7 @@ use crate::state::history::funding_payment::{FundingPaymentHistory, FundingPayme
 use crate::state::history::funding_rate::{FundingRateHistory, FundingRateRecord};
 use crate::state::market::AMM;
 use crate::state::market::{Market, Markets};
-use crate::state::state::OracleGuardRails;
+use crate::state::state::{ExtendedOracleGuardRails, OracleGuardRails};
 use crate::state::user::{User, UserPositions};
 use solana_program::clock::UnixTimestamp;
 use solana_program::msg;
@@ -90,11 +91,14 @@ pub fn update_funding_rate(
     market_index: u64,
     market: &mut Market,
     price_oracle: &AccountInfo,
+    backup_price_oracle: Option<&AccountInfo>,
     now: UnixTimestamp,
     clock_slot: u64,
     funding_rate_history: &mut RefMut<FundingRateHistory>,
     guard_rails: &OracleGuardRails,
+    extended_guard_rails: &ExtendedOracleGuardRails,
     funding_paused: bool,
+    oracle_circuit_breaker_threshold: u64,
     precomputed_mark_price: Option<u128>,
 ) -> ClearingHouseResult {
     let time_since_last_update = now
@@ -102,13 +106,24 @@ pub fn update_funding_rate(
         .ok_or_else(math_error!())?;
 
     // Pause funding if oracle is invalid or if mark/oracle spread is too divergent
-    let (block_funding_rate_update, oracle_price_data) = oracle::block_operation(
-        &market.amm,
+    let (block_funding_rate_update, oracle_status) = oracle::block_operation(
+        market,
         price_oracle,
+        backup_price_oracle,
         clock_slot,
         guard_rails,
+        extended_guard_rails,
         precomputed_mark_price,
     )?;
+    let oracle_price_data = oracle_status.price_data;
+
+    controller::oracle::update_circuit_breaker(
+        market_index,
+        market,
+        oracle_status.is_valid,
+        oracle_circuit_breaker_threshold,
+        clock_slot,
+    )?;
     let normalised_oracle_price =
         normalise_oracle_price(&market.amm, &oracle_price_data, precomputed_mark_price)?;
 
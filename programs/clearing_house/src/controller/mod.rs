@@ -1,5 +1,6 @@
 pub mod amm;
 pub mod funding;
+pub mod oracle;
 pub mod orders;
 pub mod position;
 pub mod repeg;
@@ -0,0 +1,101 @@
+use crate::error::*;
+use crate::math::oracle::OracleStatus;
+use crate::math_error;
+use crate::state::history::oracle::{OracleHistory, OracleRecord, OracleRecordType};
+use crate::state::market::Market;
+use anchor_lang::prelude::*;
+use solana_program::msg;
+use std::cell::RefMut;
+
+pub fn update_oracle_status(
+    market_index: u64,
+    market: &mut Market,
+    oracle: &Pubkey,
+    oracle_status: &OracleStatus,
+    mark_price: u128,
+    oracle_history: &mut RefMut<OracleHistory>,
+    circuit_breaker_threshold: u64,
+    now: i64,
+    clock_slot: u64,
+) -> ClearingHouseResult {
+    let mut record_types: Vec<OracleRecordType> = vec![];
+
+    let invalid_reading_counted = update_circuit_breaker(
+        market_index,
+        market,
+        oracle_status.is_valid,
+        circuit_breaker_threshold,
+        clock_slot,
+    )?;
+    if invalid_reading_counted {
+        record_types.push(OracleRecordType::Invalid);
+    }
+
+    // Lift reduce only once the feed recovers
+    if oracle_status.is_valid && market.reduce_only {
+        msg!("Oracle recovered for market {}", market_index);
+        market.reduce_only = false;
+        record_types.push(OracleRecordType::Recovered);
+    }
+
+    if oracle_status.mark_too_divergent {
+        record_types.push(OracleRecordType::TooDivergent);
+    }
+
+    for record_type in record_types {
+        let record_id = oracle_history.next_record_id();
+        oracle_history.append(OracleRecord {
+            ts: now,
+            record_id,
+            market_index,
+            oracle: *oracle,
+            record_type,
+            oracle_price: oracle_status.price_data.price,
+            oracle_confidence: oracle_status.price_data.confidence,
+            oracle_delay: oracle_status.price_data.delay,
+            mark_price,
+            oracle_mark_spread_pct: oracle_status.oracle_mark_spread_pct,
+            consecutive_invalid_readings: market.consecutive_invalid_oracle_readings,
+            reduce_only: market.reduce_only,
+        });
+    }
+
+    Ok(())
+}
+
+// Every instruction that reads the oracle counts towards the circuit breaker, so it trips even if
+// no one calls update_oracle_status. Only update_oracle_status lifts reduce only, so recoveries are
+// always recorded in the oracle history
+pub fn update_circuit_breaker(
+    market_index: u64,
+    market: &mut Market,
+    is_oracle_valid: bool,
+    circuit_breaker_threshold: u64,
+    clock_slot: u64,
+) -> ClearingHouseResult<bool> {
+    if is_oracle_valid {
+        market.consecutive_invalid_oracle_readings = 0;
+        return Ok(false);
+    }
+
+    // A slot only counts as one invalid reading, however many instructions read the oracle in it
+    if market.last_invalid_oracle_reading_slot == clock_slot {
+        return Ok(false);
+    }
+
+    market.last_invalid_oracle_reading_slot = clock_slot;
+    market.consecutive_invalid_oracle_readings = market
+        .consecutive_invalid_oracle_readings
+        .checked_add(1)
+        .ok_or_else(math_error!())?;
+
+    if circuit_breaker_threshold > 0
+        && market.consecutive_invalid_oracle_readings >= circuit_breaker_threshold
+        && !market.reduce_only
+    {
+        msg!("Oracle circuit breaker tripped for market {}", market_index);
+        market.reduce_only = true;
+    }
+
+    Ok(true)
+}
@@ -1,4 +1,4 @@
-use crate::controller::position::{add_new_position, get_position_index};
+use crate::controller::position::{add_new_position, get_position_index, PositionDirection};
 use crate::error::ClearingHouseResult;
 use crate::error::*;
 use crate::math::casting::cast;
@@ -14,16 +14,20 @@ use crate::math::{amm, fees, margin::*, orders::*};
 use crate::state::{
     history::order_history::{OrderHistory, OrderRecord},
     history::trade::{TradeHistory, TradeRecord},
-    market::Markets,
+    market::{Market, Markets},
     order_state::*,
     state::*,
-    user::{User, UserPositions},
+    user::{MarketPosition, User, UserPositions},
     user_orders::*,
 };
 
 use crate::controller;
 use crate::math::amm::normalise_oracle_price;
 use crate::math::fees::calculate_order_fee_tier;
+use crate::math::oracle::{get_oracle_price_data, get_oracle_status};
+use crate::optional_accounts::{
+    get_backup_oracle, get_referrer_for_fill_orders, get_user_accounts_for_fill_orders,
+};
 use crate::order_validation::validate_order;
 use crate::state::history::funding_payment::FundingPaymentHistory;
 use crate::state::history::funding_rate::FundingRateHistory;
@@ -37,6 +41,8 @@ pub fn place_order(
     user: &mut Box<Account<User>>,
     user_positions: &AccountLoader<UserPositions>,
     markets: &AccountLoader<Markets>,
+    oracle: &AccountInfo,
+    remaining_accounts: &[AccountInfo],
     user_orders: &AccountLoader<UserOrders>,
     funding_payment_history: &AccountLoader<FundingPaymentHistory>,
     order_history: &AccountLoader<OrderHistory>,
@@ -44,7 +50,8 @@ pub fn place_order(
     referrer: &Option<Account<User>>,
     clock: &Clock,
     params: OrderParams,
-) -> ClearingHouseResult {
+    group_id: u128,
+) -> ClearingHouseResult<Option<u128>> {
     let now = clock.unix_timestamp;
 
     let user_positions = &mut user_positions
@@ -64,9 +71,7 @@ pub fn place_order(
         now,
     )?;
 
-    let user_orders = &mut user_orders
-        .load_mut()
-        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    let user_orders = &mut user_orders.load_orders_mut()?;
     let new_order_idx = user_orders
         .orders
         .iter()
@@ -92,22 +97,25 @@ pub fn place_order(
     let market_index = params.market_index;
     let market = markets.get_market(market_index);
 
-    // Increment open orders for existing position
-    let position_index = get_position_index(user_positions, market_index)
-        .or_else(|_| add_new_position(user_positions, market_index))?;
-    let market_position = &mut user_positions.positions[position_index];
-    market_position.open_orders += 1;
+    let valid_oracle_price =
+        get_valid_oracle_price(state, market, oracle, remaining_accounts, clock.slot)?;
 
-    let order_id = order_history_account.next_order_id();
-    let new_order = Order {
+    let existing_position_index = get_position_index(user_positions, market_index).ok();
+    let user_base_asset_amount = match existing_position_index {
+        Some(position_index) => user_positions.positions[position_index].base_asset_amount,
+        None => 0,
+    };
+
+    let mut new_order = Order {
         status: OrderStatus::Open,
         order_type: params.order_type,
         ts: now,
-        order_id,
+        // assigned once the order is known to rest on the book
+        order_id: 0,
         user_order_id: params.user_order_id,
         market_index: params.market_index,
         price: params.price,
-        user_base_asset_amount: market_position.base_asset_amount,
+        user_base_asset_amount,
         base_asset_amount: params.base_asset_amount,
         quote_asset_amount: params.quote_asset_amount,
         base_asset_amount_filled: 0,
@@ -115,6 +123,9 @@ pub fn place_order(
         fee: 0,
         direction: params.direction,
         reduce_only: params.reduce_only,
+        post_only: params.post_only,
+        immediate_or_cancel: params.immediate_or_cancel,
+        fill_or_kill: params.fill_or_kill,
         discount_tier,
         trigger_price: params.trigger_price,
         trigger_condition: params.trigger_condition,
@@ -122,17 +133,67 @@ pub fn place_order(
             Some(referrer) => referrer.key(),
             None => Pubkey::default(),
         },
-
-        // always false until we add support
-        post_only: false,
-        immediate_or_cancel: false,
-        oracle_price_offset: 0,
+        oracle_price_offset: params.oracle_price_offset,
+        max_ts: params.max_ts,
+        group_id,
+        trailing_stop_offset: params.trailing_stop_offset,
+        trailing_stop_offset_type: params.trailing_stop_offset_type,
+        twap_slice_count: params.twap_slice_count,
+        twap_interval: params.twap_interval,
+        twap_last_slice_ts: 0,
+        position_limit: params.position_limit,
+        trigger_price_source: params.trigger_price_source,
         padding: [0; 3],
     };
 
+    // Trailing stops start trailing from their trigger price source when they are placed
+    if new_order.order_type == OrderType::TrailingStop {
+        if let Some(source_price) = calculate_trailing_stop_source_price(
+            &new_order,
+            market,
+            market.amm.mark_price()?,
+            valid_oracle_price,
+        )? {
+            new_order.trigger_price =
+                calculate_trailing_stop_trigger_price(&new_order, source_price)?;
+        }
+    }
+
     validate_order(&new_order, market, order_state)?;
 
+    // A post only order that would cross the amm would take liquidity, so it is cancelled
+    // instead of being placed. This happens before the order takes a position slot or an order id
+    if new_order.post_only && order_crosses_amm(&new_order, market, valid_oracle_price)? {
+        msg!("Post only order would cross the amm");
+        let record_id = order_history_account.next_record_id();
+        order_history_account.append(OrderRecord {
+            ts: now,
+            record_id,
+            order: new_order,
+            user: user.key(),
+            authority: user.authority,
+            action: OrderAction::PostOnlyCancel,
+            filler: Pubkey::default(),
+            trade_record_id: 0,
+            base_asset_amount_filled: 0,
+            quote_asset_amount_filled: 0,
+            filler_reward: 0,
+            fee: 0,
+            padding: [0; 10],
+        });
+        return Ok(None);
+    }
+
+    // Increment open orders for existing position
+    let position_index = match existing_position_index {
+        Some(position_index) => position_index,
+        None => add_new_position(user_positions, market_index)?,
+    };
+    let market_position = &mut user_positions.positions[position_index];
+
+    new_order.order_id = order_history_account.next_order_id();
     user_orders.orders[new_order_idx] = new_order;
+    market_position.open_orders += 1;
 
     // Add to the order history account
     let record_id = order_history_account.next_record_id();
@@ -152,6 +213,98 @@ pub fn place_order(
         padding: [0; 10],
     });
 
+    Ok(Some(new_order.order_id))
+}
+
+// Orders are priced off the live oracle rather than the amm's last_oracle_price, which is only as
+// fresh as the last trade
+fn get_valid_oracle_price(
+    state: &State,
+    market: &Market,
+    oracle: &AccountInfo,
+    remaining_accounts: &[AccountInfo],
+    clock_slot: u64,
+) -> ClearingHouseResult<Option<i128>> {
+    let backup_oracle = get_backup_oracle(&market.amm, remaining_accounts);
+    let (oracle_price_data, is_oracle_valid) = get_oracle_price_data(
+        &market.amm,
+        oracle,
+        backup_oracle,
+        clock_slot,
+        &market
+            .get_oracle_guard_rails(&state.oracle_guard_rails)
+            .validity,
+        &market.get_extended_oracle_guard_rails(&state.extended_oracle_guard_rails),
+    )?;
+
+    Ok(if is_oracle_valid {
+        Some(oracle_price_data.price)
+    } else {
+        None
+    })
+}
+
+// Places linked orders in one instruction. Filling any of them cancels the rest of the group
+pub fn place_one_cancels_other_orders(
+    state: &State,
+    order_state: &OrderState,
+    user: &mut Box<Account<User>>,
+    user_positions: &AccountLoader<UserPositions>,
+    markets: &AccountLoader<Markets>,
+    oracle: &AccountInfo,
+    remaining_accounts: &[AccountInfo],
+    user_orders: &AccountLoader<UserOrders>,
+    funding_payment_history: &AccountLoader<FundingPaymentHistory>,
+    order_history: &AccountLoader<OrderHistory>,
+    discount_token: Option<TokenAccount>,
+    referrer: &Option<Account<User>>,
+    clock: &Clock,
+    legs: Vec<OrderParams>,
+) -> ClearingHouseResult {
+    if legs.len() < 2 {
+        msg!("One cancels other group needs at least two orders");
+        return Err(ErrorCode::InvalidOrderGroup);
+    }
+
+    let market_index = legs[0].market_index;
+    if legs.iter().any(|leg| leg.market_index != market_index) {
+        msg!("One cancels other orders must be for the same market");
+        return Err(ErrorCode::InvalidOrderGroup);
+    }
+
+    if legs.iter().any(|leg| leg.post_only) {
+        msg!("One cancels other orders cant be post only");
+        return Err(ErrorCode::InvalidOrderGroup);
+    }
+
+    // The group is identified by the order id of its first leg
+    let group_id = order_history
+        .load()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?
+        .last_order_id
+        .checked_add(1)
+        .ok_or_else(math_error!())?;
+
+    for leg in legs {
+        place_order(
+            state,
+            order_state,
+            user,
+            user_positions,
+            markets,
+            oracle,
+            remaining_accounts,
+            user_orders,
+            funding_payment_history,
+            order_history,
+            discount_token,
+            referrer,
+            clock,
+            leg,
+            group_id,
+        )?;
+    }
+
     Ok(())
 }
 
@@ -165,9 +318,7 @@ pub fn cancel_order_by_order_id(
     order_history: &AccountLoader<OrderHistory>,
     clock: &Clock,
 ) -> ClearingHouseResult {
-    let user_orders = &mut user_orders
-        .load_mut()
-        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    let user_orders = &mut user_orders.load_orders_mut()?;
 
     let order_index = user_orders
         .orders
@@ -184,6 +335,9 @@ pub fn cancel_order_by_order_id(
         funding_payment_history,
         order_history,
         clock,
+        OrderAction::Cancel,
+        Pubkey::default(),
+        0,
     )
 }
 
@@ -197,9 +351,7 @@ pub fn cancel_order_by_user_order_id(
     order_history: &AccountLoader<OrderHistory>,
     clock: &Clock,
 ) -> ClearingHouseResult {
-    let user_orders = &mut user_orders
-        .load_mut()
-        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    let user_orders = &mut user_orders.load_orders_mut()?;
 
     let order_index = user_orders
         .orders
@@ -216,9 +368,66 @@ pub fn cancel_order_by_user_order_id(
         funding_payment_history,
         order_history,
         clock,
+        OrderAction::Cancel,
+        Pubkey::default(),
+        0,
     )
 }
 
+pub fn cancel_all_orders(
+    user: &mut Box<Account<User>>,
+    user_positions: &AccountLoader<UserPositions>,
+    markets: &AccountLoader<Markets>,
+    user_orders: &AccountLoader<UserOrders>,
+    funding_payment_history: &AccountLoader<FundingPaymentHistory>,
+    order_history: &AccountLoader<OrderHistory>,
+    clock: &Clock,
+    market_index: Option<u64>,
+    direction: Option<PositionDirection>,
+    reduce_only: Option<bool>,
+) -> ClearingHouseResult {
+    let user_orders = &mut user_orders.load_orders_mut()?;
+
+    for order in user_orders.orders.iter_mut() {
+        if order.status != OrderStatus::Open {
+            continue;
+        }
+
+        if let Some(market_index) = market_index {
+            if order.market_index != market_index {
+                continue;
+            }
+        }
+
+        if let Some(direction) = direction {
+            if order.direction != direction {
+                continue;
+            }
+        }
+
+        if let Some(reduce_only) = reduce_only {
+            if order.reduce_only != reduce_only {
+                continue;
+            }
+        }
+
+        cancel_order(
+            order,
+            user,
+            user_positions,
+            markets,
+            funding_payment_history,
+            order_history,
+            clock,
+            OrderAction::Cancel,
+            Pubkey::default(),
+            0,
+        )?;
+    }
+
+    Ok(())
+}
+
 pub fn cancel_order(
     order: &mut Order,
     user: &mut Box<Account<User>>,
@@ -227,6 +436,9 @@ pub fn cancel_order(
     funding_payment_history: &AccountLoader<FundingPaymentHistory>,
     order_history: &AccountLoader<OrderHistory>,
     clock: &Clock,
+    action: OrderAction,
+    filler: Pubkey,
+    filler_reward: u128,
 ) -> ClearingHouseResult {
     let now = clock.unix_timestamp;
 
@@ -262,13 +474,14 @@ pub fn cancel_order(
         order: *order,
         user: user.key(),
         authority: user.authority,
-        action: OrderAction::Cancel,
-        filler: Pubkey::default(),
+        action,
+        filler,
         trade_record_id: 0,
         base_asset_amount_filled: 0,
         quote_asset_amount_filled: 0,
-        filler_reward: 0,
-        fee: 0,
+        filler_reward,
+        // the filler's reward is paid out of the user's collateral
+        fee: filler_reward,
         padding: [0; 10],
     });
 
@@ -281,14 +494,177 @@ pub fn cancel_order(
     Ok(())
 }
 
-pub fn fill_order(
+pub fn modify_order(
+    order_id: u128,
+    state: &State,
+    order_state: &OrderState,
+    user: &Account<User>,
+    markets: &AccountLoader<Markets>,
+    oracle: &AccountInfo,
+    remaining_accounts: &[AccountInfo],
+    user_orders: &AccountLoader<UserOrders>,
+    order_history: &AccountLoader<OrderHistory>,
+    clock: &Clock,
+    params: ModifyOrderParams,
+) -> ClearingHouseResult {
+    let now = clock.unix_timestamp;
+
+    let user_orders = &mut user_orders.load_orders_mut()?;
+
+    let order_index = user_orders
+        .orders
+        .iter()
+        .position(|order| order.order_id == order_id)
+        .ok_or_else(print_error!(ErrorCode::OrderDoesNotExist))?;
+    let order = &mut user_orders.orders[order_index];
+
+    if order.status != OrderStatus::Open {
+        return Err(ErrorCode::OrderNotOpen);
+    }
+
+    if order.max_ts != 0 && now > order.max_ts {
+        msg!("Cant modify expired order");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    let markets = &markets
+        .load()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    let market = markets.get_market(order.market_index);
+
+    if !market.amm.is_market_oracle(oracle.key) {
+        return Err(ErrorCode::InvalidOracle);
+    }
+
+    // The order must still have something left to fill after the amendment
+    let base_asset_amount_left_to_fill = params
+        .base_asset_amount
+        .checked_sub(order.base_asset_amount_filled)
+        .ok_or_else(print_error!(ErrorCode::InvalidOrder))?;
+
+    if base_asset_amount_left_to_fill < market.amm.minimum_base_asset_trade_size {
+        msg!("Modified order base_asset_amount left to fill smaller than market minimum_base_asset_trade_size");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    let modified_order = Order {
+        base_asset_amount: params.base_asset_amount,
+        price: params.price,
+        // trailing stops keep the trigger price they have trailed to
+        trigger_price: if order.order_type == OrderType::TrailingStop {
+            order.trigger_price
+        } else {
+            params.trigger_price
+        },
+        reduce_only: params.reduce_only,
+        ..*order
+    };
+
+    validate_order(&modified_order, market, order_state)?;
+
+    if modified_order.post_only
+        && order_crosses_amm(
+            &modified_order,
+            market,
+            get_valid_oracle_price(state, market, oracle, remaining_accounts, clock.slot)?,
+        )?
+    {
+        msg!("Post only order would cross the amm");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    *order = modified_order;
+
+    let order_history_account = &mut order_history
+        .load_mut()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    let record_id = order_history_account.next_record_id();
+    order_history_account.append(OrderRecord {
+        ts: now,
+        record_id,
+        order: *order,
+        user: user.key(),
+        authority: user.authority,
+        action: OrderAction::Modify,
+        filler: Pubkey::default(),
+        trade_record_id: 0,
+        base_asset_amount_filled: 0,
+        quote_asset_amount_filled: 0,
+        filler_reward: 0,
+        fee: 0,
+        padding: [0; 10],
+    });
+
+    Ok(())
+}
+
+pub fn expire_order(
+    order_id: u128,
+    order_state: &OrderState,
+    user: &mut Box<Account<User>>,
+    user_positions: &AccountLoader<UserPositions>,
+    markets: &AccountLoader<Markets>,
+    user_orders: &AccountLoader<UserOrders>,
+    filler: &mut Box<Account<User>>,
+    funding_payment_history: &AccountLoader<FundingPaymentHistory>,
+    order_history: &AccountLoader<OrderHistory>,
+    clock: &Clock,
+) -> ClearingHouseResult {
+    let now = clock.unix_timestamp;
+
+    let user_orders = &mut user_orders.load_orders_mut()?;
+
+    let order_index = user_orders
+        .orders
+        .iter()
+        .position(|order| order.order_id == order_id)
+        .ok_or_else(print_error!(ErrorCode::OrderDoesNotExist))?;
+    let order = &mut user_orders.orders[order_index];
+
+    if order.max_ts == 0 || now <= order.max_ts {
+        return Err(ErrorCode::OrderNotExpired);
+    }
+
+    // Users dont get rewarded for expiring their own orders
+    let filler_reward = if filler.key() == user.key() {
+        0
+    } else {
+        min(order_state.expire_order_reward, user.collateral)
+    };
+
+    cancel_order(
+        order,
+        user,
+        user_positions,
+        markets,
+        funding_payment_history,
+        order_history,
+        clock,
+        OrderAction::Expire,
+        filler.key(),
+        filler_reward,
+    )?;
+
+    user.collateral = user.collateral.saturating_sub(filler_reward);
+
+    filler.collateral = filler
+        .collateral
+        .checked_add(filler_reward)
+        .ok_or_else(math_error!())?;
+
+    Ok(())
+}
+
+// Returns the base asset amount filled and whether the order was cancelled instead of being filled
+pub fn fill_order<'info>(
     order_id: u128,
     state: &State,
     order_state: &OrderState,
     user: &mut Box<Account<User>>,
     user_positions: &AccountLoader<UserPositions>,
     markets: &AccountLoader<Markets>,
-    oracle: &AccountInfo,
+    oracle: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
     user_orders: &AccountLoader<UserOrders>,
     filler: &mut Box<Account<User>>,
     funding_payment_history: &AccountLoader<FundingPaymentHistory>,
@@ -297,7 +673,7 @@ pub fn fill_order(
     funding_rate_history: &AccountLoader<FundingRateHistory>,
     referrer: Option<Account<User>>,
     clock: &Clock,
-) -> ClearingHouseResult<u128> {
+) -> ClearingHouseResult<(u128, bool)> {
     let now = clock.unix_timestamp;
     let clock_slot = clock.slot;
 
@@ -320,9 +696,7 @@ pub fn fill_order(
         )?;
     }
 
-    let user_orders = &mut user_orders
-        .load_mut()
-        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    let user_orders = &mut user_orders.load_orders_mut()?;
     let order_index = user_orders
         .orders
         .iter()
@@ -334,6 +708,16 @@ pub fn fill_order(
         return Err(ErrorCode::OrderNotOpen);
     }
 
+    if order.max_ts != 0 && now > order.max_ts {
+        msg!("Order expired");
+        return Ok((0, false));
+    }
+
+    if order.order_type == OrderType::Twap && now < calculate_twap_next_slice_ts(order)? {
+        msg!("Twap order next slice not available yet");
+        return Ok((0, false));
+    }
+
     let market_index = order.market_index;
     {
         let markets = &markets
@@ -345,11 +729,45 @@ pub fn fill_order(
             return Err(ErrorCode::MarketIndexNotInitialized);
         }
 
-        if !market.amm.oracle.eq(oracle.key) {
+        if !market.amm.is_market_oracle(oracle.key) {
             return Err(ErrorCode::InvalidOracle);
         }
     }
 
+    // A reduce only order that can no longer reduce the position is cancelled instead of failing
+    // the fill
+    let position_index = get_position_index(user_positions, market_index)?;
+    if calculate_base_asset_amount_to_close_position(
+        order,
+        user_positions.positions[position_index].base_asset_amount,
+    ) == Some(0)
+    {
+        msg!("Reduce only order cant reduce position");
+        let order_history_account = &mut order_history
+            .load_mut()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+        let record_id = order_history_account.next_record_id();
+        order_history_account.append(OrderRecord {
+            ts: now,
+            record_id,
+            order: *order,
+            user: user.key(),
+            authority: user.authority,
+            action: OrderAction::ReduceOnlyCancel,
+            filler: filler.key(),
+            trade_record_id: 0,
+            base_asset_amount_filled: 0,
+            quote_asset_amount_filled: 0,
+            filler_reward: 0,
+            fee: 0,
+            padding: [0; 10],
+        });
+
+        user_positions.positions[position_index].open_orders -= 1;
+        *order = Order::default();
+        return Ok((0, true));
+    }
+
     let mark_price_before: u128;
     let oracle_mark_spread_pct_before: i128;
     let is_oracle_valid: bool;
@@ -360,21 +778,32 @@ pub fn fill_order(
             .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
         let market = markets.get_market_mut(market_index);
         mark_price_before = market.amm.mark_price()?;
-        let oracle_price_data = &market.amm.get_oracle_price(oracle, clock_slot)?;
-        oracle_mark_spread_pct_before = amm::calculate_oracle_mark_spread_pct(
-            &market.amm,
-            oracle_price_data,
-            0,
+        let backup_oracle = get_backup_oracle(&market.amm, remaining_accounts);
+        let oracle_status = get_oracle_status(
+            market,
+            oracle,
+            backup_oracle,
+            clock_slot,
+            &state.oracle_guard_rails,
+            &state.extended_oracle_guard_rails,
             Some(mark_price_before),
         )?;
+        let oracle_price_data = &oracle_status.price_data;
+        oracle_mark_spread_pct_before = oracle_status.oracle_mark_spread_pct;
         oracle_price = oracle_price_data.price;
         let normalised_price =
             normalise_oracle_price(&market.amm, oracle_price_data, Some(mark_price_before))?;
-        is_oracle_valid =
-            amm::is_oracle_valid(oracle_price_data, &state.oracle_guard_rails.validity)?;
+        is_oracle_valid = oracle_status.is_valid;
         if is_oracle_valid {
             amm::update_oracle_price_twap(&mut market.amm, now, normalised_price)?;
         }
+        controller::oracle::update_circuit_breaker(
+            market_index,
+            market,
+            is_oracle_valid,
+            state.oracle_circuit_breaker_threshold,
+            clock_slot,
+        )?;
     }
 
     let valid_oracle_price = if is_oracle_valid {
@@ -383,6 +812,37 @@ pub fn fill_order(
         None
     };
 
+    // The mark can move through a resting post only order, which would then take liquidity from the
+    // amm instead of providing it, so it keeps resting until the mark moves back
+    if order.post_only
+        && order_crosses_amm(
+            order,
+            markets
+                .load()
+                .or(Err(ErrorCode::UnableToLoadAccountLoader))?
+                .get_market(market_index),
+            valid_oracle_price,
+        )?
+    {
+        msg!("Post only order would cross the amm");
+        return Ok((0, false));
+    }
+
+    // Every fill attempt moves a trailing stop's trigger price along with its source price
+    if order.order_type == OrderType::TrailingStop {
+        if let Some(source_price) = calculate_trailing_stop_source_price(
+            order,
+            markets
+                .load()
+                .or(Err(ErrorCode::UnableToLoadAccountLoader))?
+                .get_market(market_index),
+            mark_price_before,
+            valid_oracle_price,
+        )? {
+            order.trigger_price = calculate_trailing_stop_trigger_price(order, source_price)?;
+        }
+    }
+
     let (base_asset_amount, quote_asset_amount, potentially_risk_increasing) = execute_order(
         state,
         user,
@@ -398,36 +858,53 @@ pub fn fill_order(
     )?;
 
     if base_asset_amount == 0 {
-        return Ok(0);
+        return Ok((0, false));
     }
 
-    let mark_price_after: u128;
-    let oracle_price_after: i128;
-    let oracle_mark_spread_pct_after: i128;
+    // Order fails if it's risk increasing and the oracle circuit breaker has tripped
+    if potentially_risk_increasing
+        && markets
+            .load()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?
+            .get_market(market_index)
+            .reduce_only
+    {
+        return Err(ErrorCode::MarketReduceOnly);
+    }
+
+    let mark_price_after: u128;
+    let oracle_price_after: i128;
+    let oracle_mark_spread_pct_after: i128;
+    let oracle_guard_rails: OracleGuardRails;
     {
         let markets = &mut markets
             .load_mut()
             .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
         let market = markets.get_market_mut(market_index);
+        oracle_guard_rails = market.get_oracle_guard_rails(&state.oracle_guard_rails);
         mark_price_after = market.amm.mark_price()?;
-        let oracle_price_data = &market.amm.get_oracle_price(oracle, clock_slot)?;
-        oracle_mark_spread_pct_after = amm::calculate_oracle_mark_spread_pct(
-            &market.amm,
-            oracle_price_data,
-            0,
+        let backup_oracle = get_backup_oracle(&market.amm, remaining_accounts);
+        let oracle_status = get_oracle_status(
+            market,
+            oracle,
+            backup_oracle,
+            clock_slot,
+            &state.oracle_guard_rails,
+            &state.extended_oracle_guard_rails,
             Some(mark_price_after),
         )?;
-        oracle_price_after = oracle_price_data.price;
+        oracle_mark_spread_pct_after = oracle_status.oracle_mark_spread_pct;
+        oracle_price_after = oracle_status.price_data.price;
     }
 
     let is_oracle_mark_too_divergent_before = amm::is_oracle_mark_too_divergent(
         oracle_mark_spread_pct_before,
-        &state.oracle_guard_rails.price_divergence,
+        &oracle_guard_rails.price_divergence,
     )?;
 
     let is_oracle_mark_too_divergent_after = amm::is_oracle_mark_too_divergent(
         oracle_mark_spread_pct_after,
-        &state.oracle_guard_rails.price_divergence,
+        &oracle_guard_rails.price_divergence,
     )?;
 
     // if oracle-mark divergence pushed outside limit, block order
@@ -454,11 +931,21 @@ pub fn fill_order(
         &markets
             .load()
             .or(Err(ErrorCode::UnableToLoadAccountLoader))?,
+        Some(oracle),
+        remaining_accounts,
+        clock_slot,
     )?;
     if !meets_initial_maintenance_requirement && potentially_risk_increasing {
         return Err(ErrorCode::InsufficientCollateral);
     }
 
+    // Each twap slice is rewarded from the time it became available rather than from placement
+    let order_ts = if order.order_type == OrderType::Twap {
+        calculate_twap_next_slice_ts(order)?
+    } else {
+        order.ts
+    };
+
     let discount_tier = order.discount_tier;
     let (user_fee, fee_to_market, token_discount, filler_reward, referrer_reward, referee_discount) =
         fees::calculate_fee_for_limit_order(
@@ -466,7 +953,7 @@ pub fn fill_order(
             &state.fee_structure,
             &order_state.order_filler_reward_structure,
             &discount_tier,
-            order.ts,
+            order_ts,
             now,
             &referrer,
             filler.key() == user.key(),
@@ -507,21 +994,22 @@ pub fn fill_order(
         .checked_add(referee_discount)
         .ok_or_else(math_error!())?;
 
-    filler.collateral = filler
+    let filler_collateral = filler
         .collateral
         .checked_add(cast(filler_reward)?)
         .ok_or_else(math_error!())?;
 
     // Update the referrer's collateral with their reward
-    if let Some(mut referrer) = referrer {
-        referrer.total_referral_reward = referrer
-            .total_referral_reward
-            .checked_add(referrer_reward)
-            .ok_or_else(math_error!())?;
-        referrer
-            .exit(&crate::ID)
-            .or(Err(ErrorCode::UnableToWriteToRemainingAccount))?;
-    }
+    let referrer = match referrer {
+        Some(mut referrer) => {
+            referrer.total_referral_reward = referrer
+                .total_referral_reward
+                .checked_add(referrer_reward)
+                .ok_or_else(math_error!())?;
+            Some(referrer)
+        }
+        None => None,
+    };
 
     {
         let markets = &mut markets
@@ -537,9 +1025,51 @@ pub fn fill_order(
         )?;
     }
 
+    if order.order_type == OrderType::Twap {
+        order.twap_last_slice_ts = now;
+    }
+
+    // Try to update the funding rate at the end of every trade
+    {
+        let markets = &mut markets
+            .load_mut()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+        let market = markets.get_market_mut(market_index);
+        let backup_oracle = get_backup_oracle(&market.amm, remaining_accounts);
+        let funding_rate_history = &mut funding_rate_history
+            .load_mut()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+        controller::funding::update_funding_rate(
+            market_index,
+            market,
+            oracle,
+            backup_oracle,
+            now,
+            clock_slot,
+            funding_rate_history,
+            &state.oracle_guard_rails,
+            &state.extended_oracle_guard_rails,
+            state.funding_paused,
+            state.oracle_circuit_breaker_threshold,
+            Some(mark_price_before),
+        )?;
+    }
+
     let trade_history_account = &mut trade_history
         .load_mut()
         .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    let order_history_account = &mut order_history
+        .load_mut()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    if let Some(referrer) = referrer {
+        referrer
+            .exit(&crate::ID)
+            .or(Err(ErrorCode::UnableToWriteToRemainingAccount))?;
+    }
+
+    filler.collateral = filler_collateral;
+
     let trade_record_id = trade_history_account.next_record_id();
     trade_history_account.append(TradeRecord {
         ts: now,
@@ -560,9 +1090,6 @@ pub fn fill_order(
         oracle_price: oracle_price_after,
     });
 
-    let order_history_account = &mut order_history
-        .load_mut()
-        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
     let record_id = order_history_account.next_record_id();
     order_history_account.append(OrderRecord {
         ts: now,
@@ -580,41 +1107,876 @@ pub fn fill_order(
         padding: [0; 10],
     });
 
+    let group_id = order.group_id;
+    let order_id = order.order_id;
+
     // Cant reset order until after its been logged in order history
     if order.base_asset_amount == order.base_asset_amount_filled
         || order.order_type == OrderType::Market
     {
         *order = Order::default();
-        let position_index = get_position_index(user_positions, market_index)?;
-        let market_position = &mut user_positions.positions[position_index];
-        market_position.open_orders -= 1;
+        user_positions.positions[position_index].open_orders -= 1;
     }
 
-    // Try to update the funding rate at the end of every trade
+    cancel_order_group_siblings(
+        group_id,
+        order_id,
+        user.key(),
+        user.authority,
+        user_orders,
+        &mut user_positions.positions[position_index],
+        order_history_account,
+        filler.key(),
+        trade_record_id,
+        now,
+    );
+
+    cancel_reduce_only_orders_if_position_closed(
+        market_index,
+        user.key(),
+        user.authority,
+        user_orders,
+        &mut user_positions.positions[position_index],
+        order_history_account,
+        filler.key(),
+        trade_record_id,
+        now,
+    );
+
+    Ok((base_asset_amount, false))
+}
+
+// Fills a batch of orders sequentially. Each order's user, user positions and user orders
+// accounts lead the remaining accounts in the same order as order_ids, followed by any referrer
+// and oracle accounts. An order that can't be filled is logged and skipped.
+pub fn fill_orders<'info>(
+    order_ids: &[u128],
+    state: &State,
+    order_state: &OrderState,
+    markets: &AccountLoader<Markets>,
+    oracle: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    filler: &mut Box<Account<User>>,
+    funding_payment_history: &AccountLoader<FundingPaymentHistory>,
+    trade_history: &AccountLoader<TradeHistory>,
+    order_history: &AccountLoader<OrderHistory>,
+    funding_rate_history: &AccountLoader<FundingRateHistory>,
+    clock: &Clock,
+) -> ClearingHouseResult<u128> {
+    let user_accounts_len = order_ids.len().checked_mul(3).ok_or_else(math_error!())?;
+    if remaining_accounts.len() < user_accounts_len {
+        return Err(ErrorCode::InvalidFillOrdersAccounts);
+    }
+    let user_account_infos = &remaining_accounts[..user_accounts_len];
+
+    let filler_collateral_before = filler.collateral;
+    let mut orders_filled: u64 = 0;
+    for (order_id, user_account_infos) in order_ids.iter().zip(user_account_infos.chunks(3)) {
+        match fill_order_in_batch(
+            *order_id,
+            state,
+            order_state,
+            user_account_infos,
+            markets,
+            oracle,
+            remaining_accounts,
+            filler,
+            funding_payment_history,
+            trade_history,
+            order_history,
+            funding_rate_history,
+            clock,
+        ) {
+            Ok((_, true)) => msg!("Cancelled order {}", order_id),
+            Ok((0, _)) => msg!("Could not fill order {}", order_id),
+            Ok(_) => orders_filled += 1,
+            Err(error) => msg!("Could not fill order {}: {}", order_id, error),
+        }
+    }
+
+    let filler_reward = cast(
+        filler
+            .collateral
+            .checked_sub(filler_collateral_before)
+            .ok_or_else(math_error!())?,
+    )?;
+    msg!(
+        "Filled {} of {} orders for a filler reward of {}",
+        orders_filled,
+        order_ids.len(),
+        filler_reward
+    );
+
+    Ok(filler_reward)
+}
+
+fn fill_order_in_batch<'info>(
+    order_id: u128,
+    state: &State,
+    order_state: &OrderState,
+    user_account_infos: &[AccountInfo<'info>],
+    markets: &AccountLoader<Markets>,
+    oracle: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    filler: &mut Box<Account<User>>,
+    funding_payment_history: &AccountLoader<FundingPaymentHistory>,
+    trade_history: &AccountLoader<TradeHistory>,
+    order_history: &AccountLoader<OrderHistory>,
+    funding_rate_history: &AccountLoader<FundingRateHistory>,
+    clock: &Clock,
+) -> ClearingHouseResult<(u128, bool)> {
+    let (mut user, user_positions, user_orders) =
+        get_user_accounts_for_fill_orders(user_account_infos)?;
+
+    // The filler is written back once at the end of the instruction, which would clobber the
+    // user account if they were the same
+    if user.key() == filler.key() {
+        return Err(ErrorCode::InvalidFillOrdersAccounts);
+    }
+
+    let referrer =
+        get_referrer_for_fill_orders(remaining_accounts, &user.key(), order_id, &user_orders)?;
+
+    let market_index = user_orders
+        .load_orders()?
+        .orders
+        .iter()
+        .find(|order| order.order_id == order_id)
+        .ok_or(ErrorCode::OrderDoesNotExist)?
+        .market_index;
+
+    // Settle funding and persist it first, so a failed fill only has to undo the trade
     {
-        let markets = &mut markets
+        let user_positions = &mut user_positions
             .load_mut()
             .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
-        let market = markets.get_market_mut(market_index);
-        let funding_rate_history = &mut funding_rate_history
+        let funding_payment_history = &mut funding_payment_history
             .load_mut()
             .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
-        controller::funding::update_funding_rate(
-            market_index,
-            market,
-            oracle,
+        let markets = &markets
+            .load()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+        controller::funding::settle_funding_payment(
+            &mut user,
+            user_positions,
+            markets,
+            funding_payment_history,
+            clock.unix_timestamp,
+        )?;
+    }
+    user.exit(&crate::ID)
+        .or(Err(ErrorCode::UnableToWriteToRemainingAccount))?;
+
+    // Snapshot everything fill_order can write before returning an error, so a failed fill
+    // leaves the batch as if it was never attempted. fill_order only touches the user's orders in
+    // the order's market and appends at most one funding rate record
+    let market_before = Box::new(
+        *markets
+            .load()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?
+            .get_market(market_index),
+    );
+    let user_positions_before = Box::new(
+        *user_positions
+            .load()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?,
+    );
+    let market_orders_before: Vec<(usize, Order)> = user_orders
+        .load_orders()?
+        .orders
+        .iter()
+        .enumerate()
+        .filter(|(_, order)| order.market_index == market_index)
+        .map(|(index, order)| (index, *order))
+        .collect();
+    let funding_rate_history_head_before = funding_rate_history
+        .load()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?
+        .snapshot_head();
+    let referrer_before = match &referrer {
+        Some(referrer) => {
+            let referrer_account_info = referrer.to_account_info();
+            let data = referrer_account_info
+                .try_borrow_data()
+                .or(Err(ErrorCode::UnableToLoadAccountLoader))?
+                .to_vec();
+            Some((referrer_account_info, data))
+        }
+        None => None,
+    };
+    let filler_collateral_before = filler.collateral;
+
+    let result = fill_order(
+        order_id,
+        state,
+        order_state,
+        &mut user,
+        &user_positions,
+        markets,
+        oracle,
+        remaining_accounts,
+        &user_orders,
+        filler,
+        funding_payment_history,
+        trade_history,
+        order_history,
+        funding_rate_history,
+        referrer,
+        clock,
+    );
+
+    match result {
+        Ok(fill) => {
+            user.exit(&crate::ID)
+                .or(Err(ErrorCode::UnableToWriteToRemainingAccount))?;
+            Ok(fill)
+        }
+        Err(error) => {
+            *markets
+                .load_mut()
+                .or(Err(ErrorCode::UnableToLoadAccountLoader))?
+                .get_market_mut(market_index) = *market_before;
+            *user_positions
+                .load_mut()
+                .or(Err(ErrorCode::UnableToLoadAccountLoader))? = *user_positions_before;
+            {
+                let user_orders = &mut user_orders.load_orders_mut()?;
+                for (index, order) in market_orders_before {
+                    user_orders.orders[index] = order;
+                }
+            }
+            funding_rate_history
+                .load_mut()
+                .or(Err(ErrorCode::UnableToLoadAccountLoader))?
+                .restore_head(funding_rate_history_head_before);
+            if let Some((referrer_account_info, data)) = referrer_before {
+                referrer_account_info
+                    .try_borrow_mut_data()
+                    .or(Err(ErrorCode::UnableToWriteToRemainingAccount))?
+                    .copy_from_slice(&data);
+            }
+            filler.collateral = filler_collateral_before;
+            Err(error)
+        }
+    }
+}
+
+// Fills a taker limit order against a crossing maker limit order at the maker's price, without
+// trading against the amm
+pub fn match_orders<'info>(
+    taker_order_id: u128,
+    maker_order_id: u128,
+    state: &State,
+    order_state: &OrderState,
+    taker: &mut Box<Account<User>>,
+    taker_positions: &AccountLoader<UserPositions>,
+    taker_orders: &AccountLoader<UserOrders>,
+    maker: &mut Box<Account<User>>,
+    maker_positions: &AccountLoader<UserPositions>,
+    maker_orders: &AccountLoader<UserOrders>,
+    markets: &AccountLoader<Markets>,
+    oracle: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    filler: &mut Box<Account<User>>,
+    funding_payment_history: &AccountLoader<FundingPaymentHistory>,
+    trade_history: &AccountLoader<TradeHistory>,
+    order_history: &AccountLoader<OrderHistory>,
+    taker_referrer: Option<Account<User>>,
+    clock: &Clock,
+) -> ClearingHouseResult<u128> {
+    let now = clock.unix_timestamp;
+    let clock_slot = clock.slot;
+
+    if taker.key() == maker.key() {
+        msg!("Cant match orders from the same user");
+        return Err(ErrorCode::InvalidOrderMatch);
+    }
+
+    let taker_positions = &mut taker_positions
+        .load_mut()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    let maker_positions = &mut maker_positions
+        .load_mut()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    {
+        let funding_payment_history = &mut funding_payment_history
+            .load_mut()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+        let markets = &markets
+            .load()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+        controller::funding::settle_funding_payment(
+            taker,
+            taker_positions,
+            markets,
+            funding_payment_history,
             now,
+        )?;
+        controller::funding::settle_funding_payment(
+            maker,
+            maker_positions,
+            markets,
+            funding_payment_history,
+            now,
+        )?;
+    }
+
+    let taker_orders = &mut taker_orders.load_orders_mut()?;
+    let taker_order_index = taker_orders
+        .orders
+        .iter()
+        .position(|order| order.order_id == taker_order_id)
+        .ok_or_else(print_error!(ErrorCode::OrderDoesNotExist))?;
+    let taker_order = &mut taker_orders.orders[taker_order_index];
+
+    let maker_orders = &mut maker_orders.load_orders_mut()?;
+    let maker_order_index = maker_orders
+        .orders
+        .iter()
+        .position(|order| order.order_id == maker_order_id)
+        .ok_or_else(print_error!(ErrorCode::OrderDoesNotExist))?;
+    let maker_order = &mut maker_orders.orders[maker_order_index];
+
+    if taker_order.status != OrderStatus::Open || maker_order.status != OrderStatus::Open {
+        return Err(ErrorCode::OrderNotOpen);
+    }
+
+    if (taker_order.max_ts != 0 && now > taker_order.max_ts)
+        || (maker_order.max_ts != 0 && now > maker_order.max_ts)
+    {
+        msg!("Cant match expired order");
+        return Err(ErrorCode::InvalidOrderMatch);
+    }
+
+    if taker_order.order_type != OrderType::Limit || maker_order.order_type != OrderType::Limit {
+        msg!("Only limit orders can be matched");
+        return Err(ErrorCode::InvalidOrderMatch);
+    }
+
+    if taker_order.post_only {
+        msg!("Post only order cant be the taker");
+        return Err(ErrorCode::InvalidOrderMatch);
+    }
+
+    // Order ids only ever increase, so the order that was resting first is always the maker and
+    // the filler cant pick which side's price the orders trade at
+    if maker_order.order_id > taker_order.order_id {
+        msg!("Maker order must be placed before the taker order");
+        return Err(ErrorCode::InvalidOrderMatch);
+    }
+
+    if taker_order.market_index != maker_order.market_index
+        || taker_order.direction == maker_order.direction
+    {
+        msg!("Matched orders must be on opposite sides of the same market");
+        return Err(ErrorCode::InvalidOrderMatch);
+    }
+
+    let market_index = taker_order.market_index;
+    let mark_price: u128;
+    let oracle_price: i128;
+    let is_oracle_valid: bool;
+    let minimum_base_asset_trade_size: u128;
+    {
+        let markets = &markets
+            .load()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+        let market = markets.get_market(market_index);
+
+        if !market.amm.is_market_oracle(oracle.key) {
+            return Err(ErrorCode::InvalidOracle);
+        }
+
+        mark_price = market.amm.mark_price()?;
+        let backup_oracle = get_backup_oracle(&market.amm, remaining_accounts);
+        let (oracle_price_data, oracle_is_valid) = get_oracle_price_data(
+            &market.amm,
+            oracle,
+            backup_oracle,
             clock_slot,
-            funding_rate_history,
-            &state.oracle_guard_rails,
-            state.funding_paused,
-            Some(mark_price_before),
+            &market
+                .get_oracle_guard_rails(&state.oracle_guard_rails)
+                .validity,
+            &market.get_extended_oracle_guard_rails(&state.extended_oracle_guard_rails),
         )?;
+        oracle_price = oracle_price_data.price;
+        is_oracle_valid = oracle_is_valid;
+        minimum_base_asset_trade_size = market.amm.minimum_base_asset_trade_size;
     }
 
+    let valid_oracle_price = if is_oracle_valid {
+        Some(oracle_price)
+    } else {
+        None
+    };
+
+    let (taker_price, maker_price) = match (
+        calculate_limit_price(taker_order, valid_oracle_price)?,
+        calculate_limit_price(maker_order, valid_oracle_price)?,
+    ) {
+        (Some(taker_price), Some(maker_price)) => (taker_price, maker_price),
+        _ => {
+            msg!("Cant match oracle price offset order without valid oracle price");
+            return Err(ErrorCode::InvalidOrderMatch);
+        }
+    };
+
+    let orders_cross = match taker_order.direction {
+        PositionDirection::Long => taker_price >= maker_price,
+        PositionDirection::Short => taker_price <= maker_price,
+    };
+
+    if !orders_cross {
+        msg!("Orders do not cross");
+        return Err(ErrorCode::InvalidOrderMatch);
+    }
+
+    let taker_position_index = get_position_index(taker_positions, market_index)?;
+    let maker_position_index = get_position_index(maker_positions, market_index)?;
+
+    let mut base_asset_amount = min(
+        taker_order
+            .base_asset_amount
+            .checked_sub(taker_order.base_asset_amount_filled)
+            .ok_or_else(math_error!())?,
+        maker_order
+            .base_asset_amount
+            .checked_sub(maker_order.base_asset_amount_filled)
+            .ok_or_else(math_error!())?,
+    );
+
+    if let Some(base_asset_amount_to_position_limit) =
+        calculate_base_asset_amount_to_position_limit(
+            taker_order,
+            taker_positions.positions[taker_position_index].base_asset_amount,
+        )?
+    {
+        base_asset_amount = min(base_asset_amount, base_asset_amount_to_position_limit);
+    }
+
+    if let Some(base_asset_amount_to_position_limit) =
+        calculate_base_asset_amount_to_position_limit(
+            maker_order,
+            maker_positions.positions[maker_position_index].base_asset_amount,
+        )?
+    {
+        base_asset_amount = min(base_asset_amount, base_asset_amount_to_position_limit);
+    }
+
+    if let Some(base_asset_amount_to_close_position) = calculate_base_asset_amount_to_close_position(
+        taker_order,
+        taker_positions.positions[taker_position_index].base_asset_amount,
+    ) {
+        base_asset_amount = min(base_asset_amount, base_asset_amount_to_close_position);
+    }
+
+    if let Some(base_asset_amount_to_close_position) = calculate_base_asset_amount_to_close_position(
+        maker_order,
+        maker_positions.positions[maker_position_index].base_asset_amount,
+    ) {
+        base_asset_amount = min(base_asset_amount, base_asset_amount_to_close_position);
+    }
+
+    if base_asset_amount < minimum_base_asset_trade_size {
+        msg!("base asset amount too small {}", base_asset_amount);
+        return Err(ErrorCode::InvalidOrderMatch);
+    }
+
+    let quote_asset_amount = calculate_quote_asset_amount_at_price(base_asset_amount, maker_price)?;
+
+    let (taker_potentially_risk_increasing, taker_reduce_only) = {
+        let markets = &mut markets
+            .load_mut()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+        let market = markets.get_market_mut(market_index);
+        controller::position::update_position_with_base_asset_amount_at_price(
+            base_asset_amount,
+            quote_asset_amount,
+            taker_order.direction,
+            market,
+            taker,
+            &mut taker_positions.positions[taker_position_index],
+        )?
+    };
+
+    let (maker_potentially_risk_increasing, maker_reduce_only) = {
+        let markets = &mut markets
+            .load_mut()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+        let market = markets.get_market_mut(market_index);
+        controller::position::update_position_with_base_asset_amount_at_price(
+            base_asset_amount,
+            quote_asset_amount,
+            maker_order.direction,
+            market,
+            maker,
+            &mut maker_positions.positions[maker_position_index],
+        )?
+    };
+
+    if (!taker_reduce_only && taker_order.reduce_only)
+        || (!maker_reduce_only && maker_order.reduce_only)
+    {
+        return Err(ErrorCode::ReduceOnlyOrderIncreasedRisk);
+    }
+
+    {
+        let markets = &markets
+            .load()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+        // Order fails if it's risk increasing and the oracle circuit breaker has tripped
+        if (taker_potentially_risk_increasing || maker_potentially_risk_increasing)
+            && markets.get_market(market_index).reduce_only
+        {
+            return Err(ErrorCode::MarketReduceOnly);
+        }
+
+        // Order fails if it's risk increasing and it brings the user collateral below the
+        // initial margin requirement
+        if taker_potentially_risk_increasing
+            && !meets_initial_margin_requirement(
+                state,
+                taker,
+                taker_positions,
+                markets,
+                Some(oracle),
+                remaining_accounts,
+                clock_slot,
+            )?
+        {
+            return Err(ErrorCode::InsufficientCollateral);
+        }
+
+        if maker_potentially_risk_increasing
+            && !meets_initial_margin_requirement(
+                state,
+                maker,
+                maker_positions,
+                markets,
+                Some(oracle),
+                remaining_accounts,
+                clock_slot,
+            )?
+        {
+            return Err(ErrorCode::InsufficientCollateral);
+        }
+    }
+
+    // The taker pays what a fill against the amm would, referral included, while the maker pays
+    // nothing for the liquidity it adds
+    let filler_is_user = filler.key() == taker.key() || filler.key() == maker.key();
+    let (
+        taker_fee,
+        fee_to_market,
+        taker_token_discount,
+        filler_reward,
+        referrer_reward,
+        referee_discount,
+    ) = fees::calculate_fee_for_limit_order(
+        quote_asset_amount,
+        &state.fee_structure,
+        &order_state.order_filler_reward_structure,
+        &taker_order.discount_tier,
+        taker_order.ts,
+        now,
+        &taker_referrer,
+        filler_is_user,
+    )?;
+
+    // Increment the clearing house's total fee variables
+    {
+        let markets = &mut markets
+            .load_mut()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+        let market = markets.get_market_mut(market_index);
+        market.amm.total_fee = market
+            .amm
+            .total_fee
+            .checked_add(fee_to_market)
+            .ok_or_else(math_error!())?;
+        market.amm.total_fee_minus_distributions = market
+            .amm
+            .total_fee_minus_distributions
+            .checked_add(fee_to_market)
+            .ok_or_else(math_error!())?;
+    }
+
+    // Subtract the fee from the taker's collateral
+    taker.collateral = taker.collateral.saturating_sub(taker_fee);
+    taker.total_fee_paid = taker
+        .total_fee_paid
+        .checked_add(taker_fee)
+        .ok_or_else(math_error!())?;
+    taker.total_token_discount = taker
+        .total_token_discount
+        .checked_add(taker_token_discount)
+        .ok_or_else(math_error!())?;
+    taker.total_referee_discount = taker
+        .total_referee_discount
+        .checked_add(referee_discount)
+        .ok_or_else(math_error!())?;
+
+    filler.collateral = filler
+        .collateral
+        .checked_add(filler_reward)
+        .ok_or_else(math_error!())?;
+
+    // Update the referrer's collateral with their reward
+    if let Some(mut referrer) = taker_referrer {
+        referrer.total_referral_reward = referrer
+            .total_referral_reward
+            .checked_add(referrer_reward)
+            .ok_or_else(math_error!())?;
+        referrer
+            .exit(&crate::ID)
+            .or(Err(ErrorCode::UnableToWriteToRemainingAccount))?;
+    }
+
+    update_order_after_trade(
+        taker_order,
+        minimum_base_asset_trade_size,
+        base_asset_amount,
+        quote_asset_amount,
+        taker_fee,
+    )?;
+    update_order_after_trade(
+        maker_order,
+        minimum_base_asset_trade_size,
+        base_asset_amount,
+        quote_asset_amount,
+        0,
+    )?;
+
+    let trade_history_account = &mut trade_history
+        .load_mut()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    let taker_trade_record_id = trade_history_account.next_record_id();
+    trade_history_account.append(TradeRecord {
+        ts: now,
+        record_id: taker_trade_record_id,
+        user_authority: taker.authority,
+        user: taker.key(),
+        direction: taker_order.direction,
+        base_asset_amount,
+        quote_asset_amount,
+        mark_price_before: mark_price,
+        mark_price_after: mark_price,
+        fee: taker_fee,
+        token_discount: taker_token_discount,
+        referrer_reward,
+        referee_discount,
+        liquidation: false,
+        market_index,
+        oracle_price,
+    });
+    let maker_trade_record_id = trade_history_account.next_record_id();
+    trade_history_account.append(TradeRecord {
+        ts: now,
+        record_id: maker_trade_record_id,
+        user_authority: maker.authority,
+        user: maker.key(),
+        direction: maker_order.direction,
+        base_asset_amount,
+        quote_asset_amount,
+        mark_price_before: mark_price,
+        mark_price_after: mark_price,
+        fee: 0,
+        token_discount: 0,
+        referrer_reward: 0,
+        referee_discount: 0,
+        liquidation: false,
+        market_index,
+        oracle_price,
+    });
+
+    let order_history_account = &mut order_history
+        .load_mut()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    let record_id = order_history_account.next_record_id();
+    order_history_account.append(OrderRecord {
+        ts: now,
+        record_id,
+        order: *taker_order,
+        user: taker.key(),
+        authority: taker.authority,
+        action: OrderAction::Fill,
+        filler: filler.key(),
+        trade_record_id: taker_trade_record_id,
+        base_asset_amount_filled: base_asset_amount,
+        quote_asset_amount_filled: quote_asset_amount,
+        filler_reward,
+        fee: taker_fee,
+        padding: [0; 10],
+    });
+    let record_id = order_history_account.next_record_id();
+    order_history_account.append(OrderRecord {
+        ts: now,
+        record_id,
+        order: *maker_order,
+        user: maker.key(),
+        authority: maker.authority,
+        action: OrderAction::Fill,
+        filler: filler.key(),
+        trade_record_id: maker_trade_record_id,
+        base_asset_amount_filled: base_asset_amount,
+        quote_asset_amount_filled: quote_asset_amount,
+        filler_reward: 0,
+        fee: 0,
+        padding: [0; 10],
+    });
+
+    let taker_group_id = taker_order.group_id;
+    let maker_group_id = maker_order.group_id;
+
+    // Cant reset orders until after they have been logged in order history
+    if taker_order.base_asset_amount == taker_order.base_asset_amount_filled {
+        *taker_order = Order::default();
+        taker_positions.positions[taker_position_index].open_orders -= 1;
+    }
+
+    if maker_order.base_asset_amount == maker_order.base_asset_amount_filled {
+        *maker_order = Order::default();
+        maker_positions.positions[maker_position_index].open_orders -= 1;
+    }
+
+    cancel_order_group_siblings(
+        taker_group_id,
+        taker_order_id,
+        taker.key(),
+        taker.authority,
+        taker_orders,
+        &mut taker_positions.positions[taker_position_index],
+        order_history_account,
+        filler.key(),
+        taker_trade_record_id,
+        now,
+    );
+    cancel_order_group_siblings(
+        maker_group_id,
+        maker_order_id,
+        maker.key(),
+        maker.authority,
+        maker_orders,
+        &mut maker_positions.positions[maker_position_index],
+        order_history_account,
+        filler.key(),
+        maker_trade_record_id,
+        now,
+    );
+
+    cancel_reduce_only_orders_if_position_closed(
+        market_index,
+        taker.key(),
+        taker.authority,
+        taker_orders,
+        &mut taker_positions.positions[taker_position_index],
+        order_history_account,
+        filler.key(),
+        taker_trade_record_id,
+        now,
+    );
+    cancel_reduce_only_orders_if_position_closed(
+        market_index,
+        maker.key(),
+        maker.authority,
+        maker_orders,
+        &mut maker_positions.positions[maker_position_index],
+        order_history_account,
+        filler.key(),
+        maker_trade_record_id,
+        now,
+    );
+
     Ok(base_asset_amount)
 }
 
+// Any fill of a one cancels other order cancels the rest of its group, which is always in the
+// same market
+fn cancel_order_group_siblings(
+    group_id: u128,
+    order_id: u128,
+    user: Pubkey,
+    authority: Pubkey,
+    user_orders: &mut UserOrdersRefMut,
+    market_position: &mut MarketPosition,
+    order_history_account: &mut OrderHistory,
+    filler: Pubkey,
+    trade_record_id: u128,
+    now: i64,
+) {
+    if group_id == 0 {
+        return;
+    }
+
+    for sibling in user_orders.orders.iter_mut().filter(|sibling| {
+        sibling.status == OrderStatus::Open
+            && sibling.group_id == group_id
+            && sibling.order_id != order_id
+    }) {
+        let record_id = order_history_account.next_record_id();
+        order_history_account.append(OrderRecord {
+            ts: now,
+            record_id,
+            order: *sibling,
+            user,
+            authority,
+            action: OrderAction::Cancel,
+            filler,
+            trade_record_id,
+            base_asset_amount_filled: 0,
+            quote_asset_amount_filled: 0,
+            filler_reward: 0,
+            fee: 0,
+            padding: [0; 10],
+        });
+
+        market_position.open_orders -= 1;
+        *sibling = Order::default();
+    }
+}
+
+// Reduce only orders have nothing left to do once the position they were reducing is closed
+fn cancel_reduce_only_orders_if_position_closed(
+    market_index: u64,
+    user: Pubkey,
+    authority: Pubkey,
+    user_orders: &mut UserOrdersRefMut,
+    market_position: &mut MarketPosition,
+    order_history_account: &mut OrderHistory,
+    filler: Pubkey,
+    trade_record_id: u128,
+    now: i64,
+) {
+    if market_position.base_asset_amount != 0 {
+        return;
+    }
+
+    for order in user_orders.orders.iter_mut().filter(|order| {
+        order.status == OrderStatus::Open && order.market_index == market_index && order.reduce_only
+    }) {
+        let record_id = order_history_account.next_record_id();
+        order_history_account.append(OrderRecord {
+            ts: now,
+            record_id,
+            order: *order,
+            user,
+            authority,
+            action: OrderAction::ReduceOnlyCancel,
+            filler,
+            trade_record_id,
+            base_asset_amount_filled: 0,
+            quote_asset_amount_filled: 0,
+            filler_reward: 0,
+            fee: 0,
+            padding: [0; 10],
+        });
+
+        market_position.open_orders -= 1;
+        *order = Order::default();
+    }
+}
+
 pub fn execute_order(
     state: &State,
     user: &mut User,
@@ -663,6 +2025,15 @@ pub fn execute_market_order(
     let market_position = &mut user_positions.positions[position_index];
     let market = markets.get_market_mut(market_index);
 
+    if order.base_asset_amount > 0 {
+        if let Some(base_asset_amount_to_close_position) =
+            calculate_base_asset_amount_to_close_position(order, market_position.base_asset_amount)
+        {
+            order.base_asset_amount =
+                min(order.base_asset_amount, base_asset_amount_to_close_position);
+        }
+    }
+
     let (potentially_risk_increasing, reduce_only, base_asset_amount, quote_asset_amount) =
         if order.base_asset_amount > 0 {
             controller::position::update_position_with_base_asset_amount(
@@ -762,6 +2133,31 @@ pub fn execute_non_market_order(
         return Ok((0, 0, false));
     }
 
+    let position_index = get_position_index(user_positions, market_index)?;
+
+    // Once a fill takes the position to its limit the order has nothing left to do, so it is
+    // shrunk to what has been filled. An order whose position is already at its limit is left as
+    // is, since it can fill again once the position moves back
+    if let Some(base_asset_amount_to_position_limit) =
+        calculate_base_asset_amount_to_position_limit(
+            order,
+            user_positions.positions[position_index].base_asset_amount,
+        )?
+    {
+        if base_asset_amount_to_position_limit == 0 {
+            msg!("Position is already at the order's position limit");
+            return Ok((0, 0, false));
+        }
+
+        if base_asset_amount >= base_asset_amount_to_position_limit {
+            base_asset_amount = base_asset_amount_to_position_limit;
+            order.base_asset_amount = order
+                .base_asset_amount_filled
+                .checked_add(base_asset_amount)
+                .ok_or_else(math_error!())?;
+        }
+    }
+
     let minimum_base_asset_trade_size = market.amm.minimum_base_asset_trade_size;
     let base_asset_amount_left_to_fill = order
         .base_asset_amount
@@ -781,11 +2177,25 @@ pub fn execute_non_market_order(
             .ok_or_else(math_error!())?;
     }
 
+    // A reduce only order is shrunk to the position when it would fill past it, so closing the
+    // position completes the order
+    if let Some(base_asset_amount_to_close_position) = calculate_base_asset_amount_to_close_position(
+        order,
+        user_positions.positions[position_index].base_asset_amount,
+    ) {
+        if base_asset_amount >= base_asset_amount_to_close_position {
+            base_asset_amount = base_asset_amount_to_close_position;
+            order.base_asset_amount = order
+                .base_asset_amount_filled
+                .checked_add(base_asset_amount)
+                .ok_or_else(math_error!())?;
+        }
+    }
+
     if base_asset_amount == 0 {
         return Ok((0, 0, false));
     }
 
-    let position_index = get_position_index(user_positions, market_index)?;
     let market_position = &mut user_positions.positions[position_index];
 
     let (potentially_risk_increasing, reduce_only, _, quote_asset_amount) =
@@ -152,19 +152,6 @@ pub fn increase_with_base_asset_amount(
         return Ok(0);
     }
 
-    // Update funding rate if this is a new position
-    if market_position.base_asset_amount == 0 {
-        market_position.last_cumulative_funding_rate = match direction {
-            PositionDirection::Long => market.amm.cumulative_funding_rate_long,
-            PositionDirection::Short => market.amm.cumulative_funding_rate_short,
-        };
-
-        market.open_interest = market
-            .open_interest
-            .checked_add(1)
-            .ok_or_else(math_error!())?;
-    }
-
     let swap_direction = match direction {
         PositionDirection::Long => SwapDirection::Remove,
         PositionDirection::Short => SwapDirection::Add,
@@ -178,9 +165,42 @@ pub fn increase_with_base_asset_amount(
         None,
     )?;
 
+    update_position_for_increase(
+        direction,
+        base_asset_amount,
+        quote_asset_swapped,
+        market,
+        market_position,
+    )?;
+
+    Ok(quote_asset_swapped)
+}
+
+// Books an increase the position has already been priced for, whether against the amm or
+// another user
+fn update_position_for_increase(
+    direction: PositionDirection,
+    base_asset_amount: u128,
+    quote_asset_amount: u128,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+) -> ClearingHouseResult {
+    // Update funding rate if this is a new position
+    if market_position.base_asset_amount == 0 {
+        market_position.last_cumulative_funding_rate = match direction {
+            PositionDirection::Long => market.amm.cumulative_funding_rate_long,
+            PositionDirection::Short => market.amm.cumulative_funding_rate_short,
+        };
+
+        market.open_interest = market
+            .open_interest
+            .checked_add(1)
+            .ok_or_else(math_error!())?;
+    }
+
     market_position.quote_asset_amount = market_position
         .quote_asset_amount
-        .checked_add(quote_asset_swapped)
+        .checked_add(quote_asset_amount)
         .ok_or_else(math_error!())?;
 
     let base_asset_amount = match direction {
@@ -209,7 +229,7 @@ pub fn increase_with_base_asset_amount(
             .ok_or_else(math_error!())?;
     }
 
-    Ok(quote_asset_swapped)
+    Ok(())
 }
 
 pub fn reduce(
@@ -314,6 +334,27 @@ pub fn reduce_with_base_asset_amount(
         None,
     )?;
 
+    update_position_for_reduce(
+        direction,
+        base_asset_amount,
+        quote_asset_swapped,
+        user,
+        market,
+        market_position,
+    )?;
+
+    Ok(quote_asset_swapped)
+}
+
+// Books a reduction the position has already been priced for and realizes its pnl
+fn update_position_for_reduce(
+    direction: PositionDirection,
+    base_asset_amount: u128,
+    quote_asset_amount: u128,
+    user: &mut User,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+) -> ClearingHouseResult {
     let base_asset_amount = match direction {
         PositionDirection::Long => cast_to_i128(base_asset_amount)?,
         PositionDirection::Short => -cast_to_i128(base_asset_amount)?,
@@ -364,18 +405,18 @@ pub fn reduce_with_base_asset_amount(
         .ok_or_else(math_error!())?;
 
     let pnl = if PositionDirection::Short == direction {
-        cast_to_i128(quote_asset_swapped)?
+        cast_to_i128(quote_asset_amount)?
             .checked_sub(cast(initial_quote_asset_amount_closed)?)
             .ok_or_else(math_error!())?
     } else {
         cast_to_i128(initial_quote_asset_amount_closed)?
-            .checked_sub(cast(quote_asset_swapped)?)
+            .checked_sub(cast(quote_asset_amount)?)
             .ok_or_else(math_error!())?
     };
 
     user.collateral = calculate_updated_collateral(user.collateral, pnl)?;
 
-    Ok(quote_asset_swapped)
+    Ok(())
 }
 
 pub fn close(
@@ -403,6 +444,26 @@ pub fn close(
         now,
         precomputed_mark_price,
     )?;
+
+    let base_asset_amount =
+        update_position_for_close(base_asset_value, user, market, market_position)?;
+
+    Ok((base_asset_value, base_asset_amount))
+}
+
+// Books closing the whole position at an exit value that has already been priced
+fn update_position_for_close(
+    base_asset_value: u128,
+    user: &mut User,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+) -> ClearingHouseResult<i128> {
+    let swap_direction = if market_position.base_asset_amount > 0 {
+        SwapDirection::Add
+    } else {
+        SwapDirection::Remove
+    };
+
     let pnl = calculate_pnl(
         base_asset_value,
         market_position.quote_asset_amount,
@@ -440,7 +501,7 @@ pub fn close(
     let base_asset_amount = market_position.base_asset_amount;
     market_position.base_asset_amount = 0;
 
-    Ok((base_asset_value, base_asset_amount))
+    Ok(base_asset_amount)
 }
 
 pub fn update_position_with_base_asset_amount(
@@ -525,6 +586,78 @@ pub fn update_position_with_base_asset_amount(
     ))
 }
 
+// Matched orders trade with each other at an agreed quote asset amount, so the amm is left untouched
+pub fn update_position_with_base_asset_amount_at_price(
+    base_asset_amount: u128,
+    quote_asset_amount: u128,
+    direction: PositionDirection,
+    market: &mut Market,
+    user: &mut User,
+    market_position: &mut MarketPosition,
+) -> ClearingHouseResult<(bool, bool)> {
+    let mut potentially_risk_increasing = true;
+    let mut reduce_only = false;
+
+    let increase_position = market_position.base_asset_amount == 0
+        || market_position.base_asset_amount > 0 && direction == PositionDirection::Long
+        || market_position.base_asset_amount < 0 && direction == PositionDirection::Short;
+    if increase_position {
+        update_position_for_increase(
+            direction,
+            base_asset_amount,
+            quote_asset_amount,
+            market,
+            market_position,
+        )?;
+    } else if market_position.base_asset_amount.unsigned_abs() > base_asset_amount {
+        update_position_for_reduce(
+            direction,
+            base_asset_amount,
+            quote_asset_amount,
+            user,
+            market,
+            market_position,
+        )?;
+
+        reduce_only = true;
+        potentially_risk_increasing = false;
+    } else {
+        let base_asset_amount_closed = market_position.base_asset_amount.unsigned_abs();
+        let base_asset_amount_after_close = base_asset_amount
+            .checked_sub(base_asset_amount_closed)
+            .ok_or_else(math_error!())?;
+
+        if base_asset_amount_after_close < base_asset_amount_closed {
+            potentially_risk_increasing = false;
+        }
+
+        // the quote asset amount is split pro rata between closing and reopening
+        let quote_asset_amount_closed = quote_asset_amount
+            .checked_mul(base_asset_amount_closed)
+            .ok_or_else(math_error!())?
+            .checked_div(base_asset_amount)
+            .ok_or_else(math_error!())?;
+
+        update_position_for_close(quote_asset_amount_closed, user, market, market_position)?;
+
+        if base_asset_amount_after_close > 0 {
+            update_position_for_increase(
+                direction,
+                base_asset_amount_after_close,
+                quote_asset_amount
+                    .checked_sub(quote_asset_amount_closed)
+                    .ok_or_else(math_error!())?,
+                market,
+                market_position,
+            )?;
+        } else {
+            reduce_only = true;
+        }
+    }
+
+    Ok((potentially_risk_increasing, reduce_only))
+}
+
 pub fn update_position_with_quote_asset_amount(
     quote_asset_amount: u128,
     direction: PositionDirection,
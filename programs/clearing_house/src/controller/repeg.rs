@@ -1,5 +1,5 @@
 use crate::error::*;
-use crate::math::{amm, repeg};
+use crate::math::{amm, oracle, repeg};
 
 use crate::math::constants::{
     SHARE_OF_FEES_ALLOCATED_TO_CLEARING_HOUSE_DENOMINATOR,
@@ -8,7 +8,7 @@ use crate::math::constants::{
 use crate::math_error;
 use crate::state::market::Market;
 
-use crate::state::state::OracleGuardRails;
+use crate::state::state::{ExtendedOracleGuardRails, OracleGuardRails};
 
 use crate::math::casting::cast_to_u128;
 use anchor_lang::prelude::AccountInfo;
@@ -17,9 +17,11 @@ use solana_program::msg;
 pub fn repeg(
     market: &mut Market,
     price_oracle: &AccountInfo,
+    backup_price_oracle: Option<&AccountInfo>,
     new_peg_candidate: u128,
     clock_slot: u64,
     oracle_guard_rails: &OracleGuardRails,
+    extended_oracle_guard_rails: &ExtendedOracleGuardRails,
 ) -> ClearingHouseResult<i128> {
     if new_peg_candidate == market.amm.peg_multiplier {
         return Err(ErrorCode::InvalidRepegRedundant);
@@ -29,10 +31,19 @@ pub fn repeg(
 
     let adjustment_cost = repeg::adjust_peg_cost(market, new_peg_candidate)?;
 
-    let oracle_price_data = &market.amm.get_oracle_price(price_oracle, clock_slot)?;
+    let oracle_guard_rails = &market.get_oracle_guard_rails(oracle_guard_rails);
+    let extended_oracle_guard_rails =
+        &market.get_extended_oracle_guard_rails(extended_oracle_guard_rails);
+    let (oracle_price_data, oracle_is_valid) = oracle::get_oracle_price_data(
+        &market.amm,
+        price_oracle,
+        backup_price_oracle,
+        clock_slot,
+        &oracle_guard_rails.validity,
+        extended_oracle_guard_rails,
+    )?;
     let oracle_price = oracle_price_data.price;
     let oracle_conf = oracle_price_data.confidence;
-    let oracle_is_valid = amm::is_oracle_valid(oracle_price_data, &oracle_guard_rails.validity)?;
 
     // if oracle is valid: check on size/direction of repeg
     if oracle_is_valid {
@@ -118,6 +118,32 @@ pub enum ErrorCode {
     UserOrderIdAlreadyInUse,
     #[msg("No positions liquidatable")]
     NoPositionsLiquidatable,
+    #[msg("Invalid oracle basket")]
+    InvalidOracleBasket,
+    #[msg("Oracle history already initialized")]
+    OracleHistoryAlreadyInitialized,
+    #[msg("Market is reduce only")]
+    MarketReduceOnly,
+    #[msg("Immediate or cancel and fill or kill orders must be in place and fill")]
+    ImmediateOrderMustBeInPlaceAndFill,
+    #[msg("Fill or kill order was not completely filled")]
+    FillOrKillOrderNotFilled,
+    #[msg("Order has not expired")]
+    OrderNotExpired,
+    #[msg("Invalid one cancels other order group")]
+    InvalidOrderGroup,
+    #[msg("Orders can not be matched")]
+    InvalidOrderMatch,
+    #[msg("Fill orders expects a user, user positions and user orders account per order")]
+    InvalidFillOrdersAccounts,
+    #[msg("Invalid user orders account")]
+    InvalidUserOrdersAccount,
+    #[msg("User orders account doesnt have room for the open orders")]
+    UserOrdersCapacityTooSmall,
+    #[msg("Invalid order history account")]
+    InvalidOrderHistoryAccount,
+    #[msg("Backup oracle not found")]
+    BackupOracleNotFound,
 }
 
 #[macro_export]
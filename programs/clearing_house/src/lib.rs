@@ -5,11 +5,20 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use context::*;
 use controller::position::{add_new_position, get_position_index, PositionDirection};
 use error::*;
-use math::{amm, bn, constants::*, fees, margin::*, orders::*, withdrawal::*};
+use math::{
+    amm, bn,
+    constants::*,
+    fees,
+    margin::*,
+    oracle::{get_oracle_price_data, get_oracle_status},
+    orders::*,
+    withdrawal::*,
+};
 
 use crate::state::{
     history::trade::TradeRecord,
-    market::{Market, Markets, OracleSource, AMM},
+    market::{Market, MarketOracleGuardRails, Markets, OracleSource, AMM},
+    oracle_basket::{OracleBasketComponent, OracleBasketType, MAX_ORACLE_BASKET_COMPONENTS},
     order_state::*,
     state::*,
     user::{MarketPosition, User, UserPositions},
@@ -33,10 +42,14 @@ declare_id!("AsW7LnXB9UA1uec9wi9MctYTgTz7YH9snhxd16GsFaGX");
 #[program]
 pub mod clearing_house {
     use crate::math;
-    use crate::optional_accounts::{get_discount_token, get_referrer, get_referrer_for_fill_order};
+    use crate::optional_accounts::{
+        get_backup_oracle, get_discount_token, get_referrer, get_referrer_for_fill_order,
+        get_referrer_for_fill_orders,
+    };
     use crate::state::history::curve::ExtendedCurveRecord;
     use crate::state::history::deposit::{DepositDirection, DepositRecord};
     use crate::state::history::liquidation::LiquidationRecord;
+    use crate::state::history::order_history::load_legacy_order_history;
 
     use super::*;
     use crate::math::amm::{
@@ -158,10 +171,15 @@ pub mod clearing_house {
             },
             order_state: Pubkey::default(),
             extended_curve_history: Pubkey::default(),
-            padding0: 0,
+            oracle_history: Pubkey::default(),
+            oracle_circuit_breaker_threshold: 0,
+            extended_oracle_guard_rails: ExtendedOracleGuardRails {
+                require_trading_status: true,
+                min_publishers: 0,
+                use_confidence_interval_for_margin: false,
+            },
+            padding0: [0; 2],
             padding1: 0,
-            padding2: 0,
-            padding3: 0,
         };
 
         Ok(())
@@ -226,12 +244,26 @@ pub mod clearing_house {
                 time_based_reward_lower_bound: 10_000, // 1 cent
             },
             min_order_quote_asset_amount: 500_000, // 50 cents
-            padding: [0; 10],
+            expire_order_reward: 10_000,           // 1 cent
+            padding: [0; 9],
         };
 
         Ok(())
     }
 
+    pub fn initialize_oracle_history(ctx: Context<InitializeOracleHistory>) -> ProgramResult {
+        let state = &mut ctx.accounts.state;
+
+        if !state.oracle_history.eq(&Pubkey::default()) {
+            return Err(ErrorCode::OracleHistoryAlreadyInitialized.into());
+        }
+
+        ctx.accounts.oracle_history.load_init()?;
+        state.oracle_history = ctx.accounts.oracle_history.key();
+
+        Ok(())
+    }
+
     pub fn initialize_market(
         ctx: Context<InitializeMarket>,
         market_index: u64,
@@ -239,6 +271,7 @@ pub mod clearing_house {
         amm_quote_asset_reserve: u128,
         amm_periodicity: i64,
         amm_peg_multiplier: u128,
+        oracle_source: OracleSource,
     ) -> ProgramResult {
         let markets = &mut ctx.accounts.markets.load_mut()?;
         let market = &markets.markets[Markets::index_from_u64(market_index)];
@@ -270,10 +303,12 @@ pub mod clearing_house {
             price: oracle_price,
             twap: oracle_price_twap,
             ..
-        } = market
-            .amm
-            .get_oracle_price(&ctx.accounts.oracle, clock_slot)
-            .unwrap();
+        } = AMM {
+            oracle_source,
+            ..market.amm
+        }
+        .get_oracle_price(&ctx.accounts.oracle, clock_slot)
+        .unwrap();
 
         let market = Market {
             initialized: true,
@@ -281,14 +316,15 @@ pub mod clearing_house {
             base_asset_amount_short: 0,
             base_asset_amount: 0,
             open_interest: 0,
-            padding0: 0,
-            padding1: 0,
-            padding2: 0,
-            padding3: 0,
+            oracle_guard_rails: MarketOracleGuardRails::default(),
+            consecutive_invalid_oracle_readings: 0,
+            reduce_only: false,
+            last_invalid_oracle_reading_slot: 0,
+            padding3: [0; 7],
             padding4: 0,
             amm: AMM {
                 oracle: *ctx.accounts.oracle.key,
-                oracle_source: OracleSource::Pyth,
+                oracle_source,
                 base_asset_reserve: amm_base_asset_reserve,
                 quote_asset_reserve: amm_quote_asset_reserve,
                 cumulative_repeg_rebate_long: 0,
@@ -310,9 +346,9 @@ pub mod clearing_house {
                 last_oracle_price_twap_ts: now,
                 last_oracle_price: oracle_price,
                 minimum_base_asset_trade_size: 10000000,
-                padding1: 0,
-                padding2: 0,
-                padding3: 0,
+                backup_oracle: Pubkey::default(),
+                backup_oracle_source: OracleSource::Pyth,
+                padding1: [0; 7],
             },
         };
 
@@ -321,6 +357,57 @@ pub mod clearing_house {
         Ok(())
     }
 
+    pub fn initialize_oracle_basket(
+        ctx: Context<InitializeOracleBasket>,
+        components: Vec<OracleBasketComponentParams>,
+    ) -> ProgramResult {
+        if components.is_empty() || components.len() > MAX_ORACLE_BASKET_COMPONENTS {
+            return Err(ErrorCode::InvalidOracleBasket.into());
+        }
+
+        let oracle_basket = &mut ctx.accounts.oracle_basket.load_init()?;
+        for (i, component) in components.iter().enumerate() {
+            let is_duplicate = components[..i]
+                .iter()
+                .any(|other_component| other_component.oracle.eq(&component.oracle));
+            if component.weight == 0 || is_duplicate {
+                return Err(ErrorCode::InvalidOracleBasket.into());
+            }
+
+            oracle_basket.components[i] = OracleBasketComponent {
+                oracle: component.oracle,
+                weight: component.weight,
+            };
+        }
+        oracle_basket.num_components = cast(components.len())?;
+
+        Ok(())
+    }
+
+    pub fn initialize_oracle_ratio(
+        ctx: Context<InitializeOracleBasket>,
+        numerator_oracle: Pubkey,
+        denominator_oracle: Pubkey,
+    ) -> ProgramResult {
+        if numerator_oracle.eq(&denominator_oracle) {
+            return Err(ErrorCode::InvalidOracleBasket.into());
+        }
+
+        let oracle_basket = &mut ctx.accounts.oracle_basket.load_init()?;
+        oracle_basket.components[0] = OracleBasketComponent {
+            oracle: numerator_oracle,
+            weight: ORACLE_BASKET_WEIGHT_PRECISION,
+        };
+        oracle_basket.components[1] = OracleBasketComponent {
+            oracle: denominator_oracle,
+            weight: ORACLE_BASKET_WEIGHT_PRECISION,
+        };
+        oracle_basket.num_components = 2;
+        oracle_basket.basket_type = OracleBasketType::Ratio;
+
+        Ok(())
+    }
+
     pub fn deposit_collateral(ctx: Context<DepositCollateral>, amount: u64) -> ProgramResult {
         let user = &mut ctx.accounts.user;
         let clock = Clock::get()?;
@@ -433,7 +520,15 @@ pub mod clearing_house {
             .checked_sub(cast(insurance_account_withdrawal)?)
             .ok_or_else(math_error!())?;
 
-        if !meets_initial_margin_requirement(&ctx.accounts.state, user, user_positions, markets)? {
+        if !meets_initial_margin_requirement(
+            &ctx.accounts.state,
+            user,
+            user_positions,
+            markets,
+            None,
+            ctx.remaining_accounts,
+            clock.slot,
+        )? {
             return Err(ErrorCode::InsufficientCollateral.into());
         }
 
@@ -480,7 +575,7 @@ pub mod clearing_house {
         valid_oracle_for_market(&ctx.accounts.oracle, &ctx.accounts.markets, market_index)
     )]
     pub fn open_position<'info>(
-        ctx: Context<OpenPosition>,
+        ctx: Context<'_, '_, '_, 'info, OpenPosition<'info>>,
         direction: PositionDirection,
         quote_asset_amount: u128,
         market_index: u64,
@@ -520,23 +615,22 @@ pub mod clearing_house {
             let market = &mut ctx.accounts.markets.load_mut()?.markets
                 [Markets::index_from_u64(market_index)];
             mark_price_before = market.amm.mark_price()?;
-            let oracle_price_data = &market
-                .amm
-                .get_oracle_price(&ctx.accounts.oracle, clock_slot)?;
-            oracle_mark_spread_pct_before = amm::calculate_oracle_mark_spread_pct(
-                &market.amm,
-                oracle_price_data,
-                0,
+            let backup_price_oracle = get_backup_oracle(&market.amm, ctx.remaining_accounts);
+            let oracle_status = get_oracle_status(
+                market,
+                &ctx.accounts.oracle,
+                backup_price_oracle,
+                clock_slot,
+                &ctx.accounts.state.oracle_guard_rails,
+                &ctx.accounts.state.extended_oracle_guard_rails,
                 Some(mark_price_before),
             )?;
-            is_oracle_valid = amm::is_oracle_valid(
-                oracle_price_data,
-                &ctx.accounts.state.oracle_guard_rails.validity,
-            )?;
+            oracle_mark_spread_pct_before = oracle_status.oracle_mark_spread_pct;
+            is_oracle_valid = oracle_status.is_valid;
             if is_oracle_valid {
                 let normalised_oracle_price = normalise_oracle_price(
                     &market.amm,
-                    oracle_price_data,
+                    &oracle_status.price_data,
                     Some(mark_price_before),
                 )?;
                 amm::update_oracle_price_twap(&mut market.amm, now, normalised_oracle_price)?;
@@ -568,26 +662,36 @@ pub mod clearing_house {
             potentially_risk_increasing = _potentially_risk_increasing;
             base_asset_amount = _base_asset_amount;
             quote_asset_amount = _quote_asset_amount;
+
+            // Trade fails if it's risk increasing and the oracle circuit breaker has tripped
+            if market.reduce_only && potentially_risk_increasing {
+                return Err(ErrorCode::MarketReduceOnly.into());
+            }
         }
 
         // Collect data about position/market after trade is executed so that it can be stored in trade history
         let mark_price_after: u128;
         let oracle_price_after: i128;
         let oracle_mark_spread_pct_after: i128;
+        let oracle_guard_rails: OracleGuardRails;
         {
             let market = &mut ctx.accounts.markets.load_mut()?.markets
                 [Markets::index_from_u64(market_index)];
+            oracle_guard_rails =
+                market.get_oracle_guard_rails(&ctx.accounts.state.oracle_guard_rails);
             mark_price_after = market.amm.mark_price()?;
-            let oracle_price_data = &market
-                .amm
-                .get_oracle_price(&ctx.accounts.oracle, clock_slot)?;
-            oracle_mark_spread_pct_after = amm::calculate_oracle_mark_spread_pct(
-                &market.amm,
-                oracle_price_data,
-                0,
+            let backup_price_oracle = get_backup_oracle(&market.amm, ctx.remaining_accounts);
+            let oracle_status = get_oracle_status(
+                market,
+                &ctx.accounts.oracle,
+                backup_price_oracle,
+                clock_slot,
+                &ctx.accounts.state.oracle_guard_rails,
+                &ctx.accounts.state.extended_oracle_guard_rails,
                 Some(mark_price_after),
             )?;
-            oracle_price_after = oracle_price_data.price;
+            oracle_mark_spread_pct_after = oracle_status.oracle_mark_spread_pct;
+            oracle_price_after = oracle_status.price_data.price;
         }
 
         // Trade fails if it's risk increasing and it brings the user below the initial margin ratio level
@@ -596,6 +700,9 @@ pub mod clearing_house {
             user,
             user_positions,
             &ctx.accounts.markets.load()?,
+            Some(&ctx.accounts.oracle),
+            ctx.remaining_accounts,
+            clock_slot,
         )?;
         if !meets_initial_margin_requirement && potentially_risk_increasing {
             return Err(ErrorCode::InsufficientCollateral.into());
@@ -664,11 +771,11 @@ pub mod clearing_house {
         // away from the oracle price
         let is_oracle_mark_too_divergent_before = amm::is_oracle_mark_too_divergent(
             oracle_mark_spread_pct_before,
-            &ctx.accounts.state.oracle_guard_rails.price_divergence,
+            &oracle_guard_rails.price_divergence,
         )?;
         let is_oracle_mark_too_divergent_after = amm::is_oracle_mark_too_divergent(
             oracle_mark_spread_pct_after,
-            &ctx.accounts.state.oracle_guard_rails.price_divergence,
+            &oracle_guard_rails.price_divergence,
         )?;
 
         // if oracle-mark divergence pushed outside limit, block trade
@@ -728,16 +835,20 @@ pub mod clearing_house {
             let market = &mut ctx.accounts.markets.load_mut()?.markets
                 [Markets::index_from_u64(market_index)];
             let price_oracle = &ctx.accounts.oracle;
+            let backup_price_oracle = get_backup_oracle(&market.amm, ctx.remaining_accounts);
             let funding_rate_history = &mut ctx.accounts.funding_rate_history.load_mut()?;
             controller::funding::update_funding_rate(
                 market_index,
                 market,
                 price_oracle,
+                backup_price_oracle,
                 now,
                 clock_slot,
                 funding_rate_history,
                 &ctx.accounts.state.oracle_guard_rails,
+                &ctx.accounts.state.extended_oracle_guard_rails,
                 ctx.accounts.state.funding_paused,
+                ctx.accounts.state.oracle_circuit_breaker_threshold,
                 Some(mark_price_before),
             )?;
         }
@@ -780,15 +891,17 @@ pub mod clearing_house {
 
         // Collect data about market before trade is executed so that it can be stored in trade history
         let mark_price_before = market.amm.mark_price()?;
-        let oracle_price_data = &market
-            .amm
-            .get_oracle_price(&ctx.accounts.oracle, clock_slot)?;
-        let oracle_mark_spread_pct_before = amm::calculate_oracle_mark_spread_pct(
-            &market.amm,
-            oracle_price_data,
-            0,
+        let oracle_status = get_oracle_status(
+            market,
+            &ctx.accounts.oracle,
+            get_backup_oracle(&market.amm, ctx.remaining_accounts),
+            clock_slot,
+            &ctx.accounts.state.oracle_guard_rails,
+            &ctx.accounts.state.extended_oracle_guard_rails,
             Some(mark_price_before),
         )?;
+        let oracle_price_data = &oracle_status.price_data;
+        let oracle_mark_spread_pct_before = oracle_status.oracle_mark_spread_pct;
         let direction_to_close =
             math::position::direction_to_close_position(market_position.base_asset_amount);
         let (quote_asset_amount, base_asset_amount) = controller::position::close(
@@ -867,10 +980,7 @@ pub mod clearing_house {
         )?;
         let oracle_price_after = oracle_price_data.price;
 
-        let is_oracle_valid = amm::is_oracle_valid(
-            oracle_price_data,
-            &ctx.accounts.state.oracle_guard_rails.validity,
-        )?;
+        let is_oracle_valid = oracle_status.is_valid;
         if is_oracle_valid {
             let normalised_oracle_price =
                 normalise_oracle_price(&market.amm, oracle_price_data, Some(mark_price_before))?;
@@ -881,11 +991,15 @@ pub mod clearing_house {
         // away from the oracle price
         let is_oracle_mark_too_divergent_before = amm::is_oracle_mark_too_divergent(
             oracle_mark_spread_pct_before,
-            &ctx.accounts.state.oracle_guard_rails.price_divergence,
+            &market
+                .get_oracle_guard_rails(&ctx.accounts.state.oracle_guard_rails)
+                .price_divergence,
         )?;
         let is_oracle_mark_too_divergent_after = amm::is_oracle_mark_too_divergent(
             oracle_mark_spread_pct_after,
-            &ctx.accounts.state.oracle_guard_rails.price_divergence,
+            &market
+                .get_oracle_guard_rails(&ctx.accounts.state.oracle_guard_rails)
+                .price_divergence,
         )?;
 
         // if closing position pushes outside of oracle-mark divergence limit, block trade
@@ -918,16 +1032,20 @@ pub mod clearing_house {
         });
 
         // Try to update the funding rate at the end of every trade
+        let backup_price_oracle = get_backup_oracle(&market.amm, ctx.remaining_accounts);
         let funding_rate_history = &mut ctx.accounts.funding_rate_history.load_mut()?;
         controller::funding::update_funding_rate(
             market_index,
             market,
             price_oracle,
+            backup_price_oracle,
             now,
             clock_slot,
             funding_rate_history,
             &ctx.accounts.state.oracle_guard_rails,
+            &ctx.accounts.state.extended_oracle_guard_rails,
             ctx.accounts.state.funding_paused,
+            ctx.accounts.state.oracle_circuit_breaker_threshold,
             Some(mark_price_before),
         )?;
 
@@ -936,7 +1054,8 @@ pub mod clearing_house {
 
     #[allow(unused_must_use)]
     #[access_control(
-        market_initialized(&ctx.accounts.markets, params.market_index)
+        market_initialized(&ctx.accounts.markets, params.market_index) &&
+        valid_oracle_for_market(&ctx.accounts.oracle, &ctx.accounts.markets, params.market_index)
     )]
     pub fn place_order<'info>(ctx: Context<PlaceOrder>, params: OrderParams) -> ProgramResult {
         let account_info_iter = &mut ctx.remaining_accounts.iter();
@@ -957,12 +1076,18 @@ pub mod clearing_house {
             return Err(ErrorCode::MarketOrderMustBeInPlaceAndFill.into());
         }
 
+        if params.immediate_or_cancel || params.fill_or_kill {
+            return Err(ErrorCode::ImmediateOrderMustBeInPlaceAndFill.into());
+        }
+
         controller::orders::place_order(
             &ctx.accounts.state,
             &ctx.accounts.order_state,
             &mut ctx.accounts.user,
             &ctx.accounts.user_positions,
             &ctx.accounts.markets,
+            &ctx.accounts.oracle,
+            ctx.remaining_accounts,
             &ctx.accounts.user_orders,
             &ctx.accounts.funding_payment_history,
             &ctx.accounts.order_history,
@@ -970,6 +1095,63 @@ pub mod clearing_house {
             &referrer,
             &Clock::get()?,
             params,
+            0,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn place_one_cancels_other_orders(
+        ctx: Context<PlaceOrder>,
+        legs: Vec<OrderParams>,
+    ) -> ProgramResult {
+        let first_leg = legs.first().ok_or(ErrorCode::InvalidOrderGroup)?;
+        market_initialized(&ctx.accounts.markets, first_leg.market_index)?;
+        valid_oracle_for_market(
+            &ctx.accounts.oracle,
+            &ctx.accounts.markets,
+            first_leg.market_index,
+        )?;
+
+        let account_info_iter = &mut ctx.remaining_accounts.iter();
+        let discount_token = get_discount_token(
+            first_leg.optional_accounts.discount_token,
+            account_info_iter,
+            &ctx.accounts.state.discount_mint,
+            ctx.accounts.authority.key,
+        )?;
+        let referrer = get_referrer(
+            first_leg.optional_accounts.referrer,
+            account_info_iter,
+            &ctx.accounts.user.key(),
+            None,
+        )?;
+
+        for leg in legs.iter() {
+            if leg.order_type == OrderType::Market {
+                return Err(ErrorCode::MarketOrderMustBeInPlaceAndFill.into());
+            }
+
+            if leg.immediate_or_cancel || leg.fill_or_kill {
+                return Err(ErrorCode::ImmediateOrderMustBeInPlaceAndFill.into());
+            }
+        }
+
+        controller::orders::place_one_cancels_other_orders(
+            &ctx.accounts.state,
+            &ctx.accounts.order_state,
+            &mut ctx.accounts.user,
+            &ctx.accounts.user_positions,
+            &ctx.accounts.markets,
+            &ctx.accounts.oracle,
+            ctx.remaining_accounts,
+            &ctx.accounts.user_orders,
+            &ctx.accounts.funding_payment_history,
+            &ctx.accounts.order_history,
+            discount_token,
+            &referrer,
+            &Clock::get()?,
+            legs,
         )?;
 
         Ok(())
@@ -1005,10 +1187,77 @@ pub mod clearing_house {
         Ok(())
     }
 
+    pub fn cancel_all_orders(
+        ctx: Context<CancelOrder>,
+        market_index: Option<u64>,
+        direction: Option<PositionDirection>,
+        reduce_only: Option<bool>,
+    ) -> ProgramResult {
+        controller::orders::cancel_all_orders(
+            &mut ctx.accounts.user,
+            &ctx.accounts.user_positions,
+            &ctx.accounts.markets,
+            &ctx.accounts.user_orders,
+            &ctx.accounts.funding_payment_history,
+            &ctx.accounts.order_history,
+            &Clock::get()?,
+            market_index,
+            direction,
+            reduce_only,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn modify_order(
+        ctx: Context<ModifyOrder>,
+        order_id: u128,
+        params: ModifyOrderParams,
+    ) -> ProgramResult {
+        controller::orders::modify_order(
+            order_id,
+            &ctx.accounts.state,
+            &ctx.accounts.order_state,
+            &ctx.accounts.user,
+            &ctx.accounts.markets,
+            &ctx.accounts.oracle,
+            ctx.remaining_accounts,
+            &ctx.accounts.user_orders,
+            &ctx.accounts.order_history,
+            &Clock::get()?,
+            params,
+        )?;
+
+        Ok(())
+    }
+
+    #[access_control(
+        exchange_not_paused(&ctx.accounts.state)
+    )]
+    pub fn expire_order(ctx: Context<ExpireOrder>, order_id: u128) -> ProgramResult {
+        controller::orders::expire_order(
+            order_id,
+            &ctx.accounts.order_state,
+            &mut ctx.accounts.user,
+            &ctx.accounts.user_positions,
+            &ctx.accounts.markets,
+            &ctx.accounts.user_orders,
+            &mut ctx.accounts.filler,
+            &ctx.accounts.funding_payment_history,
+            &ctx.accounts.order_history,
+            &Clock::get()?,
+        )?;
+
+        Ok(())
+    }
+
     #[access_control(
         exchange_not_paused(&ctx.accounts.state)
     )]
-    pub fn fill_order<'info>(ctx: Context<FillOrder>, order_id: u128) -> ProgramResult {
+    pub fn fill_order<'info>(
+        ctx: Context<'_, '_, '_, 'info, FillOrder<'info>>,
+        order_id: u128,
+    ) -> ProgramResult {
         let account_info_iter = &mut ctx.remaining_accounts.iter();
         let referrer = get_referrer_for_fill_order(
             account_info_iter,
@@ -1017,7 +1266,13 @@ pub mod clearing_house {
             &ctx.accounts.user_orders,
         )?;
 
-        let base_asset_amount = controller::orders::fill_order(
+        let trailing_stop_trigger_price = ctx
+            .accounts
+            .user_orders
+            .load_orders()?
+            .trailing_stop_trigger_price(order_id);
+
+        let (base_asset_amount, order_cancelled) = controller::orders::fill_order(
             order_id,
             &ctx.accounts.state,
             &ctx.accounts.order_state,
@@ -1025,6 +1280,7 @@ pub mod clearing_house {
             &ctx.accounts.user_positions,
             &ctx.accounts.markets,
             &ctx.accounts.oracle,
+            ctx.remaining_accounts,
             &ctx.accounts.user_orders,
             &mut ctx.accounts.filler,
             &ctx.accounts.funding_payment_history,
@@ -1035,8 +1291,146 @@ pub mod clearing_house {
             &Clock::get()?,
         )?;
 
-        if base_asset_amount == 0 {
-            return Err(print_error!(ErrorCode::CouldNotFillOrder)().into());
+        // Touching a trailing stop that hasnt triggered only succeeds if it ratcheted the trigger
+        // price, so that update is kept instead of failing the fill. No filler reward is paid for it
+        if base_asset_amount == 0 && !order_cancelled {
+            let trigger_price_ratcheted = match trailing_stop_trigger_price {
+                Some(trigger_price_before) => {
+                    ctx.accounts
+                        .user_orders
+                        .load_orders()?
+                        .trailing_stop_trigger_price(order_id)
+                        != Some(trigger_price_before)
+                }
+                None => false,
+            };
+
+            if !trigger_price_ratcheted {
+                return Err(print_error!(ErrorCode::CouldNotFillOrder)().into());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[access_control(
+        exchange_not_paused(&ctx.accounts.state)
+    )]
+    pub fn fill_orders<'info>(
+        ctx: Context<'_, '_, '_, 'info, FillOrders<'info>>,
+        order_ids: Vec<u128>,
+    ) -> ProgramResult {
+        controller::orders::fill_orders(
+            &order_ids,
+            &ctx.accounts.state,
+            &ctx.accounts.order_state,
+            &ctx.accounts.markets,
+            &ctx.accounts.oracle,
+            ctx.remaining_accounts,
+            &mut ctx.accounts.filler,
+            &ctx.accounts.funding_payment_history,
+            &ctx.accounts.trade_history,
+            &ctx.accounts.order_history,
+            &ctx.accounts.funding_rate_history,
+            &Clock::get()?,
+        )?;
+
+        Ok(())
+    }
+
+    #[access_control(
+        exchange_not_paused(&ctx.accounts.state)
+    )]
+    pub fn match_orders<'info>(
+        ctx: Context<'_, '_, '_, 'info, MatchOrders<'info>>,
+        taker_order_id: u128,
+        maker_order_id: u128,
+    ) -> ProgramResult {
+        let taker_referrer = get_referrer_for_fill_orders(
+            ctx.remaining_accounts,
+            &ctx.accounts.taker.key(),
+            taker_order_id,
+            &ctx.accounts.taker_orders,
+        )?;
+        let taker_immediate_or_cancel = ctx
+            .accounts
+            .taker_orders
+            .load_orders()?
+            .orders
+            .iter()
+            .any(|order| order.order_id == taker_order_id && order.immediate_or_cancel);
+
+        controller::orders::match_orders(
+            taker_order_id,
+            maker_order_id,
+            &ctx.accounts.state,
+            &ctx.accounts.order_state,
+            &mut ctx.accounts.taker,
+            &ctx.accounts.taker_positions,
+            &ctx.accounts.taker_orders,
+            &mut ctx.accounts.maker,
+            &ctx.accounts.maker_positions,
+            &ctx.accounts.maker_orders,
+            &ctx.accounts.markets,
+            &ctx.accounts.oracle,
+            ctx.remaining_accounts,
+            &mut ctx.accounts.filler,
+            &ctx.accounts.funding_payment_history,
+            &ctx.accounts.trade_history,
+            &ctx.accounts.order_history,
+            taker_referrer,
+            &Clock::get()?,
+        )?;
+
+        // Whatever the maker couldnt fill falls back to the amm
+        let taker_order_open = |taker_orders: &AccountLoader<UserOrders>| -> Result<bool> {
+            Ok(taker_orders
+                .load_orders()?
+                .orders
+                .iter()
+                .any(|order| order.order_id == taker_order_id))
+        };
+
+        if taker_order_open(&ctx.accounts.taker_orders)? {
+            // The referrer is read again to pick up the reward written by the matched fill
+            let taker_referrer = get_referrer_for_fill_orders(
+                ctx.remaining_accounts,
+                &ctx.accounts.taker.key(),
+                taker_order_id,
+                &ctx.accounts.taker_orders,
+            )?;
+            controller::orders::fill_order(
+                taker_order_id,
+                &ctx.accounts.state,
+                &ctx.accounts.order_state,
+                &mut ctx.accounts.taker,
+                &ctx.accounts.taker_positions,
+                &ctx.accounts.markets,
+                &ctx.accounts.oracle,
+                ctx.remaining_accounts,
+                &ctx.accounts.taker_orders,
+                &mut ctx.accounts.filler,
+                &ctx.accounts.funding_payment_history,
+                &ctx.accounts.trade_history,
+                &ctx.accounts.order_history,
+                &ctx.accounts.funding_rate_history,
+                taker_referrer,
+                &Clock::get()?,
+            )?;
+        }
+
+        // Whatever the amm couldnt fill either is cancelled
+        if taker_immediate_or_cancel && taker_order_open(&ctx.accounts.taker_orders)? {
+            controller::orders::cancel_order_by_order_id(
+                taker_order_id,
+                &mut ctx.accounts.taker,
+                &ctx.accounts.taker_positions,
+                &ctx.accounts.markets,
+                &ctx.accounts.taker_orders,
+                &ctx.accounts.funding_payment_history,
+                &ctx.accounts.order_history,
+                &Clock::get()?,
+            )?;
         }
 
         Ok(())
@@ -1049,7 +1443,7 @@ pub mod clearing_house {
         valid_oracle_for_market(&ctx.accounts.oracle, &ctx.accounts.markets, params.market_index)
     )]
     pub fn place_and_fill_order<'info>(
-        ctx: Context<PlaceAndFillOrder>,
+        ctx: Context<'_, '_, '_, 'info, PlaceAndFillOrder<'info>>,
         params: OrderParams,
     ) -> ProgramResult {
         let account_info_iter = &mut ctx.remaining_accounts.iter();
@@ -1066,12 +1460,18 @@ pub mod clearing_house {
             None,
         )?;
 
-        controller::orders::place_order(
+        let base_asset_amount = params.base_asset_amount;
+        let immediate_or_cancel = params.immediate_or_cancel;
+        let fill_or_kill = params.fill_or_kill;
+
+        let order_id = controller::orders::place_order(
             &ctx.accounts.state,
             &ctx.accounts.order_state,
             &mut ctx.accounts.user,
             &ctx.accounts.user_positions,
             &ctx.accounts.markets,
+            &ctx.accounts.oracle,
+            ctx.remaining_accounts,
             &ctx.accounts.user_orders,
             &ctx.accounts.funding_payment_history,
             &ctx.accounts.order_history,
@@ -1079,16 +1479,17 @@ pub mod clearing_house {
             &referrer,
             &Clock::get()?,
             params,
+            0,
         )?;
 
-        let order_id;
-        {
-            let order_history = &ctx.accounts.order_history.load()?;
-            order_id = order_history.last_order_id;
-        }
+        // Post only orders that would cross the amm are cancelled when placed
+        let order_id = match order_id {
+            Some(order_id) => order_id,
+            None => return Ok(()),
+        };
 
         let user = &mut ctx.accounts.user;
-        controller::orders::fill_order(
+        let (base_asset_amount_filled, _) = controller::orders::fill_order(
             order_id,
             &ctx.accounts.state,
             &ctx.accounts.order_state,
@@ -1096,6 +1497,7 @@ pub mod clearing_house {
             &ctx.accounts.user_positions,
             &ctx.accounts.markets,
             &ctx.accounts.oracle,
+            ctx.remaining_accounts,
             &ctx.accounts.user_orders,
             &mut user.clone(),
             &ctx.accounts.funding_payment_history,
@@ -1106,6 +1508,35 @@ pub mod clearing_house {
             &Clock::get()?,
         )?;
 
+        if fill_or_kill && base_asset_amount_filled != base_asset_amount {
+            return Err(ErrorCode::FillOrKillOrderNotFilled.into());
+        }
+
+        // Whatever wasn't filled in this transaction is cancelled
+        if immediate_or_cancel {
+            let order_open;
+            {
+                let user_orders = &ctx.accounts.user_orders.load_orders()?;
+                order_open = user_orders
+                    .orders
+                    .iter()
+                    .any(|order| order.order_id == order_id);
+            }
+
+            if order_open {
+                controller::orders::cancel_order_by_order_id(
+                    order_id,
+                    &mut ctx.accounts.user,
+                    &ctx.accounts.user_positions,
+                    &ctx.accounts.markets,
+                    &ctx.accounts.user_orders,
+                    &ctx.accounts.funding_payment_history,
+                    &ctx.accounts.order_history,
+                    &Clock::get()?,
+                )?;
+            }
+        }
+
         Ok(())
     }
 
@@ -1246,7 +1677,9 @@ pub mod clearing_house {
 
                 let oracle_mark_too_divergent_after_close = is_oracle_mark_too_divergent(
                     oracle_mark_divergence_after_close,
-                    &state.oracle_guard_rails.price_divergence,
+                    &market
+                        .get_oracle_guard_rails(&state.oracle_guard_rails)
+                        .price_divergence,
                 )?;
 
                 // if closing pushes outside the oracle mark threshold, don't liquidate
@@ -1450,7 +1883,9 @@ pub mod clearing_house {
 
                 let oracle_mark_too_divergent_after_reduce = is_oracle_mark_too_divergent(
                     oracle_mark_divergence_after_reduce,
-                    &state.oracle_guard_rails.price_divergence,
+                    &market
+                        .get_oracle_guard_rails(&state.oracle_guard_rails)
+                        .price_divergence,
                 )?;
 
                 // if reducing pushes outside the oracle mark threshold, don't liquidate
@@ -1756,13 +2191,16 @@ pub mod clearing_house {
         let sqrt_k_before = market.amm.sqrt_k;
 
         let oracle_validity_rails = &ctx.accounts.state.oracle_guard_rails;
+        let backup_price_oracle = get_backup_oracle(&market.amm, ctx.remaining_accounts);
 
         let adjustment_cost = controller::repeg::repeg(
             market,
             price_oracle,
+            backup_price_oracle,
             new_peg_candidate,
             clock_slot,
             oracle_validity_rails,
+            &ctx.accounts.state.extended_oracle_guard_rails,
         )?;
 
         let peg_multiplier_after = market.amm.peg_multiplier;
@@ -1815,13 +2253,18 @@ pub mod clearing_house {
         let market =
             &mut ctx.accounts.markets.load_mut()?.markets[Markets::index_from_u64(market_index)];
         let price_oracle = &ctx.accounts.oracle;
-        let oracle_price_data = &market.amm.get_oracle_price(price_oracle, clock_slot)?;
-        let oracle_twap = oracle_price_data.twap;
-
-        let is_oracle_valid = amm::is_oracle_valid(
-            oracle_price_data,
-            &ctx.accounts.state.oracle_guard_rails.validity,
+        let (oracle_price_data, is_oracle_valid) = get_oracle_price_data(
+            &market.amm,
+            price_oracle,
+            get_backup_oracle(&market.amm, ctx.remaining_accounts),
+            clock_slot,
+            &market
+                .get_oracle_guard_rails(&ctx.accounts.state.oracle_guard_rails)
+                .validity,
+            &market
+                .get_extended_oracle_guard_rails(&ctx.accounts.state.extended_oracle_guard_rails),
         )?;
+        let oracle_twap = oracle_price_data.twap;
 
         if is_oracle_valid {
             let oracle_mark_gap_before = cast_to_i128(market.amm.last_mark_price_twap)?
@@ -1866,11 +2309,16 @@ pub mod clearing_house {
         let market =
             &mut ctx.accounts.markets.load_mut()?.markets[Markets::index_from_u64(market_index)];
         let price_oracle = &ctx.accounts.oracle;
-        let oracle_price_data = &market.amm.get_oracle_price(price_oracle, clock_slot)?;
-
-        let is_oracle_valid = amm::is_oracle_valid(
-            oracle_price_data,
-            &ctx.accounts.state.oracle_guard_rails.validity,
+        let (_, is_oracle_valid) = get_oracle_price_data(
+            &market.amm,
+            price_oracle,
+            get_backup_oracle(&market.amm, ctx.remaining_accounts),
+            clock_slot,
+            &market
+                .get_oracle_guard_rails(&ctx.accounts.state.oracle_guard_rails)
+                .validity,
+            &market
+                .get_extended_oracle_guard_rails(&ctx.accounts.state.extended_oracle_guard_rails),
         )?;
 
         if !is_oracle_valid {
@@ -1929,6 +2377,67 @@ pub mod clearing_house {
         Ok(())
     }
 
+    pub fn resize_user_orders(ctx: Context<ResizeUserOrders>) -> ProgramResult {
+        let user = &mut ctx.accounts.user;
+        let user_orders = &ctx.accounts.user_orders.load_orders()?;
+        let new_user_orders = &mut ctx.accounts.new_user_orders.load_orders_init(&user.key())?;
+
+        let mut new_orders = new_user_orders.orders.iter_mut();
+        for order in user_orders
+            .orders
+            .iter()
+            .filter(|order| order.status != OrderStatus::Init)
+        {
+            let new_order = new_orders
+                .next()
+                .ok_or(ErrorCode::UserOrdersCapacityTooSmall)?;
+            *new_order = *order;
+        }
+
+        user.user_orders = ctx.accounts.new_user_orders.key();
+
+        Ok(())
+    }
+
+    pub fn migrate_user_orders(ctx: Context<MigrateUserOrders>) -> ProgramResult {
+        let user = &mut ctx.accounts.user;
+        {
+            let legacy_user_orders = load_legacy_user_orders(&ctx.accounts.user_orders)?;
+            if !legacy_user_orders.user.eq(&user.key()) {
+                return Err(ErrorCode::InvalidUserOrdersAccount.into());
+            }
+
+            let new_user_orders =
+                &mut ctx.accounts.new_user_orders.load_orders_init(&user.key())?;
+
+            let mut new_orders = new_user_orders.orders.iter_mut();
+            for order in legacy_user_orders
+                .orders
+                .iter()
+                .filter(|order| order.status != OrderStatus::Init)
+            {
+                let new_order = new_orders
+                    .next()
+                    .ok_or(ErrorCode::UserOrdersCapacityTooSmall)?;
+                *new_order = order.to_order();
+            }
+        }
+
+        // The legacy account can't be loaded as UserOrders, so it's closed by hand
+        let legacy_user_orders = &ctx.accounts.user_orders;
+        let authority = &ctx.accounts.authority;
+        **authority.lamports.borrow_mut() = authority
+            .lamports()
+            .checked_add(legacy_user_orders.lamports())
+            .ok_or_else(math_error!())?;
+        **legacy_user_orders.lamports.borrow_mut() = 0;
+        legacy_user_orders.try_borrow_mut_data()?.fill(0);
+
+        user.user_orders = ctx.accounts.new_user_orders.key();
+
+        Ok(())
+    }
+
     pub fn delete_user(ctx: Context<DeleteUser>) -> ProgramResult {
         let user = &ctx.accounts.user;
 
@@ -1940,6 +2449,13 @@ pub mod clearing_house {
         Ok(())
     }
 
+    pub fn update_oracle_basket(ctx: Context<UpdateOracleBasket>) -> ProgramResult {
+        let clock_slot = Clock::get()?.slot;
+        let oracle_basket = &mut ctx.accounts.oracle_basket.load_mut()?;
+        oracle_basket.update(ctx.remaining_accounts, clock_slot)?;
+        Ok(())
+    }
+
     #[access_control(
         exchange_not_paused(&ctx.accounts.state)
     )]
@@ -1956,6 +2472,50 @@ pub mod clearing_house {
         Ok(())
     }
 
+    #[allow(unused_must_use)]
+    #[access_control(
+        market_initialized(&ctx.accounts.markets, market_index) &&
+        valid_oracle_for_market(&ctx.accounts.oracle, &ctx.accounts.markets, market_index)
+    )]
+    pub fn update_oracle_status(
+        ctx: Context<UpdateOracleStatus>,
+        market_index: u64,
+    ) -> ProgramResult {
+        let market =
+            &mut ctx.accounts.markets.load_mut()?.markets[Markets::index_from_u64(market_index)];
+        let price_oracle = &ctx.accounts.oracle;
+        let clock = Clock::get()?;
+        let now = clock.unix_timestamp;
+        let clock_slot = clock.slot;
+
+        let backup_price_oracle = get_backup_oracle(&market.amm, ctx.remaining_accounts);
+        let mark_price = market.amm.mark_price()?;
+        let oracle_status = get_oracle_status(
+            market,
+            price_oracle,
+            backup_price_oracle,
+            clock_slot,
+            &ctx.accounts.state.oracle_guard_rails,
+            &ctx.accounts.state.extended_oracle_guard_rails,
+            Some(mark_price),
+        )?;
+
+        let oracle_history = &mut ctx.accounts.oracle_history.load_mut()?;
+        controller::oracle::update_oracle_status(
+            market_index,
+            market,
+            price_oracle.key,
+            &oracle_status,
+            mark_price,
+            oracle_history,
+            ctx.accounts.state.oracle_circuit_breaker_threshold,
+            now,
+            clock_slot,
+        )?;
+
+        Ok(())
+    }
+
     #[allow(unused_must_use)]
     #[access_control(
         market_initialized(&ctx.accounts.markets, market_index) &&
@@ -1973,16 +2533,20 @@ pub mod clearing_house {
         let now = clock.unix_timestamp;
         let clock_slot = clock.slot;
 
+        let backup_price_oracle = get_backup_oracle(&market.amm, ctx.remaining_accounts);
         let funding_rate_history = &mut ctx.accounts.funding_rate_history.load_mut()?;
         controller::funding::update_funding_rate(
             market_index,
             market,
             price_oracle,
+            backup_price_oracle,
             now,
             clock_slot,
             funding_rate_history,
             &ctx.accounts.state.oracle_guard_rails,
+            &ctx.accounts.state.extended_oracle_guard_rails,
             ctx.accounts.state.funding_paused,
+            ctx.accounts.state.oracle_circuit_breaker_threshold,
             None,
         )?;
 
@@ -2103,6 +2667,22 @@ pub mod clearing_house {
         Ok(())
     }
 
+    pub fn update_order_history(ctx: Context<UpdateOrderHistory>) -> ProgramResult {
+        let order_history = load_legacy_order_history(&ctx.accounts.order_history)?;
+        let new_order_history = &mut ctx.accounts.new_order_history.load_init()?;
+
+        // Order ids and record ids carry on from the legacy history
+        new_order_history.last_order_id = order_history.last_order_id;
+        let last_record = order_history.last_record();
+        if last_record.record_id != 0 {
+            new_order_history.append(last_record.to_order_record());
+        }
+
+        let order_state = &mut ctx.accounts.order_state;
+        order_state.order_history = ctx.accounts.new_order_history.key();
+        Ok(())
+    }
+
     pub fn update_curve_history(ctx: Context<UpdateCurveHistory>) -> ProgramResult {
         let curve_history = &ctx.accounts.curve_history.load()?;
         let extended_curve_history = &mut ctx.accounts.extended_curve_history.load_init()?;
@@ -2228,6 +2808,14 @@ pub mod clearing_house {
         Ok(())
     }
 
+    pub fn update_expire_order_reward(
+        ctx: Context<AdminUpdateOrderState>,
+        expire_order_reward: u128,
+    ) -> ProgramResult {
+        ctx.accounts.order_state.expire_order_reward = expire_order_reward;
+        Ok(())
+    }
+
     pub fn update_oracle_guard_rails(
         ctx: Context<AdminUpdateState>,
         oracle_guard_rails: OracleGuardRails,
@@ -2236,6 +2824,14 @@ pub mod clearing_house {
         Ok(())
     }
 
+    pub fn update_extended_oracle_guard_rails(
+        ctx: Context<AdminUpdateState>,
+        extended_oracle_guard_rails: ExtendedOracleGuardRails,
+    ) -> ProgramResult {
+        ctx.accounts.state.extended_oracle_guard_rails = extended_oracle_guard_rails;
+        Ok(())
+    }
+
     #[access_control(
         market_initialized(&ctx.accounts.markets, market_index)
     )]
@@ -2252,6 +2848,48 @@ pub mod clearing_house {
         Ok(())
     }
 
+    #[access_control(
+        market_initialized(&ctx.accounts.markets, market_index)
+    )]
+    pub fn update_market_oracle_guard_rails(
+        ctx: Context<AdminUpdateMarket>,
+        market_index: u64,
+        oracle_guard_rails: Option<OracleGuardRails>,
+        extended_oracle_guard_rails: Option<ExtendedOracleGuardRails>,
+    ) -> ProgramResult {
+        let market =
+            &mut ctx.accounts.markets.load_mut()?.markets[Markets::index_from_u64(market_index)];
+        market.oracle_guard_rails = match oracle_guard_rails {
+            Some(oracle_guard_rails) => {
+                // the market keeps the state's extended guard rails unless they're overridden too
+                let extended_oracle_guard_rails = extended_oracle_guard_rails
+                    .unwrap_or_else(|| ctx.accounts.state.extended_oracle_guard_rails.clone());
+                MarketOracleGuardRails::from_oracle_guard_rails(
+                    &oracle_guard_rails,
+                    &extended_oracle_guard_rails,
+                )?
+            }
+            None => MarketOracleGuardRails::default(),
+        };
+        Ok(())
+    }
+
+    #[access_control(
+        market_initialized(&ctx.accounts.markets, market_index)
+    )]
+    pub fn update_market_backup_oracle(
+        ctx: Context<AdminUpdateMarket>,
+        market_index: u64,
+        backup_oracle: Pubkey,
+        backup_oracle_source: OracleSource,
+    ) -> ProgramResult {
+        let market =
+            &mut ctx.accounts.markets.load_mut()?.markets[Markets::index_from_u64(market_index)];
+        market.amm.backup_oracle = backup_oracle;
+        market.amm.backup_oracle_source = backup_oracle_source;
+        Ok(())
+    }
+
     #[access_control(
         market_initialized(&ctx.accounts.markets, market_index)
     )]
@@ -2301,6 +2939,14 @@ pub mod clearing_house {
         Ok(())
     }
 
+    pub fn update_oracle_circuit_breaker_threshold(
+        ctx: Context<AdminUpdateState>,
+        oracle_circuit_breaker_threshold: u64,
+    ) -> ProgramResult {
+        ctx.accounts.state.oracle_circuit_breaker_threshold = oracle_circuit_breaker_threshold;
+        Ok(())
+    }
+
     pub fn update_max_deposit(ctx: Context<AdminUpdateState>, max_deposit: u128) -> ProgramResult {
         ctx.accounts.state.max_deposit = max_deposit;
         Ok(())
@@ -2342,8 +2988,7 @@ fn valid_oracle_for_market(
 ) -> Result<()> {
     if !markets.load()?.markets[Markets::index_from_u64(market_index)]
         .amm
-        .oracle
-        .eq(oracle.key)
+        .is_market_oracle(oracle.key)
     {
         return Err(ErrorCode::InvalidOracle.into());
     }
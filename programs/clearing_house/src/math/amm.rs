@@ -15,8 +15,10 @@ use crate::math::constants::{
 use crate::math::position::_calculate_base_asset_value_and_pnl;
 use crate::math::quote_asset::{asset_to_reserve_amount, reserve_to_asset_amount};
 use crate::math_error;
-use crate::state::market::{Market, OraclePriceData, AMM};
-use crate::state::state::{PriceDivergenceGuardRails, ValidityGuardRails};
+use crate::state::market::{Market, OraclePriceData, OraclePriceStatus, AMM};
+use crate::state::state::{
+    ExtendedOracleGuardRails, PriceDivergenceGuardRails, ValidityGuardRails,
+};
 
 pub fn calculate_price(
     quote_asset_reserve: u128,
@@ -418,6 +420,7 @@ pub fn use_oracle_price_for_margin_calculation(
 pub fn is_oracle_valid(
     oracle_price_data: &OraclePriceData,
     valid_oracle_guard_rails: &ValidityGuardRails,
+    extended_oracle_guard_rails: &ExtendedOracleGuardRails,
 ) -> ClearingHouseResult<bool> {
     let OraclePriceData {
         price: oracle_price,
@@ -425,6 +428,8 @@ pub fn is_oracle_valid(
         confidence: oracle_conf,
         twap_confidence: oracle_twap_conf,
         delay: oracle_delay,
+        status: oracle_status,
+        num_publishers: oracle_num_publishers,
     } = *oracle_price_data;
 
     let is_oracle_price_nonpositive = (oracle_twap <= 0) || (oracle_price <= 0);
@@ -450,7 +455,14 @@ pub fn is_oracle_valid(
 
     let is_stale = oracle_delay.gt(&valid_oracle_guard_rails.slots_before_stale);
 
+    let is_not_trading = extended_oracle_guard_rails.require_trading_status
+        && oracle_status != OraclePriceStatus::Trading;
+
+    let has_too_few_publishers = oracle_num_publishers < extended_oracle_guard_rails.min_publishers;
+
     Ok(!(is_stale
+        || is_not_trading
+        || has_too_few_publishers
         || is_conf_too_large
         || is_oracle_price_nonpositive
         || is_oracle_price_too_volatile))
@@ -7,6 +7,7 @@ pub const MARGIN_PRECISION: u128 = 10_000; // expo = -4
 pub const PEG_PRECISION: u128 = 1_000; //expo = -3
 pub const PRICE_SPREAD_PRECISION: i128 = 10_000; // expo = -4
 pub const PRICE_SPREAD_PRECISION_U128: u128 = 10_000; // expo = -4
+pub const ORACLE_BASKET_WEIGHT_PRECISION: u128 = 1_000_000; // expo = -6
 
 // PRECISION CONVERSIONS
 pub const PRICE_TO_PEG_PRECISION_RATIO: u128 = MARK_PRICE_PRECISION / PEG_PRECISION; // expo: 7
@@ -55,3 +56,4 @@ pub const DEFAULT_REFEREE_DISCOUNT_DENOMINATOR: u128 = 100;
 pub const MAX_LIQUIDATION_SLIPPAGE: i128 = 100; // expo = -2
 pub const MAX_LIQUIDATION_SLIPPAGE_U128: u128 = 100; // expo = -2
 pub const MAX_MARK_TWAP_DIVERGENCE: u128 = 5_000; // expo = -3
+pub const TRAILING_STOP_MIN_RATCHET_DENOMINATOR: u128 = 10; // 1/10th of the offset
@@ -3,17 +3,18 @@ use crate::math::collateral::calculate_updated_collateral;
 use crate::math::constants::MARGIN_PRECISION;
 use crate::math::position::{
     calculate_base_asset_value_and_pnl, calculate_base_asset_value_and_pnl_with_oracle_price,
+    calculate_conservative_oracle_price,
 };
 use crate::math_error;
-use crate::state::market::Markets;
-use crate::state::user::{User, UserPositions};
+use crate::state::market::{Market, Markets};
+use crate::state::user::{MarketPosition, User, UserPositions};
 use std::cell::{Ref, RefMut};
 
 use crate::math::amm::use_oracle_price_for_margin_calculation;
 use crate::math::casting::cast_to_i128;
 use crate::math::oracle::{get_oracle_status, OracleStatus};
 use crate::math::slippage::calculate_slippage;
-use crate::state::state::{OracleGuardRails, State};
+use crate::state::state::{ExtendedOracleGuardRails, OracleGuardRails, State};
 use anchor_lang::prelude::{AccountInfo, Pubkey};
 use anchor_lang::Key;
 use solana_program::clock::Slot;
@@ -21,24 +22,44 @@ use solana_program::msg;
 use std::collections::BTreeMap;
 use std::ops::Div;
 
-pub fn meets_initial_margin_requirement(
+pub fn meets_initial_margin_requirement<'a>(
     state: &State,
     user: &User,
     user_positions: &RefMut<UserPositions>,
     markets: &Ref<Markets>,
+    oracle: Option<&AccountInfo<'a>>,
+    remaining_accounts: &[AccountInfo<'a>],
+    clock_slot: Slot,
 ) -> ClearingHouseResult<bool> {
     let mut initial_margin_requirement: u128 = 0;
     let mut unrealized_pnl: i128 = 0;
 
+    let mut oracle_account_infos: BTreeMap<Pubkey, &AccountInfo> = BTreeMap::new();
+    for account_info in remaining_accounts.iter().chain(oracle) {
+        oracle_account_infos.insert(account_info.key(), account_info);
+    }
+
     for market_position in user_positions.positions.iter() {
         if market_position.base_asset_amount == 0 {
             continue;
         }
 
         let market = markets.get_market(market_position.market_index);
-        let amm = &market.amm;
+        let extended_oracle_guard_rails =
+            market.get_extended_oracle_guard_rails(&state.extended_oracle_guard_rails);
         let (position_base_asset_value, position_unrealized_pnl) =
-            calculate_base_asset_value_and_pnl(market_position, amm)?;
+            if extended_oracle_guard_rails.use_confidence_interval_for_margin {
+                calculate_base_asset_value_and_pnl_with_oracle_confidence(
+                    market,
+                    market_position,
+                    &oracle_account_infos,
+                    &state.oracle_guard_rails,
+                    &state.extended_oracle_guard_rails,
+                    clock_slot,
+                )?
+            } else {
+                calculate_base_asset_value_and_pnl(market_position, &market.amm)?
+            };
 
         initial_margin_requirement = initial_margin_requirement
             .checked_add(
@@ -62,6 +83,92 @@ pub fn meets_initial_margin_requirement(
     Ok(total_collateral >= initial_margin_requirement)
 }
 
+// Uses the oracle price at the conservative end of its confidence interval when it values the
+// position below the amm
+fn calculate_base_asset_value_and_pnl_with_oracle_confidence(
+    market: &Market,
+    market_position: &MarketPosition,
+    oracle_account_infos: &BTreeMap<Pubkey, &AccountInfo>,
+    oracle_guard_rails: &OracleGuardRails,
+    extended_oracle_guard_rails: &ExtendedOracleGuardRails,
+    clock_slot: Slot,
+) -> ClearingHouseResult<(u128, i128)> {
+    let (amm_position_base_asset_value, amm_position_unrealized_pnl) =
+        calculate_base_asset_value_and_pnl(market_position, &market.amm)?;
+
+    let oracle_price = match get_market_oracle_account_infos(market, oracle_account_infos) {
+        Ok((oracle_account_info, backup_oracle_account_info)) => {
+            let mark_price = market.amm.mark_price()?;
+            let oracle_status = get_oracle_status(
+                market,
+                oracle_account_info,
+                backup_oracle_account_info,
+                clock_slot,
+                oracle_guard_rails,
+                extended_oracle_guard_rails,
+                Some(mark_price),
+            )?;
+
+            if !oracle_status.is_valid
+                || !use_oracle_price_for_margin_calculation(
+                    oracle_status.oracle_mark_spread_pct,
+                    &market
+                        .get_oracle_guard_rails(oracle_guard_rails)
+                        .price_divergence,
+                )?
+            {
+                return Ok((amm_position_base_asset_value, amm_position_unrealized_pnl));
+            }
+
+            calculate_conservative_oracle_price(
+                market_position.base_asset_amount,
+                oracle_status.price_data.price,
+                oracle_status.price_data.confidence,
+            )?
+        }
+        // Positions in other markets don't need their oracle passed in, so fall back to the last
+        // oracle price the market recorded
+        Err(ErrorCode::OracleNotFound) if market.amm.last_oracle_price > 0 => {
+            market.amm.last_oracle_price
+        }
+        Err(ErrorCode::OracleNotFound) => {
+            return Ok((amm_position_base_asset_value, amm_position_unrealized_pnl));
+        }
+        Err(error_code) => return Err(error_code),
+    };
+
+    let (oracle_position_base_asset_value, oracle_position_unrealized_pnl) =
+        calculate_base_asset_value_and_pnl_with_oracle_price(market_position, oracle_price)?;
+
+    if oracle_position_unrealized_pnl < amm_position_unrealized_pnl {
+        Ok((
+            oracle_position_base_asset_value,
+            oracle_position_unrealized_pnl,
+        ))
+    } else {
+        Ok((amm_position_base_asset_value, amm_position_unrealized_pnl))
+    }
+}
+
+fn get_market_oracle_account_infos<'a, 'b>(
+    market: &Market,
+    oracle_account_infos: &BTreeMap<Pubkey, &'a AccountInfo<'b>>,
+) -> ClearingHouseResult<(&'a AccountInfo<'b>, Option<&'a AccountInfo<'b>>)> {
+    let oracle_account_info = oracle_account_infos
+        .get(&market.amm.oracle)
+        .ok_or(ErrorCode::OracleNotFound)?;
+
+    if market.amm.backup_oracle.eq(&Pubkey::default()) {
+        return Ok((*oracle_account_info, None));
+    }
+
+    let backup_oracle_account_info = oracle_account_infos
+        .get(&market.amm.backup_oracle)
+        .ok_or(ErrorCode::BackupOracleNotFound)?;
+
+    Ok((*oracle_account_info, Some(*backup_oracle_account_info)))
+}
+
 #[derive(PartialEq)]
 pub enum LiquidationType {
     NONE,
@@ -130,27 +237,38 @@ pub fn calculate_liquidation_status(
             .ok_or_else(math_error!())?;
 
         // Block the liquidation if the oracle is invalid or the oracle and mark are too divergent
-        let oracle_account_info = oracle_account_infos
-            .get(&market.amm.oracle)
-            .ok_or(ErrorCode::OracleNotFound)?;
+        let (oracle_account_info, backup_oracle_account_info) =
+            get_market_oracle_account_infos(market, &oracle_account_infos)?;
 
         let mark_price_before = market.amm.mark_price()?;
 
         let oracle_status = get_oracle_status(
-            &market.amm,
+            market,
             oracle_account_info,
+            backup_oracle_account_info,
             clock_slot,
             oracle_guard_rails,
+            &state.extended_oracle_guard_rails,
             Some(mark_price_before),
         )?;
 
+        let market_oracle_guard_rails = market.get_oracle_guard_rails(oracle_guard_rails);
+        let market_extended_oracle_guard_rails =
+            market.get_extended_oracle_guard_rails(&state.extended_oracle_guard_rails);
+
+        // The state's use_for_liquidations has never been read, so only a market override can turn
+        // the oracle off for liquidations and existing deployments keep valuing with the oracle
+        let use_oracle_for_liquidations =
+            !market.oracle_guard_rails.enabled || market.oracle_guard_rails.use_for_liquidations;
+
         let market_partial_margin_requirement: u128;
         let market_maintenance_margin_requirement: u128;
         let mut close_position_slippage = None;
-        if oracle_status.is_valid
+        if use_oracle_for_liquidations
+            && oracle_status.is_valid
             && use_oracle_price_for_margin_calculation(
                 oracle_status.oracle_mark_spread_pct,
-                &oracle_guard_rails.price_divergence,
+                &market_oracle_guard_rails.price_divergence,
             )?
         {
             let market_index = market_position.market_index;
@@ -161,9 +279,18 @@ pub fn calculate_liquidation_status(
             )?;
             close_position_slippage = Some(exit_slippage);
 
-            let oracle_exit_price = oracle_status
-                .price_data
-                .price
+            let oracle_price =
+                if market_extended_oracle_guard_rails.use_confidence_interval_for_margin {
+                    calculate_conservative_oracle_price(
+                        market_position.base_asset_amount,
+                        oracle_status.price_data.price,
+                        oracle_status.price_data.confidence,
+                    )?
+                } else {
+                    oracle_status.price_data.price
+                };
+
+            let oracle_exit_price = oracle_price
                 .checked_add(exit_slippage)
                 .ok_or_else(math_error!())?;
 
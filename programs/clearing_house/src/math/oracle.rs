@@ -1,32 +1,34 @@
-use crate::error::ClearingHouseResult;
+use crate::error::*;
 use crate::math::amm;
-use crate::state::market::{OraclePriceData, AMM};
-use crate::state::state::OracleGuardRails;
-use anchor_lang::prelude::AccountInfo;
+use crate::math_error;
+use crate::state::market::{Market, OraclePriceData, AMM};
+use crate::state::state::{ExtendedOracleGuardRails, OracleGuardRails, ValidityGuardRails};
+use anchor_lang::prelude::{AccountInfo, Pubkey};
 use solana_program::clock::Slot;
+use solana_program::msg;
+use std::cmp::{max, min};
 
 pub fn block_operation(
-    amm: &AMM,
+    market: &Market,
     oracle_account_info: &AccountInfo,
+    backup_oracle_account_info: Option<&AccountInfo>,
     clock_slot: Slot,
     guard_rails: &OracleGuardRails,
+    extended_guard_rails: &ExtendedOracleGuardRails,
     precomputed_mark_price: Option<u128>,
-) -> ClearingHouseResult<(bool, OraclePriceData)> {
-    let OracleStatus {
-        price_data: oracle_price_data,
-        is_valid: oracle_is_valid,
-        mark_too_divergent: is_oracle_mark_too_divergent,
-        oracle_mark_spread_pct: _,
-    } = get_oracle_status(
-        amm,
+) -> ClearingHouseResult<(bool, OracleStatus)> {
+    let oracle_status = get_oracle_status(
+        market,
         oracle_account_info,
+        backup_oracle_account_info,
         clock_slot,
         guard_rails,
+        extended_guard_rails,
         precomputed_mark_price,
     )?;
 
-    let block = !oracle_is_valid || is_oracle_mark_too_divergent;
-    Ok((block, oracle_price_data))
+    let block = !oracle_status.is_valid || oracle_status.mark_too_divergent;
+    Ok((block, oracle_status))
 }
 
 #[derive(Default, Clone, Copy, Debug)]
@@ -38,14 +40,25 @@ pub struct OracleStatus {
 }
 
 pub fn get_oracle_status(
-    amm: &AMM,
+    market: &Market,
     oracle_account_info: &AccountInfo,
+    backup_oracle_account_info: Option<&AccountInfo>,
     clock_slot: Slot,
     guard_rails: &OracleGuardRails,
+    extended_guard_rails: &ExtendedOracleGuardRails,
     precomputed_mark_price: Option<u128>,
 ) -> ClearingHouseResult<OracleStatus> {
-    let oracle_price_data = amm.get_oracle_price(oracle_account_info, clock_slot)?;
-    let oracle_is_valid = amm::is_oracle_valid(&oracle_price_data, &guard_rails.validity)?;
+    let amm = &market.amm;
+    let guard_rails = &market.get_oracle_guard_rails(guard_rails);
+    let extended_guard_rails = &market.get_extended_oracle_guard_rails(extended_guard_rails);
+    let (oracle_price_data, oracle_is_valid) = get_oracle_price_data(
+        amm,
+        oracle_account_info,
+        backup_oracle_account_info,
+        clock_slot,
+        &guard_rails.validity,
+        extended_guard_rails,
+    )?;
     let oracle_mark_spread_pct =
         amm::calculate_oracle_mark_spread_pct(amm, &oracle_price_data, 0, precomputed_mark_price)?;
     let is_oracle_mark_too_divergent =
@@ -58,3 +71,88 @@ pub fn get_oracle_status(
         mark_too_divergent: is_oracle_mark_too_divergent,
     })
 }
+
+pub fn get_oracle_price_data(
+    amm: &AMM,
+    oracle_account_info: &AccountInfo,
+    backup_oracle_account_info: Option<&AccountInfo>,
+    clock_slot: Slot,
+    validity_guard_rails: &ValidityGuardRails,
+    extended_guard_rails: &ExtendedOracleGuardRails,
+) -> ClearingHouseResult<(OraclePriceData, bool)> {
+    let oracle_price_data = amm.get_oracle_price(oracle_account_info, clock_slot)?;
+    let oracle_is_valid = amm::is_oracle_valid(
+        &oracle_price_data,
+        validity_guard_rails,
+        extended_guard_rails,
+    )?;
+
+    // A market with a backup oracle always reads both, so the backup cant be left out to pick
+    // which of the two prices is used
+    let backup_oracle_account_info = match backup_oracle_account_info {
+        Some(backup_oracle_account_info) => backup_oracle_account_info,
+        None if amm.backup_oracle.eq(&Pubkey::default()) => {
+            return Ok((oracle_price_data, oracle_is_valid))
+        }
+        None => return Err(ErrorCode::BackupOracleNotFound),
+    };
+
+    // A backup oracle that can't be read leaves the market on its primary oracle
+    let backup_oracle_price_data =
+        match amm.get_oracle_price(backup_oracle_account_info, clock_slot) {
+            Ok(backup_oracle_price_data) => backup_oracle_price_data,
+            Err(error) => {
+                msg!("Could not read backup oracle: {}", error);
+                return Ok((oracle_price_data, oracle_is_valid));
+            }
+        };
+    let backup_oracle_is_valid = amm::is_oracle_valid(
+        &backup_oracle_price_data,
+        validity_guard_rails,
+        extended_guard_rails,
+    )?;
+
+    match (oracle_is_valid, backup_oracle_is_valid) {
+        (true, true) => Ok((
+            calculate_median_oracle_price_data(&oracle_price_data, &backup_oracle_price_data)?,
+            true,
+        )),
+        (false, true) => Ok((backup_oracle_price_data, true)),
+        _ => Ok((oracle_price_data, oracle_is_valid)),
+    }
+}
+
+fn calculate_median_oracle_price_data(
+    oracle_price_data: &OraclePriceData,
+    backup_oracle_price_data: &OraclePriceData,
+) -> ClearingHouseResult<OraclePriceData> {
+    // the median of two readings is their midpoint, confidence, delay and publisher count take the worse of the two
+    Ok(OraclePriceData {
+        price: oracle_price_data
+            .price
+            .checked_add(backup_oracle_price_data.price)
+            .ok_or_else(math_error!())?
+            .checked_div(2)
+            .ok_or_else(math_error!())?,
+        twap: oracle_price_data
+            .twap
+            .checked_add(backup_oracle_price_data.twap)
+            .ok_or_else(math_error!())?
+            .checked_div(2)
+            .ok_or_else(math_error!())?,
+        confidence: max(
+            oracle_price_data.confidence,
+            backup_oracle_price_data.confidence,
+        ),
+        twap_confidence: max(
+            oracle_price_data.twap_confidence,
+            backup_oracle_price_data.twap_confidence,
+        ),
+        delay: max(oracle_price_data.delay, backup_oracle_price_data.delay),
+        status: oracle_price_data.status,
+        num_publishers: min(
+            oracle_price_data.num_publishers,
+            backup_oracle_price_data.num_publishers,
+        ),
+    })
+}
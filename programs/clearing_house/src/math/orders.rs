@@ -2,10 +2,12 @@ use crate::error::*;
 use crate::math;
 use crate::math_error;
 use crate::state::market::Market;
-use crate::state::user_orders::{Order, OrderTriggerCondition, OrderType};
+use crate::state::user_orders::{
+    Order, OrderTriggerCondition, OrderTriggerPriceSource, OrderType, TrailingStopOffsetType,
+};
 use solana_program::msg;
 use std::cell::RefMut;
-use std::cmp::min;
+use std::cmp::{max, min};
 use std::ops::Div;
 
 use crate::controller::amm::SwapDirection;
@@ -15,6 +17,8 @@ use crate::math::amm::calculate_swap_output;
 use crate::math::casting::{cast, cast_to_i128, cast_to_u128};
 use crate::math::constants::{
     AMM_TO_QUOTE_PRECISION_RATIO, MARGIN_PRECISION, MARK_PRICE_PRECISION,
+    MARK_PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO, PRICE_SPREAD_PRECISION_U128,
+    TRAILING_STOP_MIN_RATCHET_DENOMINATOR,
 };
 use crate::math::margin::calculate_free_collateral;
 use crate::math::quote_asset::asset_to_reserve_amount;
@@ -29,7 +33,9 @@ pub fn calculate_base_asset_amount_market_can_execute(
     valid_oracle_price: Option<i128>,
 ) -> ClearingHouseResult<u128> {
     match order.order_type {
-        OrderType::Limit => calculate_base_asset_amount_to_trade_for_limit(order, market),
+        OrderType::Limit => {
+            calculate_base_asset_amount_to_trade_for_limit(order, market, valid_oracle_price)
+        }
         OrderType::TriggerMarket => calculate_base_asset_amount_to_trade_for_trigger_market(
             order,
             market,
@@ -42,6 +48,13 @@ pub fn calculate_base_asset_amount_market_can_execute(
             precomputed_mark_price,
             valid_oracle_price,
         ),
+        OrderType::TrailingStop => calculate_base_asset_amount_to_trade_for_trigger_market(
+            order,
+            market,
+            precomputed_mark_price,
+            valid_oracle_price,
+        ),
+        OrderType::Twap => calculate_base_asset_amount_to_trade_for_twap(order, market),
         OrderType::Market => Err(ErrorCode::InvalidOrder),
     }
 }
@@ -49,14 +62,23 @@ pub fn calculate_base_asset_amount_market_can_execute(
 fn calculate_base_asset_amount_to_trade_for_limit(
     order: &Order,
     market: &Market,
+    valid_oracle_price: Option<i128>,
 ) -> ClearingHouseResult<u128> {
+    let limit_price = match calculate_limit_price(order, valid_oracle_price)? {
+        Some(limit_price) => limit_price,
+        None => {
+            msg!("Cant fill oracle price offset order without valid oracle price");
+            return Ok(0);
+        }
+    };
+
     let base_asset_amount_to_fill = order
         .base_asset_amount
         .checked_sub(order.base_asset_amount_filled)
         .ok_or_else(math_error!())?;
 
     let (max_trade_base_asset_amount, max_trade_direction) =
-        math::amm::calculate_max_base_asset_amount_to_trade(&market.amm, order.price)?;
+        math::amm::calculate_max_base_asset_amount_to_trade(&market.amm, limit_price)?;
     if max_trade_direction != order.direction || max_trade_base_asset_amount == 0 {
         return Ok(0);
     }
@@ -66,21 +88,112 @@ fn calculate_base_asset_amount_to_trade_for_limit(
     Ok(base_asset_amount_to_trade)
 }
 
+// Oracle price offset orders float with the oracle, so their limit price is only known at fill time
+pub fn calculate_limit_price(
+    order: &Order,
+    valid_oracle_price: Option<i128>,
+) -> ClearingHouseResult<Option<u128>> {
+    if order.oracle_price_offset == 0 {
+        return Ok(Some(order.price));
+    }
+
+    let oracle_price = match valid_oracle_price {
+        Some(oracle_price) => oracle_price,
+        None => return Ok(None),
+    };
+
+    let limit_price = oracle_price
+        .checked_add(order.oracle_price_offset)
+        .ok_or_else(math_error!())?;
+
+    if limit_price <= 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(cast_to_u128(limit_price)?))
+}
+
+pub fn calculate_quote_asset_amount_at_price(
+    base_asset_amount: u128,
+    price: u128,
+) -> ClearingHouseResult<u128> {
+    base_asset_amount
+        .checked_mul(price)
+        .ok_or_else(math_error!())?
+        .checked_div(MARK_PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO)
+        .ok_or_else(math_error!())
+}
+
+pub fn order_crosses_amm(
+    order: &Order,
+    market: &Market,
+    valid_oracle_price: Option<i128>,
+) -> ClearingHouseResult<bool> {
+    let limit_price = match calculate_limit_price(order, valid_oracle_price)? {
+        Some(limit_price) => limit_price,
+        None => return Ok(false),
+    };
+
+    let (max_trade_base_asset_amount, max_trade_direction) =
+        math::amm::calculate_max_base_asset_amount_to_trade(&market.amm, limit_price)?;
+
+    Ok(max_trade_direction == order.direction && max_trade_base_asset_amount > 0)
+}
+
 fn calculate_base_asset_amount_to_trade_for_trigger_market(
     order: &Order,
     market: &Market,
     precomputed_mark_price: Option<u128>,
     valid_oracle_price: Option<i128>,
 ) -> ClearingHouseResult<u128> {
-    let mark_price = match precomputed_mark_price {
-        Some(mark_price) => mark_price,
-        None => market.amm.mark_price()?,
+    let trigger_condition_satisfied = match order.trigger_price_source {
+        OrderTriggerPriceSource::Mark => {
+            let mark_price = match precomputed_mark_price {
+                Some(mark_price) => mark_price,
+                None => market.amm.mark_price()?,
+            };
+            is_mark_price_trigger_condition_satisfied(order, mark_price, valid_oracle_price)?
+        }
+        // Oracle sources can't be moved by trading against the amm, but they only trigger while
+        // the oracle is valid
+        OrderTriggerPriceSource::Oracle | OrderTriggerPriceSource::OracleTwap => {
+            match valid_oracle_price {
+                Some(oracle_price) => {
+                    let trigger_source_price =
+                        if order.trigger_price_source == OrderTriggerPriceSource::Oracle {
+                            oracle_price
+                        } else {
+                            market.amm.last_oracle_price_twap
+                        };
+                    is_trigger_condition_satisfied(order, trigger_source_price)?
+                }
+                None => {
+                    msg!("Cant trigger order with oracle trigger price source without valid oracle price");
+                    false
+                }
+            }
+        }
     };
 
+    if !trigger_condition_satisfied {
+        return Ok(0);
+    }
+
+    order
+        .base_asset_amount
+        .checked_sub(order.base_asset_amount_filled)
+        .ok_or_else(math_error!())
+}
+
+fn is_mark_price_trigger_condition_satisfied(
+    order: &Order,
+    mark_price: u128,
+    valid_oracle_price: Option<i128>,
+) -> ClearingHouseResult<bool> {
     match order.trigger_condition {
         OrderTriggerCondition::Above => {
             if mark_price <= order.trigger_price {
-                return Ok(0);
+                return Ok(false);
             }
 
             // If there is a valid oracle, check that trigger condition is also satisfied by
@@ -93,13 +206,13 @@ fn calculate_base_asset_amount_to_trade_for_trigger_market(
                     .ok_or_else(math_error!())?;
 
                 if cast_to_u128(oracle_price_101pct)? <= order.trigger_price {
-                    return Ok(0);
+                    return Ok(false);
                 }
             }
         }
         OrderTriggerCondition::Below => {
             if mark_price >= order.trigger_price {
-                return Ok(0);
+                return Ok(false);
             }
 
             // If there is a valid oracle, check that trigger condition is also satisfied by
@@ -112,16 +225,95 @@ fn calculate_base_asset_amount_to_trade_for_trigger_market(
                     .ok_or_else(math_error!())?;
 
                 if cast_to_u128(oracle_price_99pct)? >= order.trigger_price {
-                    return Ok(0);
+                    return Ok(false);
                 }
             }
         }
     }
 
-    order
-        .base_asset_amount
-        .checked_sub(order.base_asset_amount_filled)
-        .ok_or_else(math_error!())
+    Ok(true)
+}
+
+fn is_trigger_condition_satisfied(
+    order: &Order,
+    trigger_source_price: i128,
+) -> ClearingHouseResult<bool> {
+    let trigger_price = cast_to_i128(order.trigger_price)?;
+    Ok(match order.trigger_condition {
+        OrderTriggerCondition::Above => trigger_source_price > trigger_price,
+        OrderTriggerCondition::Below => trigger_source_price < trigger_price,
+    })
+}
+
+// Trailing stops trail the price their trigger condition is checked against. Oracle sources only
+// move the trigger price while the oracle is valid
+pub fn calculate_trailing_stop_source_price(
+    order: &Order,
+    market: &Market,
+    mark_price: u128,
+    valid_oracle_price: Option<i128>,
+) -> ClearingHouseResult<Option<u128>> {
+    let source_price = match (order.trigger_price_source, valid_oracle_price) {
+        (OrderTriggerPriceSource::Mark, _) => return Ok(Some(mark_price)),
+        (OrderTriggerPriceSource::Oracle, Some(oracle_price)) => oracle_price,
+        (OrderTriggerPriceSource::OracleTwap, Some(_)) => market.amm.last_oracle_price_twap,
+        (_, None) => return Ok(None),
+    };
+
+    Ok(Some(cast_to_u128(source_price)?))
+}
+
+// Trailing stops only ever move their trigger price in the direction that tightens the stop. A
+// short (below) trigger ratchets up as the source price rises and a long (above) trigger ratchets
+// down as it falls. Once placed, the trigger only ratchets when it tightens by at least a tenth of
+// the offset, so a fill attempt cant nudge it for every tick of the source price
+pub fn calculate_trailing_stop_trigger_price(
+    order: &Order,
+    source_price: u128,
+) -> ClearingHouseResult<u128> {
+    let offset = match order.trailing_stop_offset_type {
+        TrailingStopOffsetType::Price => order.trailing_stop_offset,
+        TrailingStopOffsetType::Percentage => source_price
+            .checked_mul(order.trailing_stop_offset)
+            .ok_or_else(math_error!())?
+            .checked_div(PRICE_SPREAD_PRECISION_U128)
+            .ok_or_else(math_error!())?,
+    };
+
+    let trigger_price = match order.trigger_condition {
+        OrderTriggerCondition::Below => source_price.saturating_sub(offset),
+        OrderTriggerCondition::Above => {
+            source_price.checked_add(offset).ok_or_else(math_error!())?
+        }
+    };
+
+    // the order is being placed
+    if order.trigger_price == 0 {
+        return Ok(trigger_price);
+    }
+
+    let min_ratchet = max(offset / TRAILING_STOP_MIN_RATCHET_DENOMINATOR, 1);
+    let tightened = match order.trigger_condition {
+        OrderTriggerCondition::Below => {
+            trigger_price
+                >= order
+                    .trigger_price
+                    .checked_add(min_ratchet)
+                    .ok_or_else(math_error!())?
+        }
+        OrderTriggerCondition::Above => {
+            trigger_price
+                .checked_add(min_ratchet)
+                .ok_or_else(math_error!())?
+                <= order.trigger_price
+        }
+    };
+
+    Ok(if tightened {
+        trigger_price
+    } else {
+        order.trigger_price
+    })
 }
 
 fn calculate_base_asset_amount_to_trade_for_trigger_limit(
@@ -143,7 +335,57 @@ fn calculate_base_asset_amount_to_trade_for_trigger_limit(
         }
     }
 
-    calculate_base_asset_amount_to_trade_for_limit(order, market)
+    calculate_base_asset_amount_to_trade_for_limit(order, market, valid_oracle_price)
+}
+
+// Twap orders trade one slice at a time with the price as a limit for every slice
+fn calculate_base_asset_amount_to_trade_for_twap(
+    order: &Order,
+    market: &Market,
+) -> ClearingHouseResult<u128> {
+    let base_asset_amount_to_fill = order
+        .base_asset_amount
+        .checked_sub(order.base_asset_amount_filled)
+        .ok_or_else(math_error!())?;
+
+    let base_asset_amount_to_trade = min(
+        base_asset_amount_to_fill,
+        calculate_twap_slice_base_asset_amount(order)?,
+    );
+
+    let (max_trade_base_asset_amount, max_trade_direction) =
+        math::amm::calculate_max_base_asset_amount_to_trade(&market.amm, order.price)?;
+    if max_trade_direction != order.direction || max_trade_base_asset_amount == 0 {
+        return Ok(0);
+    }
+
+    Ok(min(base_asset_amount_to_trade, max_trade_base_asset_amount))
+}
+
+// Slices are rounded up so the order is done in at most twap_slice_count fills
+pub fn calculate_twap_slice_base_asset_amount(order: &Order) -> ClearingHouseResult<u128> {
+    let twap_slice_count = cast_to_u128(order.twap_slice_count)?;
+
+    order
+        .base_asset_amount
+        .checked_add(twap_slice_count)
+        .ok_or_else(math_error!())?
+        .checked_sub(1)
+        .ok_or_else(math_error!())?
+        .checked_div(twap_slice_count)
+        .ok_or_else(math_error!())
+}
+
+// The first slice can be filled as soon as the order is placed
+pub fn calculate_twap_next_slice_ts(order: &Order) -> ClearingHouseResult<i64> {
+    if order.twap_last_slice_ts == 0 {
+        return Ok(order.ts);
+    }
+
+    order
+        .twap_last_slice_ts
+        .checked_add(order.twap_interval)
+        .ok_or_else(math_error!())
 }
 
 pub fn calculate_base_asset_amount_user_can_execute(
@@ -195,7 +437,64 @@ pub fn calculate_base_asset_amount_user_can_execute(
         .ok_or_else(math_error!())?
         .unsigned_abs();
 
-    Ok(base_asset_amount)
+    // Never trade past the position limit, using the position as it is at fill time
+    match calculate_base_asset_amount_to_position_limit(
+        order,
+        user_positions.positions[position_index].base_asset_amount,
+    )? {
+        Some(base_asset_amount_to_position_limit) => {
+            Ok(min(base_asset_amount, base_asset_amount_to_position_limit))
+        }
+        None => Ok(base_asset_amount),
+    }
+}
+
+// A position limit of N caps a long order at a position of +N and a short order at -N. None if
+// the order has no position limit
+pub fn calculate_base_asset_amount_to_position_limit(
+    order: &Order,
+    position_base_asset_amount: i128,
+) -> ClearingHouseResult<Option<u128>> {
+    if order.position_limit == 0 {
+        return Ok(None);
+    }
+
+    let position_limit = cast_to_i128(order.position_limit)?;
+    let base_asset_amount_to_position_limit = match order.direction {
+        PositionDirection::Long => position_limit
+            .checked_sub(position_base_asset_amount)
+            .ok_or_else(math_error!())?,
+        PositionDirection::Short => position_limit
+            .checked_add(position_base_asset_amount)
+            .ok_or_else(math_error!())?,
+    };
+
+    Ok(Some(cast_to_u128(max(
+        base_asset_amount_to_position_limit,
+        0,
+    ))?))
+}
+
+// A reduce only order can fill up to the size of a position on the other side of the order and
+// nothing once the position is closed or on the same side. None if the order isnt reduce only
+pub fn calculate_base_asset_amount_to_close_position(
+    order: &Order,
+    position_base_asset_amount: i128,
+) -> Option<u128> {
+    if !order.reduce_only {
+        return None;
+    }
+
+    let order_reduces_position = match order.direction {
+        PositionDirection::Long => position_base_asset_amount < 0,
+        PositionDirection::Short => position_base_asset_amount > 0,
+    };
+
+    if order_reduces_position {
+        Some(position_base_asset_amount.unsigned_abs())
+    } else {
+        Some(0)
+    }
 }
 
 pub fn calculate_available_quote_asset_user_can_execute(
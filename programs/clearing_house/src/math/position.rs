@@ -3,6 +3,7 @@ use crate::controller::position::PositionDirection;
 use crate::error::*;
 use crate::math::amm;
 use crate::math::amm::calculate_quote_asset_amount_swapped;
+use crate::math::casting::cast_to_i128;
 use crate::math::constants::{AMM_RESERVE_PRECISION, PRICE_TO_QUOTE_PRECISION_RATIO};
 use crate::math::pnl::calculate_pnl;
 use crate::math_error;
@@ -84,6 +85,24 @@ pub fn calculate_base_asset_value_and_pnl_with_oracle_price(
     Ok((base_asset_value, pnl))
 }
 
+// Values longs at the bottom of the oracle's confidence interval and shorts at the top
+pub fn calculate_conservative_oracle_price(
+    base_asset_amount: i128,
+    oracle_price: i128,
+    oracle_confidence: u128,
+) -> ClearingHouseResult<i128> {
+    let oracle_confidence = cast_to_i128(oracle_confidence)?;
+    if base_asset_amount > 0 {
+        oracle_price
+            .checked_sub(oracle_confidence)
+            .ok_or_else(math_error!())
+    } else {
+        oracle_price
+            .checked_add(oracle_confidence)
+            .ok_or_else(math_error!())
+    }
+}
+
 pub fn direction_to_close_position(base_asset_amount: i128) -> PositionDirection {
     if base_asset_amount > 0 {
         PositionDirection::Short
@@ -1,7 +1,8 @@
 use crate::context::{InitializeUserOptionalAccounts, ManagePositionOptionalAccounts};
 use crate::error::{ClearingHouseResult, ErrorCode};
-use crate::state::user::User;
-use crate::state::user_orders::UserOrders;
+use crate::state::market::AMM;
+use crate::state::user::{User, UserPositions};
+use crate::state::user_orders::{UserOrders, UserOrdersLoader};
 use anchor_lang::prelude::{AccountInfo, Pubkey};
 use anchor_lang::{Account, AccountLoader};
 use solana_program::account_info::next_account_info;
@@ -144,9 +145,7 @@ pub fn get_referrer_for_fill_order<'a, 'b, 'c>(
     order_id: u128,
     user_orders: &AccountLoader<UserOrders>,
 ) -> ClearingHouseResult<Option<Account<'b, User>>> {
-    let user_orders = &user_orders
-        .load()
-        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+    let user_orders = &user_orders.load_orders()?;
     let order_index = user_orders
         .orders
         .iter()
@@ -171,3 +170,94 @@ pub fn get_referrer_for_fill_order<'a, 'b, 'c>(
 
     Ok(referrer)
 }
+
+pub fn get_referrer_for_fill_orders<'a>(
+    accounts: &[AccountInfo<'a>],
+    user_public_key: &Pubkey,
+    order_id: u128,
+    user_orders: &AccountLoader<UserOrders>,
+) -> ClearingHouseResult<Option<Account<'a, User>>> {
+    let expected_referrer = {
+        let user_orders = &user_orders.load_orders()?;
+        user_orders
+            .orders
+            .iter()
+            .find(|order| order.order_id == order_id)
+            .ok_or(ErrorCode::OrderDoesNotExist)?
+            .referrer
+    };
+
+    if expected_referrer.eq(&Pubkey::default()) {
+        return Ok(None);
+    }
+
+    // referrers sit among the trailing accounts in no particular order, so find them by key
+    let referrer_account_info = accounts
+        .iter()
+        .find(|account_info| account_info.key.eq(&expected_referrer))
+        .ok_or(ErrorCode::ReferrerNotFound)?;
+
+    get_referrer(
+        true,
+        &mut std::slice::from_ref(referrer_account_info).iter(),
+        user_public_key,
+        Some(&expected_referrer),
+    )
+    .or_else(|error| match error {
+        ErrorCode::CouldNotDeserializeReferrer => Ok(None),
+        _ => Err(error),
+    })
+}
+
+pub type FillOrdersUserAccounts<'a> = (
+    Box<Account<'a, User>>,
+    AccountLoader<'a, UserPositions>,
+    AccountLoader<'a, UserOrders>,
+);
+
+pub fn get_user_accounts_for_fill_orders<'a>(
+    account_infos: &[AccountInfo<'a>],
+) -> ClearingHouseResult<FillOrdersUserAccounts<'a>> {
+    if account_infos.len() != 3
+        || account_infos
+            .iter()
+            .any(|account_info| !account_info.is_writable)
+    {
+        return Err(ErrorCode::InvalidFillOrdersAccounts);
+    }
+
+    let user: Box<Account<User>> = Box::new(
+        Account::try_from(&account_infos[0]).or(Err(ErrorCode::InvalidFillOrdersAccounts))?,
+    );
+    let user_positions: AccountLoader<UserPositions> =
+        AccountLoader::try_from(&account_infos[1]).or(Err(ErrorCode::InvalidFillOrdersAccounts))?;
+    let user_orders: AccountLoader<UserOrders> =
+        AccountLoader::try_from(&account_infos[2]).or(Err(ErrorCode::InvalidFillOrdersAccounts))?;
+
+    // same checks the FillOrder accounts constraints make for a single fill
+    if !user.positions.eq(account_infos[1].key)
+        || !user_positions
+            .load()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?
+            .user
+            .eq(account_infos[0].key)
+        || !user_orders.load_orders()?.user.eq(account_infos[0].key)
+    {
+        return Err(ErrorCode::InvalidFillOrdersAccounts);
+    }
+
+    Ok((user, user_positions, user_orders))
+}
+
+pub fn get_backup_oracle<'a, 'b>(
+    amm: &AMM,
+    accounts: &'a [AccountInfo<'b>],
+) -> Option<&'a AccountInfo<'b>> {
+    if amm.backup_oracle.eq(&Pubkey::default()) {
+        return None;
+    }
+
+    accounts
+        .iter()
+        .find(|account_info| account_info.key.eq(&amm.backup_oracle))
+}
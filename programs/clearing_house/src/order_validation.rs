@@ -1,10 +1,13 @@
 use crate::controller::position::PositionDirection;
 use crate::error::*;
 use crate::math::constants::*;
+use crate::math::orders::{calculate_limit_price, calculate_twap_slice_base_asset_amount};
 use crate::math::quote_asset::asset_to_reserve_amount;
 use crate::state::market::Market;
 use crate::state::order_state::OrderState;
-use crate::state::user_orders::{Order, OrderTriggerCondition, OrderType};
+use crate::state::user_orders::{
+    Order, OrderTriggerCondition, OrderTriggerPriceSource, OrderType, TrailingStopOffsetType,
+};
 
 use solana_program::msg;
 use std::ops::Div;
@@ -19,20 +22,58 @@ pub fn validate_order(
         OrderType::Limit => validate_limit_order(order, market, order_state)?,
         OrderType::TriggerMarket => validate_trigger_market_order(order, market, order_state)?,
         OrderType::TriggerLimit => validate_trigger_limit_order(order, market, order_state)?,
+        OrderType::TrailingStop => validate_trailing_stop_order(order, market, order_state)?,
+        OrderType::Twap => validate_twap_order(order, market, order_state)?,
     }
 
-    if order.immediate_or_cancel {
-        msg!("immediate_or_cancel not supported yet");
+    if order.post_only && (order.immediate_or_cancel || order.fill_or_kill) {
+        msg!("post_only orders cant be immediate_or_cancel or fill_or_kill");
         return Err(ErrorCode::InvalidOrder);
     }
 
-    if order.post_only {
-        msg!("post_only not supported yet");
+    if order.fill_or_kill && order.base_asset_amount == 0 {
+        msg!("fill_or_kill orders must specify base_asset_amount");
         return Err(ErrorCode::InvalidOrder);
     }
 
-    if order.oracle_price_offset != 0 {
-        msg!("oracle_price_offset not supported yet");
+    if order.post_only && order.order_type != OrderType::Limit {
+        msg!("post_only only supported for limit orders");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if order.max_ts != 0 && order.max_ts <= order.ts {
+        msg!("Order max_ts must be in the future");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if order.oracle_price_offset != 0 && order.order_type != OrderType::Limit {
+        msg!("oracle_price_offset only supported for limit orders");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if order.trailing_stop_offset != 0 && order.order_type != OrderType::TrailingStop {
+        msg!("trailing_stop_offset only supported for trailing stop orders");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if order.position_limit != 0 && order.order_type == OrderType::Market {
+        msg!("position_limit not supported for market orders");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if order.trigger_price_source != OrderTriggerPriceSource::Mark
+        && order.order_type != OrderType::TriggerMarket
+        && order.order_type != OrderType::TriggerLimit
+        && order.order_type != OrderType::TrailingStop
+    {
+        msg!("trigger_price_source only supported for trigger and trailing stop orders");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if (order.twap_slice_count != 0 || order.twap_interval != 0)
+        && order.order_type != OrderType::Twap
+    {
+        msg!("twap_slice_count and twap_interval only supported for twap orders");
         return Err(ErrorCode::InvalidOrder);
     }
 
@@ -66,7 +107,12 @@ fn validate_limit_order(
 ) -> ClearingHouseResult {
     validate_base_asset_amount(order, market)?;
 
-    if order.price == 0 {
+    if order.oracle_price_offset != 0 {
+        if order.price != 0 {
+            msg!("Oracle price offset limit order should not have price");
+            return Err(ErrorCode::InvalidOrder);
+        }
+    } else if order.price == 0 {
         msg!("Limit order price == 0");
         return Err(ErrorCode::InvalidOrder);
     }
@@ -81,8 +127,10 @@ fn validate_limit_order(
         return Err(ErrorCode::InvalidOrder);
     }
 
-    let approximate_market_value = order
-        .price
+    // Oracle price offset orders are valued at the last oracle price the market saw
+    let approximate_price =
+        calculate_limit_price(order, Some(market.amm.last_oracle_price))?.unwrap_or(0);
+    let approximate_market_value = approximate_price
         .checked_mul(order.base_asset_amount)
         .or(Some(u128::MAX))
         .unwrap()
@@ -189,6 +237,123 @@ fn validate_trigger_market_order(
     Ok(())
 }
 
+fn validate_trailing_stop_order(
+    order: &Order,
+    market: &Market,
+    order_state: &OrderState,
+) -> ClearingHouseResult {
+    validate_base_asset_amount(order, market)?;
+
+    if order.price > 0 {
+        msg!("Trailing stop order should not have price");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if order.quote_asset_amount != 0 {
+        msg!("Trailing stop order should not have a quote asset amount");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if order.trailing_stop_offset == 0 {
+        msg!("Trailing stop order trailing_stop_offset == 0");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if order.trailing_stop_offset_type == TrailingStopOffsetType::Percentage
+        && order.trailing_stop_offset >= PRICE_SPREAD_PRECISION_U128
+    {
+        msg!("Trailing stop order percentage offset must be less than 100%");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    // A trailing stop closes out the opposite side, so it trails below the mark when selling
+    // and above the mark when buying
+    match order.direction {
+        PositionDirection::Long => {
+            if order.trigger_condition != OrderTriggerCondition::Above {
+                msg!("Long trailing stop order must have trigger condition above");
+                return Err(ErrorCode::InvalidOrder);
+            }
+        }
+        PositionDirection::Short => {
+            if order.trigger_condition != OrderTriggerCondition::Below {
+                msg!("Short trailing stop order must have trigger condition below");
+                return Err(ErrorCode::InvalidOrder);
+            }
+        }
+    }
+
+    if order.trigger_price == 0 {
+        msg!("Trailing stop order trigger_price == 0");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    let approximate_market_value = order
+        .trigger_price
+        .saturating_mul(order.base_asset_amount)
+        .div(AMM_RESERVE_PRECISION)
+        .div(MARK_PRICE_PRECISION / QUOTE_PRECISION);
+
+    if approximate_market_value < order_state.min_order_quote_asset_amount {
+        msg!("Order value < $0.50 ({:?})", approximate_market_value);
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    Ok(())
+}
+
+fn validate_twap_order(
+    order: &Order,
+    market: &Market,
+    order_state: &OrderState,
+) -> ClearingHouseResult {
+    validate_base_asset_amount(order, market)?;
+
+    if order.trigger_price > 0 {
+        msg!("Twap order should not have trigger price");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if order.quote_asset_amount != 0 {
+        msg!("Twap order should not have a quote asset amount");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    // Without a limit price a filler could push the mark before each slice
+    if order.price == 0 {
+        msg!("Twap order price == 0");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if order.twap_slice_count == 0 {
+        msg!("Twap order twap_slice_count == 0");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if order.twap_interval <= 0 {
+        msg!("Twap order twap_interval must be positive");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    if calculate_twap_slice_base_asset_amount(order)? < market.amm.minimum_base_asset_trade_size {
+        msg!("Twap order slice smaller than market minimum_base_asset_trade_size");
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    let approximate_market_value = order
+        .price
+        .saturating_mul(order.base_asset_amount)
+        .div(AMM_RESERVE_PRECISION)
+        .div(MARK_PRICE_PRECISION / QUOTE_PRECISION);
+
+    if approximate_market_value < order_state.min_order_quote_asset_amount {
+        msg!("Order value < $0.50 ({:?})", approximate_market_value);
+        return Err(ErrorCode::InvalidOrder);
+    }
+
+    Ok(())
+}
+
 fn validate_base_asset_amount(order: &Order, market: &Market) -> ClearingHouseResult {
     if order.base_asset_amount == 0 {
         msg!("Order base_asset_amount cant be 0");
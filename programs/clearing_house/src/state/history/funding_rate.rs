@@ -21,6 +21,19 @@ impl FundingRateHistory {
         let prev_record = &self.funding_rate_records[FundingRateHistory::index_of(prev_record_id)];
         prev_record.record_id + 1
     }
+
+    // The head and the record the next append overwrites, which is enough to undo that append
+    pub fn snapshot_head(&self) -> (u64, FundingRateRecord) {
+        (
+            self.head,
+            self.funding_rate_records[FundingRateHistory::index_of(self.head)],
+        )
+    }
+
+    pub fn restore_head(&mut self, (head, record): (u64, FundingRateRecord)) {
+        self.funding_rate_records[FundingRateHistory::index_of(head)] = record;
+        self.head = head;
+    }
 }
 
 #[zero_copy]
@@ -3,5 +3,6 @@ pub mod deposit;
 pub mod funding_payment;
 pub mod funding_rate;
 pub mod liquidation;
+pub mod oracle;
 pub mod order_history;
 pub mod trade;
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+
+#[account(zero_copy)]
+pub struct OracleHistory {
+    head: u64,
+    oracle_records: [OracleRecord; 1024],
+}
+
+impl OracleHistory {
+    pub fn append(&mut self, pos: OracleRecord) {
+        self.oracle_records[OracleHistory::index_of(self.head)] = pos;
+        self.head = (self.head + 1) % 1024;
+    }
+
+    pub fn index_of(counter: u64) -> usize {
+        std::convert::TryInto::try_into(counter).unwrap()
+    }
+
+    pub fn next_record_id(&self) -> u128 {
+        let prev_record_id = if self.head == 0 { 1023 } else { self.head - 1 };
+        let prev_record = &self.oracle_records[OracleHistory::index_of(prev_record_id)];
+        prev_record.record_id + 1
+    }
+}
+
+#[derive(Clone, Copy, AnchorSerialize, AnchorDeserialize, PartialEq)]
+pub enum OracleRecordType {
+    Invalid,
+    TooDivergent,
+    Recovered,
+}
+
+impl Default for OracleRecordType {
+    // UpOnly
+    fn default() -> Self {
+        OracleRecordType::Invalid
+    }
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct OracleRecord {
+    pub ts: i64,
+    pub record_id: u128,
+    pub market_index: u64,
+    pub oracle: Pubkey,
+    pub record_type: OracleRecordType,
+    pub oracle_price: i128,
+    pub oracle_confidence: u128,
+    pub oracle_delay: i64,
+    pub mark_price: u128,
+    pub oracle_mark_spread_pct: i128,
+    pub consecutive_invalid_readings: u64,
+    pub reduce_only: bool,
+}
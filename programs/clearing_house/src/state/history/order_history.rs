@@ -1,6 +1,10 @@
-use crate::state::user_orders::Order;
+use crate::error::{ClearingHouseResult, ErrorCode};
+use crate::state::user_orders::{LegacyOrder, Order};
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
 use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use std::cell::Ref;
 
 #[account(zero_copy)]
 pub struct OrderHistory {
@@ -50,11 +54,94 @@ pub struct OrderRecord {
     pub padding: [u64; 10],
 }
 
+// The layout of order history accounts before orders outgrew their padding. They have the same
+// discriminator as OrderHistory, so they are told apart by their size
+#[zero_copy]
+pub struct LegacyOrderHistory {
+    head: u64,
+    pub last_order_id: u128,
+    order_records: [LegacyOrderRecord; 1024],
+}
+
+unsafe impl Zeroable for LegacyOrderHistory {}
+unsafe impl Pod for LegacyOrderHistory {}
+
+impl LegacyOrderHistory {
+    pub fn last_record(&self) -> &LegacyOrderRecord {
+        let last_record_id = if self.head == 0 { 1023 } else { self.head - 1 };
+        &self.order_records[OrderHistory::index_of(last_record_id)]
+    }
+}
+
+pub fn load_legacy_order_history<'a>(
+    account_info: &'a AccountInfo,
+) -> ClearingHouseResult<Ref<'a, LegacyOrderHistory>> {
+    if account_info.owner != &crate::ID {
+        return Err(ErrorCode::InvalidOrderHistoryAccount);
+    }
+
+    let data = account_info
+        .try_borrow_data()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    if data.len() != 8 + std::mem::size_of::<LegacyOrderHistory>()
+        || data[..8] != OrderHistory::discriminator()
+    {
+        return Err(ErrorCode::InvalidOrderHistoryAccount);
+    }
+
+    Ok(Ref::map(data, |data| bytemuck::from_bytes(&data[8..])))
+}
+
+#[zero_copy]
+pub struct LegacyOrderRecord {
+    pub ts: i64,
+    pub record_id: u128,
+    pub user: Pubkey,
+    pub authority: Pubkey,
+    pub order: LegacyOrder,
+    pub action: OrderAction,
+    pub filler: Pubkey,
+    pub trade_record_id: u128,
+    pub base_asset_amount_filled: u128,
+    pub quote_asset_amount_filled: u128,
+    pub fee: u128,
+    pub filler_reward: u128,
+    pub padding: [u64; 10],
+}
+
+unsafe impl Zeroable for LegacyOrderRecord {}
+unsafe impl Pod for LegacyOrderRecord {}
+
+impl LegacyOrderRecord {
+    pub fn to_order_record(&self) -> OrderRecord {
+        OrderRecord {
+            ts: self.ts,
+            record_id: self.record_id,
+            user: self.user,
+            authority: self.authority,
+            order: self.order.to_order(),
+            action: self.action,
+            filler: self.filler,
+            trade_record_id: self.trade_record_id,
+            base_asset_amount_filled: self.base_asset_amount_filled,
+            quote_asset_amount_filled: self.quote_asset_amount_filled,
+            fee: self.fee,
+            filler_reward: self.filler_reward,
+            padding: [0; 10],
+        }
+    }
+}
+
 #[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq)]
 pub enum OrderAction {
     Place,
     Cancel,
     Fill,
+    PostOnlyCancel,
+    Expire,
+    Modify,
+    ReduceOnlyCancel,
 }
 
 impl Default for OrderAction {
@@ -4,7 +4,16 @@ use crate::error::*;
 use crate::math::amm;
 use crate::math::casting::{cast, cast_to_i128, cast_to_i64, cast_to_u128};
 use crate::math_error;
+use crate::state::oracle_basket::{OracleBasket, OracleBasketType};
+use crate::state::state::{
+    ExtendedOracleGuardRails, OracleGuardRails, PriceDivergenceGuardRails, ValidityGuardRails,
+};
+use crate::state::switchboard::{
+    AggregatorAccountData, SwitchboardDecimal, AGGREGATOR_ACCOUNT_DISCRIMINATOR,
+    AGGREGATOR_ACCOUNT_SIZE,
+};
 use crate::MARK_PRICE_PRECISION;
+use pyth_client::{CorpAction, PriceStatus};
 use solana_program::msg;
 
 #[account(zero_copy)]
@@ -43,19 +52,115 @@ pub struct Market {
     pub base_asset_amount: i128, // net market bias
     pub open_interest: u128,     // number of users in a position
     pub amm: AMM,
+    pub oracle_guard_rails: MarketOracleGuardRails,
+    pub consecutive_invalid_oracle_readings: u64,
+    pub reduce_only: bool, // set by the oracle circuit breaker
+    pub last_invalid_oracle_reading_slot: u64,
 
     // upgrade-ability
-    pub padding0: u128,
-    pub padding1: u128,
-    pub padding2: u128,
-    pub padding3: u128,
-    pub padding4: u128,
+    pub padding3: [u8; 7],
+    pub padding4: u64,
+}
+
+impl Market {
+    pub fn get_oracle_guard_rails(&self, default: &OracleGuardRails) -> OracleGuardRails {
+        if self.oracle_guard_rails.enabled {
+            self.oracle_guard_rails.to_oracle_guard_rails()
+        } else {
+            default.clone()
+        }
+    }
+
+    pub fn get_extended_oracle_guard_rails(
+        &self,
+        default: &ExtendedOracleGuardRails,
+    ) -> ExtendedOracleGuardRails {
+        if self.oracle_guard_rails.enabled {
+            self.oracle_guard_rails.to_extended_oracle_guard_rails()
+        } else {
+            default.clone()
+        }
+    }
+}
+
+// Overrides the state's oracle guard rails for a single market when enabled
+#[zero_copy]
+#[derive(Default)]
+pub struct MarketOracleGuardRails {
+    pub enabled: bool,
+    pub use_for_liquidations: bool,
+    pub require_trading_status: bool,
+    pub min_publishers: u32,
+    pub slots_before_stale: i64,
+    pub confidence_interval_max_size: u64,
+    pub too_volatile_ratio: i64,
+    pub mark_oracle_divergence_numerator: u64,
+    pub mark_oracle_divergence_denominator: u64,
+    pub use_confidence_interval_for_margin: bool,
+}
+
+impl MarketOracleGuardRails {
+    pub fn from_oracle_guard_rails(
+        oracle_guard_rails: &OracleGuardRails,
+        extended_oracle_guard_rails: &ExtendedOracleGuardRails,
+    ) -> ClearingHouseResult<MarketOracleGuardRails> {
+        Ok(MarketOracleGuardRails {
+            enabled: true,
+            use_for_liquidations: oracle_guard_rails.use_for_liquidations,
+            require_trading_status: extended_oracle_guard_rails.require_trading_status,
+            min_publishers: extended_oracle_guard_rails.min_publishers,
+            slots_before_stale: oracle_guard_rails.validity.slots_before_stale,
+            confidence_interval_max_size: cast(
+                oracle_guard_rails.validity.confidence_interval_max_size,
+            )?,
+            too_volatile_ratio: cast(oracle_guard_rails.validity.too_volatile_ratio)?,
+            mark_oracle_divergence_numerator: cast(
+                oracle_guard_rails
+                    .price_divergence
+                    .mark_oracle_divergence_numerator,
+            )?,
+            mark_oracle_divergence_denominator: cast(
+                oracle_guard_rails
+                    .price_divergence
+                    .mark_oracle_divergence_denominator,
+            )?,
+            use_confidence_interval_for_margin: extended_oracle_guard_rails
+                .use_confidence_interval_for_margin,
+        })
+    }
+
+    pub fn to_oracle_guard_rails(&self) -> OracleGuardRails {
+        OracleGuardRails {
+            price_divergence: PriceDivergenceGuardRails {
+                mark_oracle_divergence_numerator: u128::from(self.mark_oracle_divergence_numerator),
+                mark_oracle_divergence_denominator: u128::from(
+                    self.mark_oracle_divergence_denominator,
+                ),
+            },
+            validity: ValidityGuardRails {
+                slots_before_stale: self.slots_before_stale,
+                confidence_interval_max_size: u128::from(self.confidence_interval_max_size),
+                too_volatile_ratio: i128::from(self.too_volatile_ratio),
+            },
+            use_for_liquidations: self.use_for_liquidations,
+        }
+    }
+
+    pub fn to_extended_oracle_guard_rails(&self) -> ExtendedOracleGuardRails {
+        ExtendedOracleGuardRails {
+            require_trading_status: self.require_trading_status,
+            min_publishers: self.min_publishers,
+            use_confidence_interval_for_margin: self.use_confidence_interval_for_margin,
+        }
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
 pub enum OracleSource {
     Pyth,
     Switchboard,
+    Basket,
+    Ratio,
 }
 
 impl Default for OracleSource {
@@ -91,11 +196,11 @@ pub struct AMM {
     pub last_oracle_price_twap_ts: i64,
     pub last_oracle_price: i128,
     pub minimum_base_asset_trade_size: u128,
+    pub backup_oracle: Pubkey,
+    pub backup_oracle_source: OracleSource,
 
     // upgrade-ability
-    pub padding1: u64,
-    pub padding2: u128,
-    pub padding3: u128,
+    pub padding1: [u8; 7],
 }
 
 impl AMM {
@@ -107,11 +212,19 @@ impl AMM {
         )
     }
 
+    pub fn is_backup_oracle(&self, oracle: &Pubkey) -> bool {
+        !self.backup_oracle.eq(&Pubkey::default()) && self.backup_oracle.eq(oracle)
+    }
+
+    // The backup oracle is only ever read alongside the primary oracle, never in its place
+    pub fn is_market_oracle(&self, oracle: &Pubkey) -> bool {
+        self.oracle.eq(oracle)
+    }
+
     pub fn get_pyth_price(
-        &self,
         price_oracle: &AccountInfo,
         clock_slot: u64,
-    ) -> ClearingHouseResult<(i128, i128, u128, u128, i64)> {
+    ) -> ClearingHouseResult<OraclePriceData> {
         let pyth_price_data = price_oracle
             .try_borrow_data()
             .or(Err(ErrorCode::UnableToLoadOracle))?;
@@ -165,32 +278,143 @@ impl AMM {
             .checked_sub(cast(price_data.valid_slot)?)
             .ok_or_else(math_error!())?;
 
-        Ok((
-            oracle_price_scaled,
-            oracle_twap_scaled,
-            oracle_conf_scaled,
-            oracle_twac_scaled,
-            oracle_delay,
-        ))
+        let oracle_status = match (&price_data.agg.status, &price_data.agg.corp_act) {
+            (PriceStatus::Trading, CorpAction::NoCorpAct) => OraclePriceStatus::Trading,
+            (PriceStatus::Halted, _) => OraclePriceStatus::Halted,
+            (PriceStatus::Auction, _) => OraclePriceStatus::Auction,
+            _ => OraclePriceStatus::Unknown,
+        };
+
+        Ok(OraclePriceData {
+            price: oracle_price_scaled,
+            twap: oracle_twap_scaled,
+            confidence: oracle_conf_scaled,
+            twap_confidence: oracle_twac_scaled,
+            delay: oracle_delay,
+            status: oracle_status,
+            num_publishers: price_data.num_qt,
+        })
     }
 
-    pub fn get_oracle_price(
-        &self,
+    pub fn get_switchboard_price(
         price_oracle: &AccountInfo,
         clock_slot: u64,
     ) -> ClearingHouseResult<OraclePriceData> {
-        let (price, twap, confidence, twap_confidence, delay) = match self.oracle_source {
-            OracleSource::Pyth => self.get_pyth_price(price_oracle, clock_slot)?,
-            OracleSource::Switchboard => (0, 0, 0, 0, 0),
+        let aggregator_data = price_oracle
+            .try_borrow_data()
+            .or(Err(ErrorCode::UnableToLoadOracle))?;
+
+        let aggregator_size = std::mem::size_of::<AggregatorAccountData>();
+        if aggregator_data.len() < AGGREGATOR_ACCOUNT_SIZE
+            || aggregator_data[..8] != AGGREGATOR_ACCOUNT_DISCRIMINATOR
+        {
+            return Err(ErrorCode::UnableToLoadOracle);
+        }
+
+        let aggregator =
+            bytemuck::from_bytes::<AggregatorAccountData>(&aggregator_data[8..aggregator_size + 8]);
+        let round = &aggregator.latest_confirmed_round;
+
+        let oracle_price_scaled = convert_switchboard_decimal(&round.result)?;
+        let oracle_conf_scaled = cast_to_u128(convert_switchboard_decimal(&round.std_deviation)?)?;
+
+        let oracle_delay: i64 = cast_to_i64(clock_slot)?
+            .checked_sub(cast(round.round_open_slot)?)
+            .ok_or_else(math_error!())?;
+
+        let oracle_status = if round.num_success >= aggregator.min_oracle_results {
+            OraclePriceStatus::Trading
+        } else {
+            OraclePriceStatus::Unknown
         };
+
+        // switchboard doesn't publish a twap, so the latest round stands in for it until
+        // get_oracle_price swaps in the amm's twap of the readings
         Ok(OraclePriceData {
-            price,
-            twap,
-            confidence,
-            twap_confidence,
-            delay,
+            price: oracle_price_scaled,
+            twap: oracle_price_scaled,
+            confidence: oracle_conf_scaled,
+            twap_confidence: oracle_conf_scaled,
+            delay: oracle_delay,
+            status: oracle_status,
+            num_publishers: round.num_success,
         })
     }
+
+    pub fn get_oracle_price(
+        &self,
+        price_oracle: &AccountInfo,
+        clock_slot: u64,
+    ) -> ClearingHouseResult<OraclePriceData> {
+        let oracle_source = if self.is_backup_oracle(price_oracle.key) {
+            self.backup_oracle_source
+        } else {
+            self.oracle_source
+        };
+
+        match oracle_source {
+            OracleSource::Pyth => AMM::get_pyth_price(price_oracle, clock_slot),
+            OracleSource::Switchboard => {
+                let mut oracle_price_data = AMM::get_switchboard_price(price_oracle, clock_slot)?;
+                if self.last_oracle_price_twap > 0 {
+                    oracle_price_data.twap = self.last_oracle_price_twap;
+                }
+                Ok(oracle_price_data)
+            }
+            OracleSource::Basket => {
+                AMM::get_basket_price(price_oracle, OracleBasketType::WeightedSum, clock_slot)
+            }
+            OracleSource::Ratio => {
+                AMM::get_basket_price(price_oracle, OracleBasketType::Ratio, clock_slot)
+            }
+        }
+    }
+
+    pub fn get_basket_price(
+        price_oracle: &AccountInfo,
+        basket_type: OracleBasketType,
+        clock_slot: u64,
+    ) -> ClearingHouseResult<OraclePriceData> {
+        let oracle_basket_loader = AccountLoader::<OracleBasket>::try_from(price_oracle)
+            .or(Err(ErrorCode::UnableToLoadOracle))?;
+        let oracle_basket = oracle_basket_loader
+            .load()
+            .or(Err(ErrorCode::UnableToLoadOracle))?;
+
+        if oracle_basket.basket_type != basket_type {
+            return Err(ErrorCode::InvalidOracleBasket);
+        }
+
+        oracle_basket.get_price_data(clock_slot)
+    }
+}
+
+fn convert_switchboard_decimal(
+    switchboard_decimal: &SwitchboardDecimal,
+) -> ClearingHouseResult<i128> {
+    let switchboard_precision = 10_u128
+        .checked_pow(switchboard_decimal.scale)
+        .ok_or_else(math_error!())?;
+
+    if switchboard_precision > MARK_PRICE_PRECISION {
+        switchboard_decimal
+            .mantissa
+            .checked_div(cast(
+                switchboard_precision
+                    .checked_div(MARK_PRICE_PRECISION)
+                    .ok_or_else(math_error!())?,
+            )?)
+            .ok_or_else(math_error!())
+    } else {
+        switchboard_decimal
+            .mantissa
+            .checked_mul(cast(
+                MARK_PRICE_PRECISION
+                    .checked_div(switchboard_precision)
+                    .ok_or_else(math_error!())?,
+            )?)
+            .ok_or_else(math_error!())
+    }
 }
 
 #[derive(Default, Clone, Copy, Debug)]
@@ -200,4 +424,20 @@ pub struct OraclePriceData {
     pub confidence: u128,
     pub twap_confidence: u128,
     pub delay: i64,
+    pub status: OraclePriceStatus,
+    pub num_publishers: u32,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq)]
+pub enum OraclePriceStatus {
+    Unknown,
+    Trading,
+    Halted,
+    Auction,
+}
+
+impl Default for OraclePriceStatus {
+    fn default() -> Self {
+        OraclePriceStatus::Unknown
+    }
 }
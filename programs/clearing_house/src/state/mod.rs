@@ -1,7 +1,9 @@
 pub mod history;
 pub mod market;
+pub mod oracle_basket;
 pub mod order_state;
 #[allow(clippy::module_inception)]
 pub mod state;
+pub mod switchboard;
 pub mod user;
 pub mod user_orders;
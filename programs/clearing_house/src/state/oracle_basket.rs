@@ -0,0 +1,277 @@
+use anchor_lang::prelude::*;
+
+use crate::error::*;
+use crate::math::casting::{cast, cast_to_i128, cast_to_i64, cast_to_u128};
+use crate::math::constants::{MARK_PRICE_PRECISION, ORACLE_BASKET_WEIGHT_PRECISION};
+use crate::math_error;
+use crate::state::market::{OraclePriceData, OraclePriceStatus, AMM};
+use solana_program::msg;
+use std::cmp::{max, min};
+
+pub const MAX_ORACLE_BASKET_COMPONENTS: usize = 8;
+
+// Cranked by update_oracle_basket with the components as remaining accounts. Markets then read the
+// cached price like any other single oracle account, and its valid slot is the oldest component's
+#[account(zero_copy)]
+pub struct OracleBasket {
+    pub num_components: u64,
+    pub components: [OracleBasketComponent; 8],
+    pub price: i128,
+    pub twap: i128,
+    pub confidence: u128,
+    pub twap_confidence: u128,
+    pub valid_slot: u64,
+    pub status: OraclePriceStatus,
+    pub num_publishers: u32,
+    pub basket_type: OracleBasketType,
+
+    // upgrade-ability
+    pub padding0: u128,
+    pub padding1: u128,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq)]
+pub enum OracleBasketType {
+    WeightedSum,
+    Ratio,
+}
+
+impl Default for OracleBasketType {
+    // UpOnly
+    fn default() -> Self {
+        OracleBasketType::WeightedSum
+    }
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct OracleBasketComponent {
+    pub oracle: Pubkey,
+    pub weight: u128,
+}
+
+impl OracleBasket {
+    pub fn get_components(&self) -> ClearingHouseResult<&[OracleBasketComponent]> {
+        let num_components: usize = cast(self.num_components)?;
+        self.components
+            .get(..num_components)
+            .ok_or(ErrorCode::InvalidOracleBasket)
+    }
+
+    // Recomputes the basket from its pyth components, which are passed in any order
+    pub fn update(&mut self, accounts: &[AccountInfo], clock_slot: u64) -> ClearingHouseResult {
+        let oracle_price_data = match self.basket_type {
+            OracleBasketType::WeightedSum => self.calculate_weighted_sum(accounts, clock_slot)?,
+            OracleBasketType::Ratio => self.calculate_ratio(accounts, clock_slot)?,
+        };
+
+        self.price = oracle_price_data.price;
+        self.twap = oracle_price_data.twap;
+        self.confidence = oracle_price_data.confidence;
+        self.twap_confidence = oracle_price_data.twap_confidence;
+        self.valid_slot = cast(
+            cast_to_i64(clock_slot)?
+                .checked_sub(oracle_price_data.delay)
+                .ok_or_else(math_error!())?,
+        )?;
+        self.status = oracle_price_data.status;
+        self.num_publishers = oracle_price_data.num_publishers;
+
+        Ok(())
+    }
+
+    fn get_component_price_data(
+        component: &OracleBasketComponent,
+        accounts: &[AccountInfo],
+        clock_slot: u64,
+    ) -> ClearingHouseResult<OraclePriceData> {
+        let oracle_account_info = accounts
+            .iter()
+            .find(|account_info| account_info.key.eq(&component.oracle))
+            .ok_or(ErrorCode::OracleNotFound)?;
+
+        AMM::get_pyth_price(oracle_account_info, clock_slot)
+    }
+
+    fn calculate_weighted_sum(
+        &self,
+        accounts: &[AccountInfo],
+        clock_slot: u64,
+    ) -> ClearingHouseResult<OraclePriceData> {
+        let mut price: i128 = 0;
+        let mut twap: i128 = 0;
+        let mut confidence: u128 = 0;
+        let mut twap_confidence: u128 = 0;
+        let mut max_delay: i64 = 0;
+        let mut status = OraclePriceStatus::Trading;
+        let mut num_publishers = u32::MAX;
+
+        for component in self.get_components()? {
+            let component_price_data =
+                OracleBasket::get_component_price_data(component, accounts, clock_slot)?;
+
+            price = price
+                .checked_add(
+                    component_price_data
+                        .price
+                        .checked_mul(cast_to_i128(component.weight)?)
+                        .ok_or_else(math_error!())?,
+                )
+                .ok_or_else(math_error!())?;
+            twap = twap
+                .checked_add(
+                    component_price_data
+                        .twap
+                        .checked_mul(cast_to_i128(component.weight)?)
+                        .ok_or_else(math_error!())?,
+                )
+                .ok_or_else(math_error!())?;
+            confidence = confidence
+                .checked_add(
+                    component_price_data
+                        .confidence
+                        .checked_mul(component.weight)
+                        .ok_or_else(math_error!())?,
+                )
+                .ok_or_else(math_error!())?;
+            twap_confidence = twap_confidence
+                .checked_add(
+                    component_price_data
+                        .twap_confidence
+                        .checked_mul(component.weight)
+                        .ok_or_else(math_error!())?,
+                )
+                .ok_or_else(math_error!())?;
+
+            // the basket is only as fresh as its stalest component
+            max_delay = max(max_delay, component_price_data.delay);
+            if status == OraclePriceStatus::Trading {
+                status = component_price_data.status;
+            }
+            num_publishers = min(num_publishers, component_price_data.num_publishers);
+        }
+
+        Ok(OraclePriceData {
+            price: price
+                .checked_div(cast(ORACLE_BASKET_WEIGHT_PRECISION)?)
+                .ok_or_else(math_error!())?,
+            twap: twap
+                .checked_div(cast(ORACLE_BASKET_WEIGHT_PRECISION)?)
+                .ok_or_else(math_error!())?,
+            confidence: confidence
+                .checked_div(ORACLE_BASKET_WEIGHT_PRECISION)
+                .ok_or_else(math_error!())?,
+            twap_confidence: twap_confidence
+                .checked_div(ORACLE_BASKET_WEIGHT_PRECISION)
+                .ok_or_else(math_error!())?,
+            delay: max_delay,
+            status,
+            num_publishers,
+        })
+    }
+
+    // The first component is the numerator and the second the denominator
+    fn calculate_ratio(
+        &self,
+        accounts: &[AccountInfo],
+        clock_slot: u64,
+    ) -> ClearingHouseResult<OraclePriceData> {
+        let components = self.get_components()?;
+        if components.len() != 2 {
+            return Err(ErrorCode::InvalidOracleBasket);
+        }
+
+        let numerator_price_data =
+            OracleBasket::get_component_price_data(&components[0], accounts, clock_slot)?;
+        let denominator_price_data =
+            OracleBasket::get_component_price_data(&components[1], accounts, clock_slot)?;
+
+        let (price, confidence) = calculate_price_ratio(
+            numerator_price_data.price,
+            numerator_price_data.confidence,
+            denominator_price_data.price,
+            denominator_price_data.confidence,
+        )?;
+        let (twap, twap_confidence) = calculate_price_ratio(
+            numerator_price_data.twap,
+            numerator_price_data.twap_confidence,
+            denominator_price_data.twap,
+            denominator_price_data.twap_confidence,
+        )?;
+
+        let status = if numerator_price_data.status == OraclePriceStatus::Trading {
+            denominator_price_data.status
+        } else {
+            numerator_price_data.status
+        };
+
+        Ok(OraclePriceData {
+            price,
+            twap,
+            confidence,
+            twap_confidence,
+            delay: max(numerator_price_data.delay, denominator_price_data.delay),
+            status,
+            num_publishers: min(
+                numerator_price_data.num_publishers,
+                denominator_price_data.num_publishers,
+            ),
+        })
+    }
+
+    pub fn get_price_data(&self, clock_slot: u64) -> ClearingHouseResult<OraclePriceData> {
+        let delay = cast_to_i64(clock_slot)?
+            .checked_sub(cast(self.valid_slot)?)
+            .ok_or_else(math_error!())?;
+
+        Ok(OraclePriceData {
+            price: self.price,
+            twap: self.twap,
+            confidence: self.confidence,
+            twap_confidence: self.twap_confidence,
+            delay,
+            status: self.status,
+            num_publishers: self.num_publishers,
+        })
+    }
+}
+
+// Relative confidences add when dividing, so conf = ratio * (conf_a / price_a + conf_b / price_b)
+fn calculate_price_ratio(
+    numerator_price: i128,
+    numerator_confidence: u128,
+    denominator_price: i128,
+    denominator_confidence: u128,
+) -> ClearingHouseResult<(i128, u128)> {
+    if numerator_price <= 0 || denominator_price <= 0 {
+        msg!("Ratio oracle component has a non positive price");
+        return Err(ErrorCode::InvalidOracle);
+    }
+
+    let numerator_price = cast_to_u128(numerator_price)?;
+    let denominator_price = cast_to_u128(denominator_price)?;
+
+    let ratio = numerator_price
+        .checked_mul(MARK_PRICE_PRECISION)
+        .ok_or_else(math_error!())?
+        .checked_div(denominator_price)
+        .ok_or_else(math_error!())?;
+
+    let numerator_confidence_component = numerator_confidence
+        .checked_mul(MARK_PRICE_PRECISION)
+        .ok_or_else(math_error!())?
+        .checked_div(denominator_price)
+        .ok_or_else(math_error!())?;
+
+    let denominator_confidence_component = ratio
+        .checked_mul(denominator_confidence)
+        .ok_or_else(math_error!())?
+        .checked_div(denominator_price)
+        .ok_or_else(math_error!())?;
+
+    let confidence = numerator_confidence_component
+        .checked_add(denominator_confidence_component)
+        .ok_or_else(math_error!())?;
+
+    Ok((cast_to_i128(ratio)?, confidence))
+}
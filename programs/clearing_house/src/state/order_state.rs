@@ -6,7 +6,8 @@ pub struct OrderState {
     pub order_history: Pubkey,
     pub order_filler_reward_structure: OrderFillerRewardStructure,
     pub min_order_quote_asset_amount: u128, // minimum est. quote_asset_amount for place_order to succeed
-    pub padding: [u128; 10],
+    pub expire_order_reward: u128, // paid by the user to whoever cancels their expired order
+    pub padding: [u128; 9],
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
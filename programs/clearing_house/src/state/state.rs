@@ -39,12 +39,13 @@ pub struct State {
     pub max_deposit: u128,
     pub extended_curve_history: Pubkey,
     pub order_state: Pubkey,
+    pub oracle_history: Pubkey,
+    pub oracle_circuit_breaker_threshold: u64, // consecutive invalid readings before a market becomes reduce only
+    pub extended_oracle_guard_rails: ExtendedOracleGuardRails,
 
     // upgrade-ability
-    pub padding0: u128,
+    pub padding0: [u8; 2],
     pub padding1: u128,
-    pub padding2: u128,
-    pub padding3: u128,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
@@ -67,6 +68,15 @@ pub struct ValidityGuardRails {
     pub too_volatile_ratio: i128,
 }
 
+// Guard rails added after the state was deployed. They're kept out of OracleGuardRails so they can
+// take the place of the state's padding without changing its layout
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct ExtendedOracleGuardRails {
+    pub require_trading_status: bool,
+    pub min_publishers: u32,
+    pub use_confidence_interval_for_margin: bool,
+}
+
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
 pub struct FeeStructure {
     pub fee_numerator: u128,
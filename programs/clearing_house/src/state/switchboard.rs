@@ -0,0 +1,73 @@
+use anchor_lang::prelude::*;
+use bytemuck::{Pod, Zeroable};
+
+// Layout of the switchboard v2 aggregator account. Only the latest confirmed round is read,
+// but the full struct is kept so the account size and offsets line up with the real program.
+pub const AGGREGATOR_ACCOUNT_DISCRIMINATOR: [u8; 8] = [217, 230, 65, 101, 201, 162, 27, 125];
+
+// Size of a live aggregator account, discriminator included
+pub const AGGREGATOR_ACCOUNT_SIZE: usize = 3851;
+
+#[zero_copy]
+#[derive(Default)]
+pub struct SwitchboardDecimal {
+    pub mantissa: i128,
+    pub scale: u32,
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct Hash {
+    pub data: [u8; 32],
+}
+
+#[zero_copy]
+pub struct AggregatorRound {
+    pub num_success: u32,
+    pub num_error: u32,
+    pub is_closed: bool,
+    pub round_open_slot: u64,
+    pub round_open_timestamp: i64,
+    pub result: SwitchboardDecimal,
+    pub std_deviation: SwitchboardDecimal,
+    pub min_response: SwitchboardDecimal,
+    pub max_response: SwitchboardDecimal,
+    pub oracle_pubkeys_data: [Pubkey; 16],
+    pub medians_data: [SwitchboardDecimal; 16],
+    pub current_payout: [i64; 16],
+    pub medians_fulfilled: [bool; 16],
+    pub errors_fulfilled: [bool; 16],
+}
+
+#[zero_copy]
+pub struct AggregatorAccountData {
+    pub name: [u8; 32],
+    pub metadata: [u8; 128],
+    pub author_wallet: Pubkey,
+    pub queue_pubkey: Pubkey,
+    pub oracle_request_batch_size: u32,
+    pub min_oracle_results: u32,
+    pub min_job_results: u32,
+    pub min_update_delay_seconds: u32,
+    pub start_after: i64,
+    pub variance_threshold: SwitchboardDecimal,
+    pub force_report_period: i64,
+    pub expiration: i64,
+    pub consecutive_failure_count: u64,
+    pub next_allowed_update_time: i64,
+    pub is_locked: bool,
+    pub schedule: [u8; 32],
+    pub latest_confirmed_round: AggregatorRound,
+    pub current_round: AggregatorRound,
+    pub job_pubkeys_data: [Pubkey; 16],
+    pub job_hashes: [Hash; 16],
+    pub job_pubkeys_size: u32,
+    pub jobs_checksum: [u8; 32],
+    pub authority: Pubkey,
+    pub ebuf: [u8; 224],
+}
+
+unsafe impl Zeroable for AggregatorAccountData {}
+unsafe impl Pod for AggregatorAccountData {}
+
+const _: () = assert!(std::mem::size_of::<AggregatorAccountData>() + 8 == AGGREGATOR_ACCOUNT_SIZE);
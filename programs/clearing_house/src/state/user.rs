@@ -11,10 +11,10 @@ pub struct User {
     pub total_referral_reward: u128,
     pub total_referee_discount: u128,
     pub positions: Pubkey,
+    // default until the user orders are moved out of the user orders pda by resize_user_orders
+    pub user_orders: Pubkey,
 
     // upgrade-ability
-    pub padding0: u128,
-    pub padding1: u128,
     pub padding2: u128,
     pub padding3: u128,
 }
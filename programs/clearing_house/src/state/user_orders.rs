@@ -1,7 +1,14 @@
 use crate::controller::position::PositionDirection;
+use crate::error::{ClearingHouseResult, ErrorCode};
 use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
 use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use std::cell::{Ref, RefMut};
 
+// The orders in a user orders account aren't limited to this array. The account data is the
+// discriminator, the user and then as many orders as the account has room for, so accounts must be
+// loaded with UserOrdersLoader. The array is the capacity of accounts made by initialize_user_orders
 #[account(zero_copy)]
 #[derive(Default)]
 pub struct UserOrders {
@@ -9,10 +16,158 @@ pub struct UserOrders {
     pub orders: [Order; 32],
 }
 
+const USER_ORDERS_HEADER_SIZE: usize = 8 + 32;
+
 impl UserOrders {
     pub fn index_from_u64(index: u64) -> usize {
         std::convert::TryInto::try_into(index).unwrap()
     }
+
+    pub fn space(capacity: usize) -> usize {
+        USER_ORDERS_HEADER_SIZE + capacity * std::mem::size_of::<Order>()
+    }
+
+    fn orders_end(data_len: usize) -> ClearingHouseResult<usize> {
+        if data_len < UserOrders::space(1) {
+            return Err(ErrorCode::InvalidUserOrdersAccount);
+        }
+
+        // Accounts that orders don't fit exactly were made for LegacyOrder and have to go through
+        // migrate_user_orders before they can be used
+        let capacity = (data_len - USER_ORDERS_HEADER_SIZE) / std::mem::size_of::<Order>();
+        if UserOrders::space(capacity) != data_len {
+            return Err(ErrorCode::InvalidUserOrdersAccount);
+        }
+
+        Ok(data_len)
+    }
+}
+
+// The layout of user orders accounts before orders outgrew their padding. They have the same
+// discriminator as UserOrders, so they are told apart by their size
+#[zero_copy]
+pub struct LegacyUserOrders {
+    pub user: Pubkey,
+    pub orders: [LegacyOrder; 32],
+}
+
+unsafe impl Zeroable for LegacyUserOrders {}
+unsafe impl Pod for LegacyUserOrders {}
+
+pub fn load_legacy_user_orders<'a>(
+    account_info: &'a AccountInfo,
+) -> ClearingHouseResult<Ref<'a, LegacyUserOrders>> {
+    if account_info.owner != &crate::ID {
+        return Err(ErrorCode::InvalidUserOrdersAccount);
+    }
+
+    let data = account_info
+        .try_borrow_data()
+        .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+    if data.len() != 8 + std::mem::size_of::<LegacyUserOrders>()
+        || data[..8] != UserOrders::discriminator()
+    {
+        return Err(ErrorCode::InvalidUserOrdersAccount);
+    }
+
+    Ok(Ref::map(data, |data| bytemuck::from_bytes(&data[8..])))
+}
+
+pub struct UserOrdersRef<'a> {
+    pub user: Pubkey,
+    pub orders: Ref<'a, [Order]>,
+}
+
+impl<'a> UserOrdersRef<'a> {
+    pub fn trailing_stop_trigger_price(&self, order_id: u128) -> Option<u128> {
+        self.orders
+            .iter()
+            .find(|order| order.order_id == order_id && order.order_type == OrderType::TrailingStop)
+            .map(|order| order.trigger_price)
+    }
+}
+
+pub struct UserOrdersRefMut<'a> {
+    pub user: Pubkey,
+    pub orders: RefMut<'a, [Order]>,
+}
+
+pub trait UserOrdersLoader {
+    fn load_orders(&self) -> ClearingHouseResult<UserOrdersRef<'_>>;
+    fn load_orders_mut(&self) -> ClearingHouseResult<UserOrdersRefMut<'_>>;
+    // For accounts created with the zero constraint, whose discriminator is written on exit
+    fn load_orders_init(&self, user: &Pubkey) -> ClearingHouseResult<UserOrdersRefMut<'_>>;
+}
+
+impl<'info> UserOrdersLoader for AccountLoader<'info, UserOrders> {
+    fn load_orders(&self) -> ClearingHouseResult<UserOrdersRef<'_>> {
+        let account_info: &AccountInfo = self.as_ref();
+        let data = account_info
+            .try_borrow_data()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+        let orders_end = UserOrders::orders_end(data.len())?;
+        if data[..8] != UserOrders::discriminator() {
+            return Err(ErrorCode::InvalidUserOrdersAccount);
+        }
+
+        let user = Pubkey::new(&data[8..USER_ORDERS_HEADER_SIZE]);
+        let orders = Ref::map(data, |data| {
+            bytemuck::cast_slice(&data[USER_ORDERS_HEADER_SIZE..orders_end])
+        });
+
+        Ok(UserOrdersRef { user, orders })
+    }
+
+    fn load_orders_mut(&self) -> ClearingHouseResult<UserOrdersRefMut<'_>> {
+        let account_info: &AccountInfo = self.as_ref();
+        if !account_info.is_writable {
+            return Err(ErrorCode::UnableToLoadAccountLoader);
+        }
+
+        let data = account_info
+            .try_borrow_mut_data()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+        let orders_end = UserOrders::orders_end(data.len())?;
+        if data[..8] != UserOrders::discriminator() {
+            return Err(ErrorCode::InvalidUserOrdersAccount);
+        }
+
+        let user = Pubkey::new(&data[8..USER_ORDERS_HEADER_SIZE]);
+        let orders = RefMut::map(data, |data| {
+            bytemuck::cast_slice_mut(&mut data[USER_ORDERS_HEADER_SIZE..orders_end])
+        });
+
+        Ok(UserOrdersRefMut { user, orders })
+    }
+
+    fn load_orders_init(&self, user: &Pubkey) -> ClearingHouseResult<UserOrdersRefMut<'_>> {
+        let account_info: &AccountInfo = self.as_ref();
+        if !account_info.is_writable {
+            return Err(ErrorCode::UnableToLoadAccountLoader);
+        }
+
+        let mut data = account_info
+            .try_borrow_mut_data()
+            .or(Err(ErrorCode::UnableToLoadAccountLoader))?;
+
+        let orders_end = UserOrders::orders_end(data.len())?;
+        if data[..8] != [0; 8] {
+            return Err(ErrorCode::InvalidUserOrdersAccount);
+        }
+
+        data[8..USER_ORDERS_HEADER_SIZE].copy_from_slice(user.as_ref());
+        let orders = RefMut::map(data, |data| {
+            bytemuck::cast_slice_mut(&mut data[USER_ORDERS_HEADER_SIZE..orders_end])
+        });
+
+        Ok(UserOrdersRefMut {
+            user: *user,
+            orders,
+        })
+    }
 }
 
 #[zero_copy]
@@ -34,14 +189,28 @@ pub struct Order {
     pub reduce_only: bool,
     pub post_only: bool,
     pub immediate_or_cancel: bool,
+    pub fill_or_kill: bool,
     pub discount_tier: OrderDiscountTier,
     pub trigger_price: u128,
     pub trigger_condition: OrderTriggerCondition,
     pub referrer: Pubkey,
     pub oracle_price_offset: i128,
+    pub max_ts: i64,
+    pub group_id: u128,
+    pub trailing_stop_offset: u128,
+    pub trailing_stop_offset_type: TrailingStopOffsetType,
+    pub twap_slice_count: u64,
+    pub twap_interval: i64,
+    pub twap_last_slice_ts: i64,
+    pub position_limit: u128,
+    pub trigger_price_source: OrderTriggerPriceSource,
     pub padding: [u16; 3],
 }
 
+// Orders are cast straight out of the account data, which holds as many orders as it has room for
+unsafe impl Zeroable for Order {}
+unsafe impl Pod for Order {}
+
 impl Default for Order {
     fn default() -> Self {
         Self {
@@ -62,16 +231,86 @@ impl Default for Order {
             reduce_only: false,
             post_only: false,
             immediate_or_cancel: false,
+            fill_or_kill: false,
             discount_tier: OrderDiscountTier::None,
             trigger_price: 0,
             trigger_condition: OrderTriggerCondition::Above,
             referrer: Pubkey::default(),
             oracle_price_offset: 0,
+            max_ts: 0,
+            group_id: 0,
+            trailing_stop_offset: 0,
+            trailing_stop_offset_type: TrailingStopOffsetType::Price,
+            twap_slice_count: 0,
+            twap_interval: 0,
+            twap_last_slice_ts: 0,
+            position_limit: 0,
+            trigger_price_source: OrderTriggerPriceSource::Mark,
             padding: [0; 3],
         }
     }
 }
 
+#[zero_copy]
+pub struct LegacyOrder {
+    pub status: OrderStatus,
+    pub order_type: OrderType,
+    pub ts: i64,
+    pub order_id: u128,
+    pub user_order_id: u8,
+    pub market_index: u64,
+    pub price: u128,
+    pub user_base_asset_amount: i128,
+    pub quote_asset_amount: u128,
+    pub base_asset_amount: u128,
+    pub base_asset_amount_filled: u128,
+    pub quote_asset_amount_filled: u128,
+    pub fee: u128,
+    pub direction: PositionDirection,
+    pub reduce_only: bool,
+    pub post_only: bool,
+    pub immediate_or_cancel: bool,
+    pub discount_tier: OrderDiscountTier,
+    pub trigger_price: u128,
+    pub trigger_condition: OrderTriggerCondition,
+    pub referrer: Pubkey,
+    pub oracle_price_offset: i128,
+    pub padding: [u16; 3],
+}
+
+unsafe impl Zeroable for LegacyOrder {}
+unsafe impl Pod for LegacyOrder {}
+
+impl LegacyOrder {
+    pub fn to_order(&self) -> Order {
+        Order {
+            status: self.status,
+            order_type: self.order_type,
+            ts: self.ts,
+            order_id: self.order_id,
+            user_order_id: self.user_order_id,
+            market_index: self.market_index,
+            price: self.price,
+            user_base_asset_amount: self.user_base_asset_amount,
+            quote_asset_amount: self.quote_asset_amount,
+            base_asset_amount: self.base_asset_amount,
+            base_asset_amount_filled: self.base_asset_amount_filled,
+            quote_asset_amount_filled: self.quote_asset_amount_filled,
+            fee: self.fee,
+            direction: self.direction,
+            reduce_only: self.reduce_only,
+            post_only: self.post_only,
+            immediate_or_cancel: self.immediate_or_cancel,
+            discount_tier: self.discount_tier,
+            trigger_price: self.trigger_price,
+            trigger_condition: self.trigger_condition,
+            referrer: self.referrer,
+            oracle_price_offset: self.oracle_price_offset,
+            ..Order::default()
+        }
+    }
+}
+
 #[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq)]
 pub enum OrderStatus {
     Init,
@@ -84,6 +323,8 @@ pub enum OrderType {
     Limit,
     TriggerMarket,
     TriggerLimit,
+    TrailingStop,
+    Twap,
 }
 
 #[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq)]
@@ -107,3 +348,33 @@ impl Default for OrderTriggerCondition {
         OrderTriggerCondition::Above
     }
 }
+
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum OrderTriggerPriceSource {
+    Mark,
+    Oracle,
+    // the market's last_oracle_price_twap
+    OracleTwap,
+}
+
+impl Default for OrderTriggerPriceSource {
+    // UpOnly
+    fn default() -> Self {
+        OrderTriggerPriceSource::Mark
+    }
+}
+
+#[derive(Clone, Copy, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum TrailingStopOffsetType {
+    // offset is in MARK_PRICE_PRECISION
+    Price,
+    // offset is in PRICE_SPREAD_PRECISION
+    Percentage,
+}
+
+impl Default for TrailingStopOffsetType {
+    // UpOnly
+    fn default() -> Self {
+        TrailingStopOffsetType::Price
+    }
+}
@@ -35,9 +35,8 @@ pub fn initialize(
     user.collateral = 0;
     user.cumulative_deposits = 0;
     user.positions = *user_positions.to_account_info().key;
+    user.user_orders = Pubkey::default();
 
-    user.padding0 = 0;
-    user.padding1 = 0;
     user.padding2 = 0;
     user.padding3 = 0;
 
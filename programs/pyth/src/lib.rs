@@ -1,6 +1,6 @@
 use anchor_lang::prelude::*;
 mod pc;
-use pc::Price;
+use pc::{AccKey, Price, PriceInfo, PriceStatus};
 
 #[cfg(feature = "mainnet-beta")]
 declare_id!("GWXu4vLvXFN87dePFvM7Ejt8HEALEG9GNmwimNKHZrXG");
@@ -11,20 +11,17 @@ declare_id!("ASfdvRMCan2aoWtbDi5HLXhz2CFfgEkuDoxc57bJLKLX");
 pub mod pyth {
     use super::*;
 
-    pub fn initialize(
-        ctx: Context<Initialize>,
-        price: i64,
-        expo: i32,
-        _conf: u64,
-    ) -> ProgramResult {
+    pub fn initialize(ctx: Context<Initialize>, price: i64, expo: i32, conf: u64) -> ProgramResult {
         let oracle = &ctx.accounts.price;
 
         let mut price_oracle = Price::load(oracle).unwrap();
 
         price_oracle.agg.price = price;
-        price_oracle.agg.conf = 0;
+        price_oracle.agg.conf = conf;
+        price_oracle.agg.status = pc::PriceStatus::Trading;
 
-        price_oracle.twap = price;
+        price_oracle.twap.val = price;
+        price_oracle.twac.val = conf as i64;
         price_oracle.expo = expo;
         price_oracle.ptype = pc::PriceType::Price;
         Ok(())
@@ -34,12 +31,14 @@ pub mod pyth {
         let oracle = &ctx.accounts.price;
         let mut price_oracle = Price::load(oracle).unwrap();
 
-        price_oracle.twap = price_oracle
+        // rough stand-in for pyth's ema, use set_twap to control the twap exactly
+        price_oracle.twap.val = price_oracle
             .twap
+            .val
             .checked_add(price)
             .unwrap()
             .checked_div(2)
-            .unwrap(); //todo
+            .unwrap();
         price_oracle.agg.price = price as i64;
         Ok(())
     }
@@ -48,7 +47,80 @@ pub mod pyth {
         let oracle = &ctx.accounts.price;
         let mut price_oracle = Price::load(oracle).unwrap();
 
-        price_oracle.twap = twap;
+        price_oracle.twap.val = twap;
+        Ok(())
+    }
+
+    pub fn set_conf(ctx: Context<SetPrice>, conf: u64) -> ProgramResult {
+        let oracle = &ctx.accounts.price;
+        let mut price_oracle = Price::load(oracle).unwrap();
+
+        price_oracle.agg.conf = conf;
+        Ok(())
+    }
+
+    pub fn set_twac(ctx: Context<SetPrice>, twac: u64) -> ProgramResult {
+        let oracle = &ctx.accounts.price;
+        let mut price_oracle = Price::load(oracle).unwrap();
+
+        price_oracle.twac.val = twac as i64;
+        Ok(())
+    }
+
+    pub fn set_valid_slot(ctx: Context<SetPrice>, valid_slot: u64) -> ProgramResult {
+        let oracle = &ctx.accounts.price;
+        let mut price_oracle = Price::load(oracle).unwrap();
+
+        price_oracle.valid_slot = valid_slot;
+        Ok(())
+    }
+
+    pub fn set_status(ctx: Context<SetPrice>, status: PriceStatus) -> ProgramResult {
+        let oracle = &ctx.accounts.price;
+        let mut price_oracle = Price::load(oracle).unwrap();
+
+        price_oracle.agg.status = status;
+        Ok(())
+    }
+
+    // Only changes the exponent, prices and confidences are left as stored
+    pub fn set_expo(ctx: Context<SetPrice>, expo: i32) -> ProgramResult {
+        let oracle = &ctx.accounts.price;
+        let mut price_oracle = Price::load(oracle).unwrap();
+
+        price_oracle.expo = expo;
+        Ok(())
+    }
+
+    pub fn set_component(
+        ctx: Context<SetPrice>,
+        index: u32,
+        publisher: Pubkey,
+        price: i64,
+        conf: u64,
+        status: PriceStatus,
+    ) -> ProgramResult {
+        let oracle = &ctx.accounts.price;
+        let mut price_oracle = Price::load(oracle).unwrap();
+
+        let price_info = PriceInfo {
+            price,
+            conf,
+            status,
+            ..PriceInfo::default()
+        };
+        let component = &mut price_oracle.comp[index as usize];
+        component.publisher = AccKey {
+            val: publisher.to_bytes(),
+        };
+        component.agg = price_info;
+        component.latest = price_info;
+
+        price_oracle.num = std::cmp::max(price_oracle.num, index + 1);
+        price_oracle.num_qt = price_oracle.comp[..price_oracle.num as usize]
+            .iter()
+            .filter(|component| matches!(component.agg.status, PriceStatus::Trading))
+            .count() as u32;
         Ok(())
     }
 }
@@ -0,0 +1,136 @@
+use anchor_lang::prelude::*;
+
+declare_id!("BeA2oR5jFGwbrK7cJRNJyUUS1BsXkdMJfzVFykpyc7sz");
+
+// Size of a live aggregator account, discriminator included
+pub const AGGREGATOR_ACCOUNT_SIZE: usize = 3851;
+
+// Mirrors the switchboard v2 aggregator account so the clearing house decodes it exactly as it
+// would a live feed. Only the latest confirmed round is ever written.
+#[program]
+pub mod switchboard {
+    use super::*;
+
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        mantissa: i128,
+        scale: u32,
+        min_oracle_results: u32,
+    ) -> ProgramResult {
+        let aggregator = &mut ctx.accounts.aggregator.load_init()?;
+
+        aggregator.min_oracle_results = min_oracle_results;
+        aggregator.latest_confirmed_round.num_success = min_oracle_results;
+        aggregator.latest_confirmed_round.is_closed = true;
+        aggregator.latest_confirmed_round.round_open_slot = Clock::get()?.slot;
+        aggregator.latest_confirmed_round.round_open_timestamp = Clock::get()?.unix_timestamp;
+        aggregator.latest_confirmed_round.result = SwitchboardDecimal { mantissa, scale };
+        Ok(())
+    }
+
+    pub fn set_value(ctx: Context<SetValue>, mantissa: i128, scale: u32) -> ProgramResult {
+        let aggregator = &mut ctx.accounts.aggregator.load_mut()?;
+
+        aggregator.latest_confirmed_round.result = SwitchboardDecimal { mantissa, scale };
+        Ok(())
+    }
+
+    pub fn set_round_open_slot(ctx: Context<SetValue>, round_open_slot: u64) -> ProgramResult {
+        let aggregator = &mut ctx.accounts.aggregator.load_mut()?;
+
+        aggregator.latest_confirmed_round.round_open_slot = round_open_slot;
+        Ok(())
+    }
+
+    pub fn set_std_deviation(ctx: Context<SetValue>, mantissa: i128, scale: u32) -> ProgramResult {
+        let aggregator = &mut ctx.accounts.aggregator.load_mut()?;
+
+        aggregator.latest_confirmed_round.std_deviation = SwitchboardDecimal { mantissa, scale };
+        Ok(())
+    }
+
+    pub fn set_min_responses(
+        ctx: Context<SetValue>,
+        min_oracle_results: u32,
+        num_success: u32,
+    ) -> ProgramResult {
+        let aggregator = &mut ctx.accounts.aggregator.load_mut()?;
+
+        aggregator.min_oracle_results = min_oracle_results;
+        aggregator.latest_confirmed_round.num_success = num_success;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(zero)]
+    pub aggregator: AccountLoader<'info, AggregatorAccountData>,
+}
+
+#[derive(Accounts)]
+pub struct SetValue<'info> {
+    #[account(mut)]
+    pub aggregator: AccountLoader<'info, AggregatorAccountData>,
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct SwitchboardDecimal {
+    pub mantissa: i128,
+    pub scale: u32,
+}
+
+#[zero_copy]
+#[derive(Default)]
+pub struct Hash {
+    pub data: [u8; 32],
+}
+
+#[zero_copy]
+pub struct AggregatorRound {
+    pub num_success: u32,
+    pub num_error: u32,
+    pub is_closed: bool,
+    pub round_open_slot: u64,
+    pub round_open_timestamp: i64,
+    pub result: SwitchboardDecimal,
+    pub std_deviation: SwitchboardDecimal,
+    pub min_response: SwitchboardDecimal,
+    pub max_response: SwitchboardDecimal,
+    pub oracle_pubkeys_data: [Pubkey; 16],
+    pub medians_data: [SwitchboardDecimal; 16],
+    pub current_payout: [i64; 16],
+    pub medians_fulfilled: [bool; 16],
+    pub errors_fulfilled: [bool; 16],
+}
+
+#[account(zero_copy)]
+pub struct AggregatorAccountData {
+    pub name: [u8; 32],
+    pub metadata: [u8; 128],
+    pub author_wallet: Pubkey,
+    pub queue_pubkey: Pubkey,
+    pub oracle_request_batch_size: u32,
+    pub min_oracle_results: u32,
+    pub min_job_results: u32,
+    pub min_update_delay_seconds: u32,
+    pub start_after: i64,
+    pub variance_threshold: SwitchboardDecimal,
+    pub force_report_period: i64,
+    pub expiration: i64,
+    pub consecutive_failure_count: u64,
+    pub next_allowed_update_time: i64,
+    pub is_locked: bool,
+    pub schedule: [u8; 32],
+    pub latest_confirmed_round: AggregatorRound,
+    pub current_round: AggregatorRound,
+    pub job_pubkeys_data: [Pubkey; 16],
+    pub job_hashes: [Hash; 16],
+    pub job_pubkeys_size: u32,
+    pub jobs_checksum: [u8; 32],
+    pub authority: Pubkey,
+    pub ebuf: [u8; 224],
+}
+
+const _: () = assert!(std::mem::size_of::<AggregatorAccountData>() + 8 == AGGREGATOR_ACCOUNT_SIZE);
@@ -4,6 +4,11 @@ import { PollingUserAccountSubscriber } from './pollingUserAccountSubscriber';
 import { UserAccount, UserOrdersAccount } from '../types';
 import { UserPublicKeys } from './types';
 import { ProgramAccount } from '@project-serum/anchor';
+import { PublicKey } from '@solana/web3.js';
+import {
+	DEFAULT_USER_ORDERS_CAPACITY,
+	getUserOrdersAccountSize,
+} from '../userOrders';
 
 /**
  * @param users
@@ -27,7 +32,12 @@ export async function bulkPollingUserSubscribe(
 			userProgramAccounts = await program.account.user.all();
 		})(),
 		(async () => {
-			orderProgramAccounts = await program.account.userOrders.all();
+			// orders accounts that were resized are found through the user account instead
+			orderProgramAccounts = await program.account.userOrders.all([
+				{
+					dataSize: getUserOrdersAccountSize(DEFAULT_USER_ORDERS_CAPACITY),
+				},
+			]);
 		})(),
 	]);
 
@@ -41,7 +51,9 @@ export async function bulkPollingUserSubscribe(
 		authorityToKeys.set(userAccount.authority.toString(), {
 			user: userAccountPublicKey,
 			userPositions: userAccount.positions,
-			userOrders: undefined,
+			userOrders: userAccount.userOrders.equals(PublicKey.default)
+				? undefined
+				: userAccount.userOrders,
 		});
 
 		userToAuthority.set(
@@ -55,7 +67,9 @@ export async function bulkPollingUserSubscribe(
 
 		const authority = userToAuthority.get(userOrderAccount.user.toString());
 		const userPublicKeys = authorityToKeys.get(authority);
-		userPublicKeys.userOrders = userOrderAccountPublicKey;
+		if (userPublicKeys.userOrders === undefined) {
+			userPublicKeys.userOrders = userOrderAccountPublicKey;
+		}
 	}
 
 	await Promise.all(
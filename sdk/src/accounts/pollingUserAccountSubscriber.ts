@@ -11,11 +11,11 @@ import { EventEmitter } from 'events';
 import { PublicKey } from '@solana/web3.js';
 import {
 	getUserAccountPublicKey,
-	getUserOrdersAccountPublicKey,
+	getUserOrdersAccountPublicKeyForUser,
 } from '../addresses';
 import { UserAccount, UserOrdersAccount, UserPositionsAccount } from '../types';
 import { BulkAccountLoader } from './bulkAccountLoader';
-import { capitalize } from './utils';
+import { decodeAccount } from './utils';
 import { ClearingHouseConfigType } from '../factory/clearingHouse';
 
 export class PollingUserAccountSubscriber implements UserAccountSubscriber {
@@ -86,9 +86,10 @@ export class PollingUserAccountSubscriber implements UserAccountSubscriber {
 				eventType: 'userPositionsData',
 			});
 
-			const userOrdersPublicKey = await getUserOrdersAccountPublicKey(
+			const userOrdersPublicKey = await getUserOrdersAccountPublicKeyForUser(
 				this.program.programId,
-				userPublicKey
+				userPublicKey,
+				userAccount
 			);
 
 			this.accountsToPoll.set(userOrdersPublicKey.toString(), {
@@ -126,9 +127,11 @@ export class PollingUserAccountSubscriber implements UserAccountSubscriber {
 						return;
 					}
 
-					const account = this.program.account[
-						accountToPoll.key
-					].coder.accounts.decode(capitalize(accountToPoll.key), buffer);
+					const account = decodeAccount(
+						this.program,
+						accountToPoll.key,
+						buffer
+					);
 					this[accountToPoll.key] = account;
 					// @ts-ignore
 					this.eventEmitter.emit(accountToPoll.eventType, account);
@@ -161,9 +164,11 @@ export class PollingUserAccountSubscriber implements UserAccountSubscriber {
 		for (const [_, accountToPoll] of this.accountsToPoll) {
 			const buffer = this.accountLoader.getAccountData(accountToPoll.publicKey);
 			if (buffer) {
-				this[accountToPoll.key] = this.program.account[
-					accountToPoll.key
-				].coder.accounts.decode(capitalize(accountToPoll.key), buffer);
+				this[accountToPoll.key] = decodeAccount(
+					this.program,
+					accountToPoll.key,
+					buffer
+				);
 			}
 		}
 	}
@@ -1,3 +1,21 @@
+import { Program } from '@project-serum/anchor';
+import { decodeUserOrdersAccount } from '../userOrders';
+
 export function capitalize(value: string): string {
 	return value[0].toUpperCase() + value.slice(1);
 }
+
+export function decodeAccount<T>(
+	program: Program,
+	accountName: string,
+	buffer: Buffer
+): T {
+	if (accountName === 'userOrders') {
+		return decodeUserOrdersAccount(program, buffer) as unknown as T;
+	}
+
+	return program.account[accountName].coder.accounts.decode(
+		capitalize(accountName),
+		buffer
+	);
+}
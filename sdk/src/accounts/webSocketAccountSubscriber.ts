@@ -1,7 +1,7 @@
 import { AccountData, AccountSubscriber } from './types';
 import { Program } from '@project-serum/anchor';
 import { AccountInfo, Context, PublicKey } from '@solana/web3.js';
-import { capitalize } from './utils';
+import { decodeAccount } from './utils';
 import * as Buffer from 'buffer';
 
 export class WebSocketAccountSubscriber<T> implements AccountSubscriber<T> {
@@ -62,9 +62,7 @@ export class WebSocketAccountSubscriber<T> implements AccountSubscriber<T> {
 				slot: newSlot,
 			};
 			if (newBuffer) {
-				this.data = this.program.account[
-					this.accountName
-				].coder.accounts.decode(capitalize(this.accountName), newBuffer);
+				this.data = decodeAccount(this.program, this.accountName, newBuffer);
 				this.onChange(this.data);
 			}
 			return;
@@ -80,10 +78,7 @@ export class WebSocketAccountSubscriber<T> implements AccountSubscriber<T> {
 				buffer: newBuffer,
 				slot: newSlot,
 			};
-			this.data = this.program.account[this.accountName].coder.accounts.decode(
-				capitalize(this.accountName),
-				newBuffer
-			);
+			this.data = decodeAccount(this.program, this.accountName, newBuffer);
 			this.onChange(this.data);
 		}
 	}
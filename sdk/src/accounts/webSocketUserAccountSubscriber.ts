@@ -10,7 +10,7 @@ import { EventEmitter } from 'events';
 import { PublicKey } from '@solana/web3.js';
 import {
 	getUserAccountPublicKey,
-	getUserOrdersAccountPublicKey,
+	getUserOrdersAccountPublicKeyForUser,
 } from '../addresses';
 import { WebSocketAccountSubscriber } from './webSocketAccountSubscriber';
 import { UserAccount, UserOrdersAccount, UserPositionsAccount } from '../types';
@@ -68,9 +68,10 @@ export class WebSocketUserAccountSubscriber implements UserAccountSubscriber {
 			}
 		);
 
-		const userOrdersPublicKey = await getUserOrdersAccountPublicKey(
+		const userOrdersPublicKey = await getUserOrdersAccountPublicKeyForUser(
 			this.program.programId,
-			userPublicKey
+			userPublicKey,
+			userAccountData
 		);
 
 		this.userOrdersAccountSubscriber = new WebSocketAccountSubscriber(
@@ -1,5 +1,6 @@
 import { PublicKey } from '@solana/web3.js';
 import * as anchor from '@project-serum/anchor';
+import { UserAccount } from './types';
 
 export async function getClearingHouseStateAccountPublicKeyAndNonce(
 	programId: PublicKey
@@ -69,3 +70,23 @@ export async function getUserOrdersAccountPublicKey(
 		await getUserOrdersAccountPublicKeyAndNonce(programId, userAccount)
 	)[0];
 }
+
+/**
+ * The user orders account is the pda until the user moves its orders to another account with
+ * resizeUserOrders
+ * @param programId
+ * @param userAccountPublicKey
+ * @param userAccount
+ * @returns
+ */
+export async function getUserOrdersAccountPublicKeyForUser(
+	programId: PublicKey,
+	userAccountPublicKey: PublicKey,
+	userAccount: UserAccount
+): Promise<PublicKey> {
+	if (!userAccount.userOrders.equals(PublicKey.default)) {
+		return userAccount.userOrders;
+	}
+
+	return await getUserOrdersAccountPublicKey(programId, userAccountPublicKey);
+}
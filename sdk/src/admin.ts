@@ -8,7 +8,9 @@ import {
 import {
 	FeeStructure,
 	IWallet,
+	OracleBasketComponent,
 	OracleGuardRails,
+	ExtendedOracleGuardRails,
 	OracleSource,
 	OrderFillerRewardStructure,
 } from './types';
@@ -49,7 +51,12 @@ export class Admin extends ClearingHouse {
 		usdcMint: PublicKey,
 		adminControlsPrices: boolean
 	): Promise<
-		[TransactionSignature, TransactionSignature, TransactionSignature]
+		[
+			TransactionSignature,
+			TransactionSignature,
+			TransactionSignature,
+			TransactionSignature
+		]
 	> {
 		const stateAccountRPCResponse = await this.connection.getParsedAccountInfo(
 			await this.getStatePublicKey()
@@ -176,7 +183,14 @@ export class Admin extends ClearingHouse {
 
 		const initializeOrderStateTxSig = await this.initializeOrderState();
 
-		return [initializeTxSig, initializeHistoryTxSig, initializeOrderStateTxSig];
+		const initializeOracleHistoryTxSig = await this.initializeOracleHistory();
+
+		return [
+			initializeTxSig,
+			initializeHistoryTxSig,
+			initializeOrderStateTxSig,
+			initializeOracleHistoryTxSig,
+		];
 	}
 
 	public async initializeOrderState(): Promise<TransactionSignature> {
@@ -210,13 +224,40 @@ export class Admin extends ClearingHouse {
 		);
 	}
 
+	public async initializeOracleHistory(): Promise<TransactionSignature> {
+		const oracleHistory = anchor.web3.Keypair.generate();
+		const clearingHouseStatePublicKey =
+			await getClearingHouseStateAccountPublicKey(this.program.programId);
+
+		const initializeOracleHistoryTx =
+			await this.program.transaction.initializeOracleHistory({
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: clearingHouseStatePublicKey,
+					oracleHistory: oracleHistory.publicKey,
+				},
+				instructions: [
+					await this.program.account.oracleHistory.createInstruction(
+						oracleHistory
+					),
+				],
+			});
+
+		return await this.txSender.send(
+			initializeOracleHistoryTx,
+			[oracleHistory],
+			this.opts
+		);
+	}
+
 	public async initializeMarket(
 		marketIndex: BN,
 		priceOracle: PublicKey,
 		baseAssetReserve: BN,
 		quoteAssetReserve: BN,
 		periodicity: BN,
-		pegMultiplier: BN = PEG_PRECISION
+		pegMultiplier: BN = PEG_PRECISION,
+		oracleSource: OracleSource = OracleSource.PYTH
 	): Promise<TransactionSignature> {
 		if (this.getMarketsAccount().markets[marketIndex.toNumber()].initialized) {
 			throw Error(`MarketIndex ${marketIndex.toNumber()} already initialized`);
@@ -228,6 +269,7 @@ export class Admin extends ClearingHouse {
 			quoteAssetReserve,
 			periodicity,
 			pegMultiplier,
+			oracleSource,
 			{
 				accounts: {
 					state: await this.getStatePublicKey(),
@@ -240,6 +282,61 @@ export class Admin extends ClearingHouse {
 		return await this.txSender.send(initializeMarketTx, [], this.opts);
 	}
 
+	public async initializeOracleBasket(
+		components: OracleBasketComponent[]
+	): Promise<PublicKey> {
+		const oracleBasket = anchor.web3.Keypair.generate();
+
+		const initializeOracleBasketTx =
+			await this.program.transaction.initializeOracleBasket(components, {
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					oracleBasket: oracleBasket.publicKey,
+				},
+				instructions: [
+					await this.program.account.oracleBasket.createInstruction(
+						oracleBasket
+					),
+				],
+			});
+
+		await this.txSender.send(
+			initializeOracleBasketTx,
+			[oracleBasket],
+			this.opts
+		);
+		return oracleBasket.publicKey;
+	}
+
+	public async initializeOracleRatio(
+		numeratorOracle: PublicKey,
+		denominatorOracle: PublicKey
+	): Promise<PublicKey> {
+		const oracleRatio = anchor.web3.Keypair.generate();
+
+		const initializeOracleRatioTx =
+			await this.program.transaction.initializeOracleRatio(
+				numeratorOracle,
+				denominatorOracle,
+				{
+					accounts: {
+						admin: this.wallet.publicKey,
+						state: await this.getStatePublicKey(),
+						oracleBasket: oracleRatio.publicKey,
+					},
+					instructions: [
+						await this.program.account.oracleBasket.createInstruction(
+							oracleRatio
+						),
+					],
+				}
+			);
+
+		await this.txSender.send(initializeOracleRatioTx, [oracleRatio], this.opts);
+		return oracleRatio.publicKey;
+	}
+
 	public async moveAmmPrice(
 		baseAssetReserve: BN,
 		quoteAssetReserve: BN,
@@ -280,6 +377,27 @@ export class Admin extends ClearingHouse {
 		});
 	}
 
+	public async updateOrderHistory(): Promise<TransactionSignature> {
+		const newOrderHistory = anchor.web3.Keypair.generate();
+
+		const orderState = this.getOrderStateAccount();
+		return await this.program.rpc.updateOrderHistory({
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				orderState: await this.getOrderStatePublicKey(),
+				orderHistory: orderState.orderHistory,
+				newOrderHistory: newOrderHistory.publicKey,
+			},
+			instructions: [
+				await this.program.account.orderHistory.createInstruction(
+					newOrderHistory
+				),
+			],
+			signers: [newOrderHistory],
+		});
+	}
+
 	public async updateCurveHistory(): Promise<TransactionSignature> {
 		const extendedCurveHistory = anchor.web3.Keypair.generate();
 
@@ -351,6 +469,7 @@ export class Admin extends ClearingHouse {
 				markets: state.markets,
 				curveHistory: state.extendedCurveHistory,
 			},
+			remainingAccounts: this.getBackupOracleAccounts(marketIndex),
 		});
 	}
 
@@ -370,6 +489,7 @@ export class Admin extends ClearingHouse {
 				markets: state.markets,
 				curveHistory: state.extendedCurveHistory,
 			},
+			remainingAccounts: this.getBackupOracleAccounts(marketIndex),
 		});
 	}
 
@@ -389,6 +509,7 @@ export class Admin extends ClearingHouse {
 				markets: state.markets,
 				curveHistory: state.extendedCurveHistory,
 			},
+			remainingAccounts: this.getBackupOracleAccounts(marketIndex),
 		});
 	}
 
@@ -568,6 +689,18 @@ export class Admin extends ClearingHouse {
 		);
 	}
 
+	public async updateExpireOrderReward(
+		expireOrderReward: BN
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateExpireOrderReward(expireOrderReward, {
+			accounts: {
+				admin: this.wallet.publicKey,
+				state: await this.getStatePublicKey(),
+				orderState: await this.getOrderStatePublicKey(),
+			},
+		});
+	}
+
 	public async updateFee(fees: FeeStructure): Promise<TransactionSignature> {
 		return await this.program.rpc.updateFee(fees, {
 			accounts: {
@@ -588,6 +721,20 @@ export class Admin extends ClearingHouse {
 		});
 	}
 
+	public async updateExtendedOracleGuardRails(
+		extendedOracleGuardRails: ExtendedOracleGuardRails
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateExtendedOracleGuardRails(
+			extendedOracleGuardRails,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+	}
+
 	public async updateMarketOracle(
 		marketIndex: BN,
 		oracle: PublicKey,
@@ -608,6 +755,46 @@ export class Admin extends ClearingHouse {
 		);
 	}
 
+	public async updateMarketOracleGuardRails(
+		marketIndex: BN,
+		oracleGuardRails: OracleGuardRails | null,
+		extendedOracleGuardRails: ExtendedOracleGuardRails | null = null
+	): Promise<TransactionSignature> {
+		const state = this.getStateAccount();
+		return await this.program.rpc.updateMarketOracleGuardRails(
+			marketIndex,
+			oracleGuardRails,
+			extendedOracleGuardRails,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					markets: state.markets,
+				},
+			}
+		);
+	}
+
+	public async updateMarketBackupOracle(
+		marketIndex: BN,
+		backupOracle: PublicKey,
+		backupOracleSource: OracleSource
+	): Promise<TransactionSignature> {
+		const state = this.getStateAccount();
+		return await this.program.rpc.updateMarketBackupOracle(
+			marketIndex,
+			backupOracle,
+			backupOracleSource,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+					markets: state.markets,
+				},
+			}
+		);
+	}
+
 	public async updateMarketMinimumQuoteAssetTradeSize(
 		marketIndex: BN,
 		minimumTradeSize: BN
@@ -666,6 +853,20 @@ export class Admin extends ClearingHouse {
 		});
 	}
 
+	public async updateOracleCircuitBreakerThreshold(
+		oracleCircuitBreakerThreshold: BN
+	): Promise<TransactionSignature> {
+		return await this.program.rpc.updateOracleCircuitBreakerThreshold(
+			oracleCircuitBreakerThreshold,
+			{
+				accounts: {
+					admin: this.wallet.publicKey,
+					state: await this.getStatePublicKey(),
+				},
+			}
+		);
+	}
+
 	public async updateMaxDeposit(maxDeposit: BN): Promise<TransactionSignature> {
 		return await this.program.rpc.updateMaxDeposit(maxDeposit, {
 			accounts: {
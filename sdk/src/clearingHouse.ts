@@ -19,6 +19,7 @@ import {
 	OrderHistoryAccount,
 	OrderStateAccount,
 	OrderParams,
+	ModifyOrderParams,
 	Order,
 	ExtendedCurveHistoryAccount,
 } from './types';
@@ -43,7 +44,7 @@ import {
 	getOrderStateAccountPublicKey,
 	getUserAccountPublicKey,
 	getUserAccountPublicKeyAndNonce,
-	getUserOrdersAccountPublicKey,
+	getUserOrdersAccountPublicKeyForUser,
 	getUserOrdersAccountPublicKeyAndNonce,
 } from './addresses';
 import {
@@ -53,6 +54,10 @@ import {
 } from './accounts/types';
 import { TxSender } from './tx/types';
 import { wrapInTx } from './tx/utils';
+import {
+	DEFAULT_USER_ORDERS_CAPACITY,
+	getUserOrdersAccountSize,
+} from './userOrders';
 import {
 	getClearingHouse,
 	getWebSocketClearingHouseConfig,
@@ -407,10 +412,12 @@ export class ClearingHouse {
 			return this.userOrdersAccountPublicKey;
 		}
 
-		this.userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
-			this.program.programId,
-			await this.getUserAccountPublicKey()
-		);
+		this.userOrdersAccountPublicKey =
+			await getUserOrdersAccountPublicKeyForUser(
+				this.program.programId,
+				await this.getUserAccountPublicKey(),
+				await this.getUserAccount()
+			);
 		return this.userOrdersAccountPublicKey;
 	}
 
@@ -428,6 +435,98 @@ export class ClearingHouse {
 		return this.userOrdersExist;
 	}
 
+	/**
+	 * Moves the user's open orders to a new account that has room for capacity orders and closes the
+	 * old one
+	 * @param capacity
+	 * @returns
+	 */
+	public async resizeUserOrders(
+		capacity: number
+	): Promise<[TransactionSignature, PublicKey]> {
+		const newUserOrdersAccount = new Keypair();
+		const createUserOrdersAccountIx =
+			await this.program.account.userOrders.createInstruction(
+				newUserOrdersAccount,
+				getUserOrdersAccountSize(capacity)
+			);
+		const resizeUserOrdersIx = await this.getResizeUserOrdersIx(
+			newUserOrdersAccount.publicKey
+		);
+
+		const tx = new Transaction()
+			.add(createUserOrdersAccountIx)
+			.add(resizeUserOrdersIx);
+		const txSig = await this.txSender.send(
+			tx,
+			[newUserOrdersAccount],
+			this.opts
+		);
+
+		this.userAccount = undefined;
+		this.userOrdersAccountPublicKey = newUserOrdersAccount.publicKey;
+		return [txSig, newUserOrdersAccount.publicKey];
+	}
+
+	public async getResizeUserOrdersIx(
+		newUserOrdersAccountPublicKey: PublicKey
+	): Promise<TransactionInstruction> {
+		return await this.program.instruction.resizeUserOrders({
+			accounts: {
+				user: await this.getUserAccountPublicKey(),
+				userOrders: await this.getUserOrdersAccountPublicKey(),
+				newUserOrders: newUserOrdersAccountPublicKey,
+				authority: this.wallet.publicKey,
+			},
+		});
+	}
+
+	/**
+	 * Moves the orders in a user orders account made before orders outgrew their padding to a new
+	 * account with the current order layout
+	 * @param capacity
+	 * @returns
+	 */
+	public async migrateUserOrders(
+		capacity = DEFAULT_USER_ORDERS_CAPACITY
+	): Promise<[TransactionSignature, PublicKey]> {
+		const newUserOrdersAccount = new Keypair();
+		const createUserOrdersAccountIx =
+			await this.program.account.userOrders.createInstruction(
+				newUserOrdersAccount,
+				getUserOrdersAccountSize(capacity)
+			);
+		const migrateUserOrdersIx = await this.getMigrateUserOrdersIx(
+			newUserOrdersAccount.publicKey
+		);
+
+		const tx = new Transaction()
+			.add(createUserOrdersAccountIx)
+			.add(migrateUserOrdersIx);
+		const txSig = await this.txSender.send(
+			tx,
+			[newUserOrdersAccount],
+			this.opts
+		);
+
+		this.userAccount = undefined;
+		this.userOrdersAccountPublicKey = newUserOrdersAccount.publicKey;
+		return [txSig, newUserOrdersAccount.publicKey];
+	}
+
+	public async getMigrateUserOrdersIx(
+		newUserOrdersAccountPublicKey: PublicKey
+	): Promise<TransactionInstruction> {
+		return await this.program.instruction.migrateUserOrders({
+			accounts: {
+				user: await this.getUserAccountPublicKey(),
+				userOrders: await this.getUserOrdersAccountPublicKey(),
+				newUserOrders: newUserOrdersAccountPublicKey,
+				authority: this.wallet.publicKey,
+			},
+		});
+	}
+
 	public async depositCollateral(
 		amount: BN,
 		collateralAccountPublicKey: PublicKey,
@@ -574,6 +673,10 @@ export class ClearingHouse {
 			userAccountPublicKey
 		);
 
+		const remainingAccounts = await this.getUserPositionsOracleAccounts(
+			user.positions
+		);
+
 		const state = this.getStateAccount();
 		return await this.program.instruction.withdrawCollateral(amount, {
 			accounts: {
@@ -591,6 +694,7 @@ export class ClearingHouse {
 				fundingPaymentHistory: state.fundingPaymentHistory,
 				depositHistory: state.depositHistory,
 			},
+			remainingAccounts,
 		});
 	}
 
@@ -655,6 +759,11 @@ export class ClearingHouse {
 			});
 		}
 
+		remainingAccounts.push(
+			...(await this.getUserPositionsOracleAccounts(userAccount.positions))
+		);
+		remainingAccounts.push(...this.getBackupOracleAccounts(marketIndex));
+
 		const priceOracle =
 			this.getMarketsAccount().markets[marketIndex.toNumber()].amm.oracle;
 
@@ -757,6 +866,9 @@ export class ClearingHouse {
 				isSigner: false,
 			});
 		}
+		remainingAccounts.push(
+			...this.getBackupOracleAccounts(orderParams.marketIndex)
+		);
 
 		const state = this.getStateAccount();
 		const orderState = this.getOrderStateAccount();
@@ -778,6 +890,88 @@ export class ClearingHouse {
 		});
 	}
 
+	public async placeOneCancelsOtherOrders(
+		legs: OrderParams[],
+		discountToken?: PublicKey,
+		referrer?: PublicKey
+	): Promise<TransactionSignature> {
+		return await this.txSender.send(
+			wrapInTx(
+				await this.getPlaceOneCancelsOtherOrdersIx(
+					legs,
+					discountToken,
+					referrer
+				)
+			),
+			[],
+			this.opts
+		);
+	}
+
+	public async getPlaceOneCancelsOtherOrdersIx(
+		legs: OrderParams[],
+		discountToken?: PublicKey,
+		referrer?: PublicKey
+	): Promise<TransactionInstruction> {
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+		const userAccount = await this.getUserAccount();
+
+		const priceOracle =
+			this.getMarketsAccount().markets[legs[0].marketIndex.toNumber()].amm
+				.oracle;
+
+		const remainingAccounts = [];
+		if (legs[0].optionalAccounts.discountToken) {
+			if (!discountToken) {
+				throw Error(
+					'Optional accounts specified discount token but no discount token present'
+				);
+			}
+
+			remainingAccounts.push({
+				pubkey: discountToken,
+				isWritable: false,
+				isSigner: false,
+			});
+		}
+
+		if (legs[0].optionalAccounts.referrer) {
+			if (!referrer) {
+				throw Error(
+					'Optional accounts specified referrer but no referrer present'
+				);
+			}
+
+			remainingAccounts.push({
+				pubkey: referrer,
+				isWritable: false,
+				isSigner: false,
+			});
+		}
+		remainingAccounts.push(
+			...this.getBackupOracleAccounts(legs[0].marketIndex)
+		);
+
+		const state = this.getStateAccount();
+		const orderState = this.getOrderStateAccount();
+		return await this.program.instruction.placeOneCancelsOtherOrders(legs, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				user: userAccountPublicKey,
+				authority: this.wallet.publicKey,
+				markets: state.markets,
+				userOrders: await this.getUserOrdersAccountPublicKey(),
+				userPositions: userAccount.positions,
+				fundingPaymentHistory: state.fundingPaymentHistory,
+				fundingRateHistory: state.fundingRateHistory,
+				orderState: await this.getOrderStatePublicKey(),
+				orderHistory: orderState.orderHistory,
+				oracle: priceOracle,
+			},
+			remainingAccounts,
+		});
+	}
+
 	public async cancelOrder(orderId: BN): Promise<TransactionSignature> {
 		return await this.txSender.send(
 			wrapInTx(await this.getCancelOrderIx(orderId)),
@@ -808,6 +1002,90 @@ export class ClearingHouse {
 		});
 	}
 
+	public async cancelAllOrders(
+		marketIndex: BN | null = null,
+		direction: PositionDirection | null = null,
+		reduceOnly: boolean | null = null
+	): Promise<TransactionSignature> {
+		return await this.txSender.send(
+			wrapInTx(
+				await this.getCancelAllOrdersIx(marketIndex, direction, reduceOnly)
+			),
+			[],
+			this.opts
+		);
+	}
+
+	public async getCancelAllOrdersIx(
+		marketIndex: BN | null = null,
+		direction: PositionDirection | null = null,
+		reduceOnly: boolean | null = null
+	): Promise<TransactionInstruction> {
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+		const userAccount = await this.getUserAccount();
+
+		const state = this.getStateAccount();
+		const orderState = this.getOrderStateAccount();
+		return await this.program.instruction.cancelAllOrders(
+			marketIndex,
+			direction,
+			reduceOnly,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					user: userAccountPublicKey,
+					authority: this.wallet.publicKey,
+					markets: state.markets,
+					userOrders: await this.getUserOrdersAccountPublicKey(),
+					userPositions: userAccount.positions,
+					fundingPaymentHistory: state.fundingPaymentHistory,
+					orderState: await this.getOrderStatePublicKey(),
+					orderHistory: orderState.orderHistory,
+				},
+			}
+		);
+	}
+
+	public async modifyOrder(
+		order: Order,
+		modifyOrderParams: ModifyOrderParams
+	): Promise<TransactionSignature> {
+		return await this.txSender.send(
+			wrapInTx(await this.getModifyOrderIx(order, modifyOrderParams)),
+			[],
+			this.opts
+		);
+	}
+
+	public async getModifyOrderIx(
+		order: Order,
+		modifyOrderParams: ModifyOrderParams
+	): Promise<TransactionInstruction> {
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+
+		const oracle = this.getMarket(order.marketIndex).amm.oracle;
+
+		const state = this.getStateAccount();
+		const orderState = this.getOrderStateAccount();
+		return await this.program.instruction.modifyOrder(
+			order.orderId,
+			modifyOrderParams,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					orderState: await this.getOrderStatePublicKey(),
+					user: userAccountPublicKey,
+					authority: this.wallet.publicKey,
+					markets: state.markets,
+					userOrders: await this.getUserOrdersAccountPublicKey(),
+					orderHistory: orderState.orderHistory,
+					oracle,
+				},
+				remainingAccounts: this.getBackupOracleAccounts(order.marketIndex),
+			}
+		);
+	}
+
 	public async cancelOrderByUserId(
 		userOrderId: number
 	): Promise<TransactionSignature> {
@@ -884,6 +1162,10 @@ export class ClearingHouse {
 				isSigner: false,
 			});
 		}
+		remainingAccounts.push(
+			...(await this.getUserPositionsOracleAccounts(userAccount.positions)),
+			...this.getBackupOracleAccounts(marketIndex)
+		);
 
 		const orderId = order.orderId;
 		return await this.program.instruction.fillOrder(orderId, {
@@ -907,6 +1189,227 @@ export class ClearingHouse {
 		});
 	}
 
+	public async fillOrders(
+		orders: {
+			userAccountPublicKey: PublicKey;
+			userOrdersAccountPublicKey: PublicKey;
+			order: Order;
+		}[]
+	): Promise<TransactionSignature> {
+		return await this.txSender.send(
+			wrapInTx(await this.getFillOrdersIx(orders)),
+			[],
+			this.opts
+		);
+	}
+
+	public async getFillOrdersIx(
+		orders: {
+			userAccountPublicKey: PublicKey;
+			userOrdersAccountPublicKey: PublicKey;
+			order: Order;
+		}[]
+	): Promise<TransactionInstruction> {
+		const fillerPublicKey = await this.getUserAccountPublicKey();
+
+		const marketIndex = orders[0].order.marketIndex;
+		const oracle = this.getMarket(marketIndex).amm.oracle;
+
+		const state = this.getStateAccount();
+		const orderState = this.getOrderStateAccount();
+
+		const userAccounts = [];
+		const optionalAccounts = [];
+		for (const {
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order,
+		} of orders) {
+			const userAccount: any = await this.program.account.user.fetch(
+				userAccountPublicKey
+			);
+			userAccounts.push(
+				{
+					pubkey: userAccountPublicKey,
+					isWritable: true,
+					isSigner: false,
+				},
+				{
+					pubkey: userAccount.positions,
+					isWritable: true,
+					isSigner: false,
+				},
+				{
+					pubkey: userOrdersAccountPublicKey,
+					isWritable: true,
+					isSigner: false,
+				}
+			);
+
+			if (!order.referrer.equals(PublicKey.default)) {
+				optionalAccounts.push({
+					pubkey: order.referrer,
+					isWritable: true,
+					isSigner: false,
+				});
+			}
+			optionalAccounts.push(
+				...(await this.getUserPositionsOracleAccounts(userAccount.positions))
+			);
+		}
+		optionalAccounts.push(...this.getBackupOracleAccounts(marketIndex));
+
+		const orderIds = orders.map(({ order }) => order.orderId);
+		return await this.program.instruction.fillOrders(orderIds, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				orderState: await this.getOrderStatePublicKey(),
+				authority: this.wallet.publicKey,
+				filler: fillerPublicKey,
+				markets: state.markets,
+				tradeHistory: state.tradeHistory,
+				fundingPaymentHistory: state.fundingPaymentHistory,
+				fundingRateHistory: state.fundingRateHistory,
+				orderHistory: orderState.orderHistory,
+				oracle: oracle,
+			},
+			remainingAccounts: [...userAccounts, ...optionalAccounts],
+		});
+	}
+
+	public async matchOrders(
+		takerAccountPublicKey: PublicKey,
+		takerOrdersAccountPublicKey: PublicKey,
+		takerOrder: Order,
+		makerAccountPublicKey: PublicKey,
+		makerOrdersAccountPublicKey: PublicKey,
+		makerOrder: Order
+	): Promise<TransactionSignature> {
+		return await this.txSender.send(
+			wrapInTx(
+				await this.getMatchOrdersIx(
+					takerAccountPublicKey,
+					takerOrdersAccountPublicKey,
+					takerOrder,
+					makerAccountPublicKey,
+					makerOrdersAccountPublicKey,
+					makerOrder
+				)
+			),
+			[],
+			this.opts
+		);
+	}
+
+	public async getMatchOrdersIx(
+		takerAccountPublicKey: PublicKey,
+		takerOrdersAccountPublicKey: PublicKey,
+		takerOrder: Order,
+		makerAccountPublicKey: PublicKey,
+		makerOrdersAccountPublicKey: PublicKey,
+		makerOrder: Order
+	): Promise<TransactionInstruction> {
+		const fillerPublicKey = await this.getUserAccountPublicKey();
+		const takerAccount: any = await this.program.account.user.fetch(
+			takerAccountPublicKey
+		);
+		const makerAccount: any = await this.program.account.user.fetch(
+			makerAccountPublicKey
+		);
+
+		const oracle = this.getMarket(takerOrder.marketIndex).amm.oracle;
+
+		const state = this.getStateAccount();
+		const orderState = this.getOrderStateAccount();
+
+		const remainingAccounts = [];
+		if (!takerOrder.referrer.equals(PublicKey.default)) {
+			remainingAccounts.push({
+				pubkey: takerOrder.referrer,
+				isWritable: true,
+				isSigner: false,
+			});
+		}
+		remainingAccounts.push(
+			...(await this.getUserPositionsOracleAccounts(takerAccount.positions)),
+			...(await this.getUserPositionsOracleAccounts(makerAccount.positions)),
+			...this.getBackupOracleAccounts(takerOrder.marketIndex)
+		);
+
+		return await this.program.instruction.matchOrders(
+			takerOrder.orderId,
+			makerOrder.orderId,
+			{
+				accounts: {
+					state: await this.getStatePublicKey(),
+					orderState: await this.getOrderStatePublicKey(),
+					authority: this.wallet.publicKey,
+					filler: fillerPublicKey,
+					taker: takerAccountPublicKey,
+					takerPositions: takerAccount.positions,
+					takerOrders: takerOrdersAccountPublicKey,
+					maker: makerAccountPublicKey,
+					makerPositions: makerAccount.positions,
+					makerOrders: makerOrdersAccountPublicKey,
+					markets: state.markets,
+					tradeHistory: state.tradeHistory,
+					fundingPaymentHistory: state.fundingPaymentHistory,
+					fundingRateHistory: state.fundingRateHistory,
+					orderHistory: orderState.orderHistory,
+					oracle: oracle,
+				},
+				remainingAccounts,
+			}
+		);
+	}
+
+	public async expireOrder(
+		userAccountPublicKey: PublicKey,
+		userOrdersAccountPublicKey: PublicKey,
+		order: Order
+	): Promise<TransactionSignature> {
+		return await this.txSender.send(
+			wrapInTx(
+				await this.getExpireOrderIx(
+					userAccountPublicKey,
+					userOrdersAccountPublicKey,
+					order
+				)
+			),
+			[],
+			this.opts
+		);
+	}
+
+	public async getExpireOrderIx(
+		userAccountPublicKey: PublicKey,
+		userOrdersAccountPublicKey: PublicKey,
+		order: Order
+	): Promise<TransactionInstruction> {
+		const fillerPublicKey = await this.getUserAccountPublicKey();
+		const userAccount: any = await this.program.account.user.fetch(
+			userAccountPublicKey
+		);
+
+		const state = this.getStateAccount();
+		const orderState = this.getOrderStateAccount();
+
+		return await this.program.instruction.expireOrder(order.orderId, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				orderState: await this.getOrderStatePublicKey(),
+				authority: this.wallet.publicKey,
+				filler: fillerPublicKey,
+				user: userAccountPublicKey,
+				markets: state.markets,
+				userPositions: userAccount.positions,
+				userOrders: userOrdersAccountPublicKey,
+				fundingPaymentHistory: state.fundingPaymentHistory,
+				orderHistory: orderState.orderHistory,
+			},
+		});
+	}
+
 	public async initializeUserOrdersThenPlaceAndFillOrder(
 		orderParams: OrderParams,
 		discountToken?: PublicKey,
@@ -982,6 +1485,12 @@ export class ClearingHouse {
 				isSigner: false,
 			});
 		}
+		remainingAccounts.push(
+			...(await this.getUserPositionsOracleAccounts(userAccount.positions))
+		);
+		remainingAccounts.push(
+			...this.getBackupOracleAccounts(orderParams.marketIndex)
+		);
 
 		const state = this.getStateAccount();
 		const orderState = this.getOrderStateAccount();
@@ -1058,6 +1567,7 @@ export class ClearingHouse {
 				isSigner: false,
 			});
 		}
+		remainingAccounts.push(...this.getBackupOracleAccounts(marketIndex));
 
 		const state = this.getStateAccount();
 		return await this.program.instruction.closePosition(
@@ -1098,23 +1608,9 @@ export class ClearingHouse {
 		const liquidateeUserAccount: any = await this.program.account.user.fetch(
 			liquidateeUserAccountPublicKey
 		);
-		const liquidateePositions: any =
-			await this.program.account.userPositions.fetch(
-				liquidateeUserAccount.positions
-			);
-		const markets = this.getMarketsAccount();
-
-		const remainingAccounts = [];
-		for (const position of liquidateePositions.positions) {
-			if (!position.baseAssetAmount.eq(new BN(0))) {
-				const market = markets.markets[position.marketIndex.toNumber()];
-				remainingAccounts.push({
-					pubkey: market.amm.oracle,
-					isWritable: false,
-					isSigner: false,
-				});
-			}
-		}
+		const remainingAccounts = await this.getUserPositionsOracleAccounts(
+			liquidateeUserAccount.positions
+		);
 
 		const state = this.getStateAccount();
 		return await this.program.instruction.liquidate({
@@ -1138,6 +1634,95 @@ export class ClearingHouse {
 		});
 	}
 
+	public async getUserPositionsOracleAccounts(
+		userPositionsPublicKey: PublicKey
+	): Promise<{ pubkey: PublicKey; isWritable: boolean; isSigner: boolean }[]> {
+		const userPositions: any = await this.program.account.userPositions.fetch(
+			userPositionsPublicKey
+		);
+		const markets = this.getMarketsAccount();
+
+		const oracleAccounts = [];
+		for (const position of userPositions.positions) {
+			if (!position.baseAssetAmount.eq(new BN(0))) {
+				const market = markets.markets[position.marketIndex.toNumber()];
+				oracleAccounts.push({
+					pubkey: market.amm.oracle,
+					isWritable: false,
+					isSigner: false,
+				});
+				if (!market.amm.backupOracle.equals(PublicKey.default)) {
+					oracleAccounts.push({
+						pubkey: market.amm.backupOracle,
+						isWritable: false,
+						isSigner: false,
+					});
+				}
+			}
+		}
+		return oracleAccounts;
+	}
+
+	public async updateOracleBasket(
+		oracleBasket: PublicKey
+	): Promise<TransactionSignature> {
+		return this.txSender.send(
+			wrapInTx(await this.getUpdateOracleBasketIx(oracleBasket)),
+			[],
+			this.opts
+		);
+	}
+
+	public async getUpdateOracleBasketIx(
+		oracleBasket: PublicKey
+	): Promise<TransactionInstruction> {
+		const oracleBasketAccount: any =
+			await this.program.account.oracleBasket.fetch(oracleBasket);
+		const remainingAccounts = oracleBasketAccount.components
+			.slice(0, oracleBasketAccount.numComponents.toNumber())
+			.map((component) => {
+				return {
+					pubkey: component.oracle,
+					isWritable: false,
+					isSigner: false,
+				};
+			});
+
+		return await this.program.instruction.updateOracleBasket({
+			accounts: {
+				oracleBasket,
+			},
+			remainingAccounts,
+		});
+	}
+
+	public async updateOracleStatus(
+		oracle: PublicKey,
+		marketIndex: BN
+	): Promise<TransactionSignature> {
+		return this.txSender.send(
+			wrapInTx(await this.getUpdateOracleStatusIx(oracle, marketIndex)),
+			[],
+			this.opts
+		);
+	}
+
+	public async getUpdateOracleStatusIx(
+		oracle: PublicKey,
+		marketIndex: BN
+	): Promise<TransactionInstruction> {
+		const state = this.getStateAccount();
+		return await this.program.instruction.updateOracleStatus(marketIndex, {
+			accounts: {
+				state: await this.getStatePublicKey(),
+				markets: state.markets,
+				oracle: oracle,
+				oracleHistory: state.oracleHistory,
+			},
+			remainingAccounts: this.getBackupOracleAccounts(marketIndex),
+		});
+	}
+
 	public async updateFundingRate(
 		oracle: PublicKey,
 		marketIndex: BN
@@ -1161,9 +1746,20 @@ export class ClearingHouse {
 				oracle: oracle,
 				fundingRateHistory: state.fundingRateHistory,
 			},
+			remainingAccounts: this.getBackupOracleAccounts(marketIndex),
 		});
 	}
 
+	getBackupOracleAccounts(
+		marketIndex: BN
+	): { pubkey: PublicKey; isWritable: boolean; isSigner: boolean }[] {
+		const backupOracle = this.getMarket(marketIndex).amm.backupOracle;
+		if (backupOracle.equals(PublicKey.default)) {
+			return [];
+		}
+		return [{ pubkey: backupOracle, isWritable: false, isSigner: false }];
+	}
+
 	public async settleFundingPayment(
 		userAccount: PublicKey,
 		userPositionsAccount: PublicKey
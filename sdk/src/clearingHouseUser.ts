@@ -29,7 +29,7 @@ import {
 	calculatePositionFundingPNL,
 	calculatePositionPNL,
 	PositionDirection,
-	getUserOrdersAccountPublicKey,
+	getUserOrdersAccountPublicKeyForUser,
 	calculateNewStateAfterOrder,
 	calculateTradeSlippage,
 	BN,
@@ -45,7 +45,6 @@ export class ClearingHouseUser {
 	authority: PublicKey;
 	accountSubscriber: UserAccountSubscriber;
 	userAccountPublicKey?: PublicKey;
-	userOrdersAccountPublicKey?: PublicKey;
 	_isSubscribed = false;
 	eventEmitter: StrictEventEmitter<EventEmitter, UserAccountEvents>;
 
@@ -177,16 +176,20 @@ export class ClearingHouseUser {
 		return this.userAccountPublicKey;
 	}
 
+	// Not cached since resizeUserOrders moves the orders to a new account
 	public async getUserOrdersAccountPublicKey(): Promise<PublicKey> {
-		if (this.userOrdersAccountPublicKey) {
-			return this.userOrdersAccountPublicKey;
-		}
+		const userAccountPublicKey = await this.getUserAccountPublicKey();
+		const userAccount = this.isSubscribed
+			? this.getUserAccount()
+			: ((await this.clearingHouse.program.account.user.fetch(
+					userAccountPublicKey
+			  )) as UserAccount);
 
-		this.userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+		return await getUserOrdersAccountPublicKeyForUser(
 			this.clearingHouse.program.programId,
-			await this.getUserAccountPublicKey()
+			userAccountPublicKey,
+			userAccount
 		);
-		return this.userOrdersAccountPublicKey;
 	}
 
 	public async exists(): Promise<boolean> {
@@ -27,6 +27,7 @@ export * from './math/trade';
 export * from './math/orders';
 export * from './orders';
 export * from './orderParams';
+export * from './userOrders';
 export * from './wallet';
 export * from './types';
 export * from './math/utils';
@@ -1,8 +1,13 @@
 import {
+	isVariant,
+	ModifyOrderParams,
+	Order,
 	OrderParams,
 	OrderTriggerCondition,
+	OrderTriggerPriceSource,
 	OrderType,
 	PositionDirection,
+	TrailingStopOffsetType,
 } from './types';
 import { BN } from '@project-serum/anchor';
 import { ZERO } from './constants/numericConstants';
@@ -15,7 +20,8 @@ export function getLimitOrderParams(
 	reduceOnly: boolean,
 	discountToken = false,
 	referrer = false,
-	userOrderId = 0
+	userOrderId = 0,
+	postOnly = false
 ): OrderParams {
 	return {
 		orderType: OrderType.LIMIT,
@@ -26,8 +32,10 @@ export function getLimitOrderParams(
 		baseAssetAmount,
 		price,
 		reduceOnly,
-		postOnly: false,
+		postOnly,
 		immediateOrCancel: false,
+		fillOrKill: false,
+		maxTs: ZERO,
 		positionLimit: ZERO,
 		padding0: true,
 		padding1: ZERO,
@@ -38,6 +46,38 @@ export function getLimitOrderParams(
 		triggerCondition: OrderTriggerCondition.ABOVE,
 		triggerPrice: ZERO,
 		oraclePriceOffset: ZERO,
+		trailingStopOffset: ZERO,
+		trailingStopOffsetType: TrailingStopOffsetType.PRICE,
+		twapSliceCount: ZERO,
+		twapInterval: ZERO,
+		triggerPriceSource: OrderTriggerPriceSource.MARK,
+	};
+}
+
+export function getOracleOffsetLimitOrderParams(
+	marketIndex: BN,
+	direction: PositionDirection,
+	baseAssetAmount: BN,
+	oraclePriceOffset: BN,
+	reduceOnly: boolean,
+	discountToken = false,
+	referrer = false,
+	userOrderId = 0,
+	postOnly = false
+): OrderParams {
+	return {
+		...getLimitOrderParams(
+			marketIndex,
+			direction,
+			baseAssetAmount,
+			ZERO,
+			reduceOnly,
+			discountToken,
+			referrer,
+			userOrderId,
+			postOnly
+		),
+		oraclePriceOffset,
 	};
 }
 
@@ -50,7 +90,8 @@ export function getTriggerMarketOrderParams(
 	reduceOnly: boolean,
 	discountToken = false,
 	referrer = false,
-	userOrderId = 0
+	userOrderId = 0,
+	triggerPriceSource = OrderTriggerPriceSource.MARK
 ): OrderParams {
 	return {
 		orderType: OrderType.TRIGGER_MARKET,
@@ -63,6 +104,8 @@ export function getTriggerMarketOrderParams(
 		reduceOnly,
 		postOnly: false,
 		immediateOrCancel: false,
+		fillOrKill: false,
+		maxTs: ZERO,
 		positionLimit: ZERO,
 		padding0: true,
 		padding1: ZERO,
@@ -73,6 +116,11 @@ export function getTriggerMarketOrderParams(
 		triggerCondition,
 		triggerPrice,
 		oraclePriceOffset: ZERO,
+		trailingStopOffset: ZERO,
+		trailingStopOffsetType: TrailingStopOffsetType.PRICE,
+		twapSliceCount: ZERO,
+		twapInterval: ZERO,
+		triggerPriceSource,
 	};
 }
 
@@ -86,7 +134,8 @@ export function getTriggerLimitOrderParams(
 	reduceOnly: boolean,
 	discountToken = false,
 	referrer = false,
-	userOrderId = 0
+	userOrderId = 0,
+	triggerPriceSource = OrderTriggerPriceSource.MARK
 ): OrderParams {
 	return {
 		orderType: OrderType.TRIGGER_LIMIT,
@@ -99,6 +148,8 @@ export function getTriggerLimitOrderParams(
 		reduceOnly,
 		postOnly: false,
 		immediateOrCancel: false,
+		fillOrKill: false,
+		maxTs: ZERO,
 		positionLimit: ZERO,
 		padding0: true,
 		padding1: ZERO,
@@ -109,6 +160,73 @@ export function getTriggerLimitOrderParams(
 		triggerCondition,
 		triggerPrice,
 		oraclePriceOffset: ZERO,
+		trailingStopOffset: ZERO,
+		trailingStopOffsetType: TrailingStopOffsetType.PRICE,
+		twapSliceCount: ZERO,
+		twapInterval: ZERO,
+		triggerPriceSource,
+	};
+}
+
+export function getTrailingStopOrderParams(
+	marketIndex: BN,
+	direction: PositionDirection,
+	baseAssetAmount: BN,
+	trailingStopOffset: BN,
+	trailingStopOffsetType: TrailingStopOffsetType,
+	reduceOnly: boolean,
+	discountToken = false,
+	referrer = false,
+	userOrderId = 0,
+	triggerPriceSource = OrderTriggerPriceSource.MARK
+): OrderParams {
+	return {
+		...getTriggerMarketOrderParams(
+			marketIndex,
+			direction,
+			baseAssetAmount,
+			ZERO,
+			isVariant(direction, 'long')
+				? OrderTriggerCondition.ABOVE
+				: OrderTriggerCondition.BELOW,
+			reduceOnly,
+			discountToken,
+			referrer,
+			userOrderId,
+			triggerPriceSource
+		),
+		orderType: OrderType.TRAILING_STOP,
+		trailingStopOffset,
+		trailingStopOffsetType,
+	};
+}
+
+export function getTwapOrderParams(
+	marketIndex: BN,
+	direction: PositionDirection,
+	baseAssetAmount: BN,
+	twapSliceCount: BN,
+	twapInterval: BN,
+	reduceOnly: boolean,
+	price: BN,
+	discountToken = false,
+	referrer = false,
+	userOrderId = 0
+): OrderParams {
+	return {
+		...getLimitOrderParams(
+			marketIndex,
+			direction,
+			baseAssetAmount,
+			price,
+			reduceOnly,
+			discountToken,
+			referrer,
+			userOrderId
+		),
+		orderType: OrderType.TWAP,
+		twapSliceCount,
+		twapInterval,
 	};
 }
 
@@ -137,6 +255,8 @@ export function getMarketOrderParams(
 		reduceOnly,
 		postOnly: false,
 		immediateOrCancel: false,
+		fillOrKill: false,
+		maxTs: ZERO,
 		positionLimit: ZERO,
 		padding0: true,
 		padding1: ZERO,
@@ -147,5 +267,27 @@ export function getMarketOrderParams(
 		triggerCondition: OrderTriggerCondition.ABOVE,
 		triggerPrice: ZERO,
 		oraclePriceOffset: ZERO,
+		trailingStopOffset: ZERO,
+		trailingStopOffsetType: TrailingStopOffsetType.PRICE,
+		twapSliceCount: ZERO,
+		twapInterval: ZERO,
+		triggerPriceSource: OrderTriggerPriceSource.MARK,
+	};
+}
+
+export function getModifyOrderParams(
+	order: Order,
+	{
+		baseAssetAmount = order.baseAssetAmount,
+		price = order.price,
+		triggerPrice = order.triggerPrice,
+		reduceOnly = order.reduceOnly,
+	}: Partial<ModifyOrderParams>
+): ModifyOrderParams {
+	return {
+		baseAssetAmount,
+		price,
+		triggerPrice,
+		reduceOnly,
 	};
 }
@@ -21,6 +21,7 @@ import {
 } from './math/market';
 import {
 	AMM_TO_QUOTE_PRECISION_RATIO,
+	ONE,
 	TWO,
 	PEG_PRECISION,
 	ZERO,
@@ -171,12 +172,15 @@ function calculateAmountSwapped(
 
 export function calculateBaseAssetAmountMarketCanExecute(
 	market: Market,
-	order: Order
+	order: Order,
+	oraclePrice?: BN
 ): BN {
 	if (isVariant(order.orderType, 'limit')) {
-		return calculateAmountToTradeForLimit(market, order);
+		return calculateAmountToTradeForLimit(market, order, oraclePrice);
 	} else if (isVariant(order.orderType, 'triggerLimit')) {
 		return calculateAmountToTradeForTriggerLimit(market, order);
+	} else if (isVariant(order.orderType, 'twap')) {
+		return calculateAmountToTradeForTwap(market, order);
 	} else if (isVariant(order.orderType, 'market')) {
 		// should never be a market order queued
 		return ZERO;
@@ -185,13 +189,32 @@ export function calculateBaseAssetAmountMarketCanExecute(
 	}
 }
 
+export function getLimitPrice(order: Order, oraclePrice?: BN): BN | undefined {
+	if (order.oraclePriceOffset.eq(ZERO)) {
+		return order.price;
+	}
+
+	if (!oraclePrice) {
+		return undefined;
+	}
+
+	const limitPrice = oraclePrice.add(order.oraclePriceOffset);
+	return limitPrice.gt(ZERO) ? limitPrice : undefined;
+}
+
 export function calculateAmountToTradeForLimit(
 	market: Market,
-	order: Order
+	order: Order,
+	oraclePrice?: BN
 ): BN {
+	const limitPrice = getLimitPrice(order, oraclePrice);
+	if (!limitPrice) {
+		return ZERO;
+	}
+
 	const [maxAmountToTrade, direction] = calculateMaxBaseAssetAmountToTrade(
 		market.amm,
-		order.price
+		limitPrice
 	);
 
 	// Check that directions are the same
@@ -222,6 +245,47 @@ export function calculateAmountToTradeForTriggerLimit(
 	return calculateAmountToTradeForLimit(market, order);
 }
 
+export function getTwapSliceBaseAssetAmount(order: Order): BN {
+	return order.baseAssetAmount
+		.add(order.twapSliceCount)
+		.sub(ONE)
+		.div(order.twapSliceCount);
+}
+
+export function getTwapNextSliceTs(order: Order): BN {
+	return order.twapLastSliceTs.eq(ZERO)
+		? order.ts
+		: order.twapLastSliceTs.add(order.twapInterval);
+}
+
+export function calculateAmountToTradeForTwap(
+	market: Market,
+	order: Order
+): BN {
+	const baseAssetAmountToFill = order.baseAssetAmount.sub(
+		order.baseAssetAmountFilled
+	);
+	const sliceBaseAssetAmount = BN.min(
+		baseAssetAmountToFill,
+		getTwapSliceBaseAssetAmount(order)
+	);
+
+	if (order.price.eq(ZERO)) {
+		return sliceBaseAssetAmount;
+	}
+
+	const [maxAmountToTrade, direction] = calculateMaxBaseAssetAmountToTrade(
+		market.amm,
+		order.price
+	);
+
+	if (!isSameDirection(direction, order.direction)) {
+		return ZERO;
+	}
+
+	return BN.min(sliceBaseAssetAmount, maxAmountToTrade);
+}
+
 function isSameDirection(
 	firstDirection: PositionDirection,
 	secondDirection: PositionDirection
@@ -243,10 +307,44 @@ function calculateAmountToTradeForTriggerMarket(
 
 function isTriggerConditionSatisfied(market: Market, order: Order): boolean {
 	const markPrice = calculateMarkPrice(market);
+	const triggerPrice = isVariant(order.orderType, 'trailingStop')
+		? getTrailingStopTriggerPrice(order, markPrice)
+		: order.triggerPrice;
+	const triggerSourcePrice = getTriggerSourcePrice(market, order, markPrice);
 	if (isVariant(order.triggerCondition, 'above')) {
-		return markPrice.gt(order.triggerPrice);
+		return triggerSourcePrice.gt(triggerPrice);
 	} else {
-		return markPrice.lt(order.triggerPrice);
+		return triggerSourcePrice.lt(triggerPrice);
+	}
+}
+
+function getTriggerSourcePrice(
+	market: Market,
+	order: Order,
+	markPrice: BN
+): BN {
+	if (isVariant(order.triggerPriceSource, 'oracle')) {
+		return market.amm.lastOraclePrice;
+	} else if (isVariant(order.triggerPriceSource, 'oracleTwap')) {
+		return market.amm.lastOraclePriceTwap;
+	} else {
+		return markPrice;
+	}
+}
+
+export function getTrailingStopTriggerPrice(order: Order, markPrice: BN): BN {
+	const offset = isVariant(order.trailingStopOffsetType, 'percentage')
+		? markPrice.mul(order.trailingStopOffset).div(TEN_THOUSAND)
+		: order.trailingStopOffset;
+
+	if (isVariant(order.triggerCondition, 'below')) {
+		const triggerPrice = BN.max(markPrice.sub(offset), ZERO);
+		return BN.max(order.triggerPrice, triggerPrice);
+	} else {
+		const triggerPrice = markPrice.add(offset);
+		return order.triggerPrice.eq(ZERO)
+			? triggerPrice
+			: BN.min(order.triggerPrice, triggerPrice);
 	}
 }
 
@@ -285,5 +383,32 @@ export function calculateBaseAssetAmountUserCanExecute(
 			: SwapDirection.REMOVE
 	);
 
-	return baseAssetReservesBefore.sub(baseAssetReservesAfter).abs();
+	const baseAssetAmount = baseAssetReservesBefore
+		.sub(baseAssetReservesAfter)
+		.abs();
+
+	const position =
+		user.getUserPosition(order.marketIndex) ||
+		user.getEmptyPosition(order.marketIndex);
+	const baseAssetAmountToPositionLimit = getBaseAssetAmountToPositionLimit(
+		order,
+		position.baseAssetAmount
+	);
+	return baseAssetAmountToPositionLimit
+		? BN.min(baseAssetAmount, baseAssetAmountToPositionLimit)
+		: baseAssetAmount;
+}
+
+export function getBaseAssetAmountToPositionLimit(
+	order: Order,
+	positionBaseAssetAmount: BN
+): BN | undefined {
+	if (order.positionLimit.eq(ZERO)) {
+		return undefined;
+	}
+
+	const baseAssetAmountToPositionLimit = isVariant(order.direction, 'long')
+		? order.positionLimit.sub(positionBaseAssetAmount)
+		: order.positionLimit.add(positionBaseAssetAmount);
+	return BN.max(baseAssetAmountToPositionLimit, ZERO);
 }
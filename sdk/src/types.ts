@@ -15,12 +15,21 @@ export class PositionDirection {
 export class OracleSource {
 	static readonly PYTH = { pyth: {} };
 	static readonly SWITCHBOARD = { switchboard: {} };
+	static readonly BASKET = { basket: {} };
+	static readonly RATIO = { ratio: {} };
+}
+
+export class OracleBasketType {
+	static readonly WEIGHTED_SUM = { weightedSum: {} };
+	static readonly RATIO = { ratio: {} };
 }
 
 export class OrderType {
 	static readonly LIMIT = { limit: {} };
 	static readonly TRIGGER_MARKET = { triggerMarket: {} };
 	static readonly TRIGGER_LIMIT = { triggerLimit: {} };
+	static readonly TRAILING_STOP = { trailingStop: {} };
+	static readonly TWAP = { twap: {} };
 	static readonly MARKET = { market: {} };
 }
 
@@ -41,6 +50,9 @@ export class OrderAction {
 	static readonly PLACE = { place: {} };
 	static readonly CANCEL = { cancel: {} };
 	static readonly FILL = { fill: {} };
+	static readonly POST_ONLY_CANCEL = { postOnlyCancel: {} };
+	static readonly EXPIRE = { expire: {} };
+	static readonly REDUCE_ONLY_CANCEL = { reduceOnlyCancel: {} };
 }
 
 export class OrderTriggerCondition {
@@ -48,6 +60,17 @@ export class OrderTriggerCondition {
 	static readonly BELOW = { below: {} };
 }
 
+export class OrderTriggerPriceSource {
+	static readonly MARK = { mark: {} };
+	static readonly ORACLE = { oracle: {} };
+	static readonly ORACLE_TWAP = { oracleTwap: {} };
+}
+
+export class TrailingStopOffsetType {
+	static readonly PRICE = { price: {} };
+	static readonly PERCENTAGE = { percentage: {} };
+}
+
 export function isVariant(object: unknown, type: string) {
 	return object.hasOwnProperty(type);
 }
@@ -89,6 +112,11 @@ export type FundingRateHistoryAccount = {
 	fundingRateRecords: FundingRateRecord[];
 };
 
+export type OracleHistoryAccount = {
+	head: BN;
+	oracleRecords: OracleRecord[];
+};
+
 export type FundingPaymentHistoryAccount = {
 	head: BN;
 	fundingPaymentRecords: FundingPaymentRecord[];
@@ -172,6 +200,27 @@ export type FundingRateRecord = {
 	markPriceTwap: BN;
 };
 
+export class OracleRecordType {
+	static readonly INVALID = { invalid: {} };
+	static readonly TOO_DIVERGENT = { tooDivergent: {} };
+	static readonly RECOVERED = { recovered: {} };
+}
+
+export type OracleRecord = {
+	ts: BN;
+	recordId: BN;
+	marketIndex: BN;
+	oracle: PublicKey;
+	recordType: OracleRecordType;
+	oraclePrice: BN;
+	oracleConfidence: BN;
+	oracleDelay: BN;
+	markPrice: BN;
+	oracleMarkSpreadPct: BN;
+	consecutiveInvalidReadings: BN;
+	reduceOnly: boolean;
+};
+
 export type FundingPaymentRecord = {
 	ts: BN;
 	recordId: BN;
@@ -258,12 +307,16 @@ export type StateAccount = {
 	maxDeposit: BN;
 	orderState: PublicKey;
 	extendedCurveHistory: PublicKey;
+	oracleHistory: PublicKey;
+	oracleCircuitBreakerThreshold: BN;
+	extendedOracleGuardRails: ExtendedOracleGuardRails;
 };
 
 export type OrderStateAccount = {
 	orderHistory: PublicKey;
 	orderFillerRewardStructure: OrderFillerRewardStructure;
 	minOrderQuoteAssetAmount: BN;
+	expireOrderReward: BN;
 };
 
 export type MarketsAccount = {
@@ -278,6 +331,40 @@ export type Market = {
 	baseAssetAmountShort: BN;
 	initialized: boolean;
 	openInterest: BN;
+	oracleGuardRails: MarketOracleGuardRails;
+	consecutiveInvalidOracleReadings: BN;
+	reduceOnly: boolean;
+	lastInvalidOracleReadingSlot: BN;
+};
+
+export type OracleBasketComponent = {
+	oracle: PublicKey;
+	weight: BN;
+};
+
+export type OracleBasketAccount = {
+	numComponents: BN;
+	components: OracleBasketComponent[];
+	price: BN;
+	twap: BN;
+	confidence: BN;
+	twapConfidence: BN;
+	validSlot: BN;
+	numPublishers: number;
+	basketType: OracleBasketType;
+};
+
+export type MarketOracleGuardRails = {
+	enabled: boolean;
+	useForLiquidations: boolean;
+	requireTradingStatus: boolean;
+	minPublishers: number;
+	slotsBeforeStale: BN;
+	confidenceIntervalMaxSize: BN;
+	tooVolatileRatio: BN;
+	markOracleDivergenceNumerator: BN;
+	markOracleDivergenceDenominator: BN;
+	useConfidenceIntervalForMargin: boolean;
 };
 
 export type AMM = {
@@ -292,6 +379,8 @@ export type AMM = {
 	lastOraclePriceTwapTs: BN;
 	oracle: PublicKey;
 	oracleSource: OracleSource;
+	backupOracle: PublicKey;
+	backupOracleSource: OracleSource;
 	fundingPeriod: BN;
 	quoteAssetReserve: BN;
 	pegMultiplier: BN;
@@ -326,6 +415,7 @@ export type UserAccount = {
 	collateral: BN;
 	cumulativeDeposits: BN;
 	positions: PublicKey;
+	userOrders: PublicKey;
 	totalFeePaid: BN;
 	totalTokenDiscount: BN;
 	totalReferralReward: BN;
@@ -359,7 +449,17 @@ export type Order = {
 	referrer: PublicKey;
 	postOnly: boolean;
 	immediateOrCancel: boolean;
+	fillOrKill: boolean;
 	oraclePriceOffset: BN;
+	maxTs: BN;
+	groupId: BN;
+	trailingStopOffset: BN;
+	trailingStopOffsetType: TrailingStopOffsetType;
+	twapSliceCount: BN;
+	twapInterval: BN;
+	twapLastSliceTs: BN;
+	positionLimit: BN;
+	triggerPriceSource: OrderTriggerPriceSource;
 };
 
 export type OrderParams = {
@@ -373,16 +473,30 @@ export type OrderParams = {
 	reduceOnly: boolean;
 	postOnly: boolean;
 	immediateOrCancel: boolean;
+	fillOrKill: boolean;
+	maxTs: BN;
 	triggerPrice: BN;
 	triggerCondition: OrderTriggerCondition;
 	positionLimit: BN;
 	oraclePriceOffset: BN;
+	trailingStopOffset: BN;
+	trailingStopOffsetType: TrailingStopOffsetType;
+	twapSliceCount: BN;
+	twapInterval: BN;
+	triggerPriceSource: OrderTriggerPriceSource;
 	padding0: boolean;
 	padding1: BN;
 	optionalAccounts: {
 		discountToken: boolean;
 		referrer: boolean;
 	};
+
+export type ModifyOrderParams = {
+	baseAssetAmount: BN;
+	price: BN;
+	triggerPrice: BN;
+	reduceOnly: boolean;
+};
 };
 
 // # Misc Types
@@ -438,6 +552,12 @@ export type OracleGuardRails = {
 	useForLiquidations: boolean;
 };
 
+export type ExtendedOracleGuardRails = {
+	requireTradingStatus: boolean;
+	minPublishers: number;
+	useConfidenceIntervalForMargin: boolean;
+};
+
 export type OrderFillerRewardStructure = {
 	rewardNumerator: BN;
 	rewardDenominator: BN;
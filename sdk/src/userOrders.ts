@@ -0,0 +1,44 @@
+import { Program } from '@project-serum/anchor';
+import { PublicKey } from '@solana/web3.js';
+import { Order, UserOrdersAccount } from './types';
+
+const ACCOUNT_DISCRIMINATOR_SIZE = 8;
+const USER_ORDERS_HEADER_SIZE = ACCOUNT_DISCRIMINATOR_SIZE + 32;
+export const ORDER_SIZE = 306;
+
+// Capacity of the user orders account made by initializeUserOrders
+export const DEFAULT_USER_ORDERS_CAPACITY = 32;
+
+export function getUserOrdersAccountSize(capacity: number): number {
+	return USER_ORDERS_HEADER_SIZE + capacity * ORDER_SIZE;
+}
+
+export function getUserOrdersAccountCapacity(dataSize: number): number {
+	return Math.floor((dataSize - USER_ORDERS_HEADER_SIZE) / ORDER_SIZE);
+}
+
+/**
+ * User orders accounts hold as many orders as they have room for, so they can't be decoded with the
+ * fixed size layout in the idl
+ * @param program
+ * @param buffer
+ * @returns
+ */
+export function decodeUserOrdersAccount(
+	program: Program,
+	buffer: Buffer
+): UserOrdersAccount {
+	const user = new PublicKey(
+		buffer.slice(ACCOUNT_DISCRIMINATOR_SIZE, USER_ORDERS_HEADER_SIZE)
+	);
+
+	const orders: Order[] = [];
+	const capacity = getUserOrdersAccountCapacity(buffer.length);
+	for (let i = 0; i < capacity; i++) {
+		const offset = USER_ORDERS_HEADER_SIZE + i * ORDER_SIZE;
+		const orderBuffer = buffer.slice(offset, offset + ORDER_SIZE);
+		orders.push(program.coder.types.decode('Order', orderBuffer));
+	}
+
+	return { user, orders };
+}
@@ -4,8 +4,24 @@ if [ "$1" != "--skip-build" ]
     cp target/idl/clearing_house.json sdk/src/idl/
 fi
 
-test_files=(order.ts orderReferrer.ts marketOrder.ts triggerOrders.ts stopLimits.ts userOrderId.ts roundInFavorBaseAsset.ts marketOrderBaseAssetAmount.ts clearingHouse.ts pyth.ts userAccount.ts admin.ts updateK.ts adminWithdraw.ts curve.ts whitelist.ts fees.ts idempotentCurve.ts maxDeposit.ts deleteUser.ts maxPositions.ts maxReserves.ts twapDivergenceLiquidation.ts oraclePnlLiquidation.ts whaleLiquidation.ts roundInFavor.ts minimumTradeSize.ts cappedSymFunding.ts)
+test_files=(order.ts orderReferrer.ts marketOrder.ts triggerOrders.ts stopLimits.ts userOrderId.ts roundInFavorBaseAsset.ts marketOrderBaseAssetAmount.ts clearingHouse.ts pyth.ts userAccount.ts admin.ts updateK.ts adminWithdraw.ts curve.ts whitelist.ts fees.ts idempotentCurve.ts maxDeposit.ts deleteUser.ts maxPositions.ts maxReserves.ts twapDivergenceLiquidation.ts oraclePnlLiquidation.ts whaleLiquidation.ts roundInFavor.ts minimumTradeSize.ts cappedSymFunding.ts oracleBasket.ts oracleCircuitBreaker.ts backupOracle.ts oracleStatusGuardRails.ts marketOracleGuardRails.ts oracleConfidenceMargin.ts switchboard.ts postOnly.ts immediateOrCancel.ts oracleOffsetOrders.ts expireOrder.ts modifyOrder.ts cancelAllOrders.ts oneCancelsOther.ts trailingStop.ts twapOrders.ts positionLimit.ts matchOrders.ts fillOrders.ts triggerPriceSource.ts userOrdersCapacity.ts reduceOnlyOrders.ts)
 
 for test_file in ${test_files[@]}; do
   export ANCHOR_TEST_FILE=${test_file} && anchor test --skip-build || exit 1;
-done
\ No newline at end of file
+done
+
+# Legacy layout accounts can't be made by the current program, so the migration test loads them
+# into its own validator
+solana-test-validator --reset --quiet --ledger .anchor/legacy-test-ledger \
+  --bpf-program AsW7LnXB9UA1uec9wi9MctYTgTz7YH9snhxd16GsFaGX target/deploy/clearing_house.so \
+  --account 4dqreEcLDqF6mH8B9FZt7qVXrVjUe4FFGkCggY8isXQP tests/fixtures/legacyUserOrders/user.json \
+  --account EDU8j61e4g2Qej9qY1DbjCLKdkkrpB1XYALg9z3L7h3S tests/fixtures/legacyUserOrders/userOrders.json &
+validator_pid=$!
+until solana cluster-version --url localhost > /dev/null 2>&1; do sleep 1; done
+
+export ANCHOR_PROVIDER_URL=http://localhost:8899
+export ANCHOR_WALLET=~/.config/solana/id.json
+export ANCHOR_TEST_FILE=legacyUserOrders.ts && ./test-scripts/run-ts-mocha
+result=$?
+kill ${validator_pid}
+exit ${result}
@@ -8,6 +8,7 @@ import {
 	MARK_PRICE_PRECISION,
 	FeeStructure,
 	OracleGuardRails,
+	ExtendedOracleGuardRails,
 	OrderFillerRewardStructure,
 } from '../sdk/src';
 import { OracleSource } from '../sdk';
@@ -223,6 +224,26 @@ describe('admin', () => {
 		);
 	});
 
+	it('Update extended oracle guard rails', async () => {
+		const extendedOracleGuardRails: ExtendedOracleGuardRails = {
+			requireTradingStatus: false,
+			minPublishers: 1,
+			useConfidenceIntervalForMargin: true,
+		};
+
+		await clearingHouse.updateExtendedOracleGuardRails(
+			extendedOracleGuardRails
+		);
+
+		await clearingHouse.fetchAccounts();
+		const state = clearingHouse.getStateAccount();
+
+		assert(
+			JSON.stringify(extendedOracleGuardRails) ===
+				JSON.stringify(state.extendedOracleGuardRails)
+		);
+	});
+
 	it('Update protocol mint', async () => {
 		const mint = new PublicKey('2fvh6hkCYfpNqke9N48x6HcrW92uZVU3QSiXZX4A5L27');
 
@@ -319,6 +340,59 @@ describe('admin', () => {
 		assert(market.amm.minimumBaseAssetTradeSize.eq(minimumTradeSize));
 	});
 
+	it('Update market oracle guard rails', async () => {
+		const oracleGuardRails: OracleGuardRails = {
+			priceDivergence: {
+				markOracleDivergenceNumerator: new BN(1),
+				markOracleDivergenceDenominator: new BN(5),
+			},
+			validity: {
+				slotsBeforeStale: new BN(10),
+				confidenceIntervalMaxSize: new BN(20),
+				tooVolatileRatio: new BN(2),
+			},
+			useForLiquidations: true,
+		};
+		const extendedOracleGuardRails: ExtendedOracleGuardRails = {
+			requireTradingStatus: true,
+			minPublishers: 3,
+			useConfidenceIntervalForMargin: true,
+		};
+
+		await clearingHouse.updateMarketOracleGuardRails(
+			Markets[0].marketIndex,
+			oracleGuardRails,
+			extendedOracleGuardRails
+		);
+
+		await clearingHouse.fetchAccounts();
+		let market =
+			clearingHouse.getMarketsAccount().markets[
+				Markets[0].marketIndex.toNumber()
+			];
+		assert(market.oracleGuardRails.enabled);
+		assert(market.oracleGuardRails.slotsBeforeStale.eq(new BN(10)));
+		assert(market.oracleGuardRails.confidenceIntervalMaxSize.eq(new BN(20)));
+		assert(market.oracleGuardRails.tooVolatileRatio.eq(new BN(2)));
+		assert(market.oracleGuardRails.minPublishers === 3);
+		assert(market.oracleGuardRails.useConfidenceIntervalForMargin);
+		assert(
+			market.oracleGuardRails.markOracleDivergenceDenominator.eq(new BN(5))
+		);
+
+		await clearingHouse.updateMarketOracleGuardRails(
+			Markets[0].marketIndex,
+			null
+		);
+
+		await clearingHouse.fetchAccounts();
+		market =
+			clearingHouse.getMarketsAccount().markets[
+				Markets[0].marketIndex.toNumber()
+			];
+		assert(!market.oracleGuardRails.enabled);
+	});
+
 	it('Pause funding', async () => {
 		await clearingHouse.updateFundingPaused(true);
 		await clearingHouse.fetchAccounts();
@@ -0,0 +1,224 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+import { Transaction } from '@solana/web3.js';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	ClearingHouseUser,
+	OracleSource,
+	OrderTriggerCondition,
+	OrderTriggerPriceSource,
+	getTriggerMarketOrderParams,
+	getUserOrdersAccountPublicKey,
+	isVariant,
+} from '../sdk/src';
+
+import {
+	FeedStatus,
+	mockOracle,
+	mockUSDCMint,
+	mockUserUSDCAccount,
+	setFeedStatus,
+} from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('backup oracle', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+	let backupSolUsd;
+
+	let userAccountPublicKey;
+	let userOrdersAccountPublicKey;
+
+	const placeOracleTriggerOrder = async (triggerPrice: BN) => {
+		const orderParams = getTriggerMarketOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			AMM_RESERVE_PRECISION,
+			triggerPrice,
+			OrderTriggerCondition.BELOW,
+			false,
+			false,
+			false,
+			0,
+			OrderTriggerPriceSource.ORACLE
+		);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		return clearingHouseUser.getUserOrdersAccount().orders[0];
+	};
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+		backupSolUsd = await mockOracle(0.92);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+		await clearingHouse.updateMarketBackupOracle(
+			marketIndex,
+			backupSolUsd,
+			OracleSource.PYTH
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+	});
+
+	it('Fill with backup oracle when primary oracle is invalid', async () => {
+		await setFeedStatus(anchor.workspace.Pyth, FeedStatus.HALTED, solUsd);
+
+		// only the backup oracle is below the trigger price
+		const order = await placeOracleTriggerOrder(
+			MARK_PRICE_PRECISION.mul(new BN(95)).div(new BN(100))
+		);
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(AMM_RESERVE_PRECISION.neg()));
+		assert(position.openOrders.eq(ZERO));
+	});
+
+	it('Use median of both oracles when both are valid', async () => {
+		await setFeedStatus(anchor.workspace.Pyth, FeedStatus.TRADING, solUsd);
+
+		// the median of 0.96 is above the trigger price, though the backup is below
+		const order = await placeOracleTriggerOrder(
+			MARK_PRICE_PRECISION.mul(new BN(95)).div(new BN(100))
+		);
+		let fillFailed = false;
+		try {
+			await clearingHouse.fillOrder(
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				order
+			);
+		} catch (e) {
+			fillFailed = true;
+		}
+		assert(fillFailed);
+		await clearingHouse.cancelOrder(order.orderId);
+
+		const medianOrder = await placeOracleTriggerOrder(
+			MARK_PRICE_PRECISION.mul(new BN(97)).div(new BN(100))
+		);
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			medianOrder
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(
+			position.baseAssetAmount.eq(AMM_RESERVE_PRECISION.mul(new BN(-2)))
+		);
+		assert(position.openOrders.eq(ZERO));
+
+		const orderAfter = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(orderAfter.status, 'init'));
+	});
+
+	it('Fail to fill without the backup oracle', async () => {
+		// the median of 0.96 is below the trigger price
+		const order = await placeOracleTriggerOrder(
+			MARK_PRICE_PRECISION.mul(new BN(97)).div(new BN(100))
+		);
+		const fillOrderIx = await clearingHouse.getFillOrderIx(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
+		fillOrderIx.keys = fillOrderIx.keys.filter(
+			(key) => !key.pubkey.equals(backupSolUsd)
+		);
+		let fillFailed = false;
+		try {
+			await provider.send(new Transaction().add(fillOrderIx));
+		} catch (e) {
+			fillFailed = true;
+		}
+		assert(fillFailed);
+
+		await clearingHouseUser.fetchAccounts();
+		const orderAfter = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(orderAfter.orderId.eq(order.orderId));
+		await clearingHouse.cancelOrder(order.orderId);
+	});
+
+	it('Fail to pass the backup oracle as the market oracle', async () => {
+		try {
+			await clearingHouse.updateFundingRate(backupSolUsd, marketIndex);
+			assert(false);
+		} catch (e) {
+			assert(e.msg === 'InvalidOracle');
+		}
+	});
+});
@@ -0,0 +1,172 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	ClearingHouseUser,
+	getLimitOrderParams,
+	isVariant,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('cancel all orders', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	const marketIndexEth = new BN(1);
+	let solUsd;
+	let ethUsd;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		ethUsd = await mockOracle(1);
+		await clearingHouse.initializeMarket(
+			marketIndexEth,
+			ethUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		await clearingHouse.initializeUserAccountAndDepositCollateral(
+			usdcAmount,
+			userUSDCAccount.publicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+	});
+
+
+	const placeLimitOrder = async (
+		marketIndex: BN,
+		direction: PositionDirection,
+		reduceOnly: boolean
+	) => {
+		const price = isVariant(direction, 'long')
+			? MARK_PRICE_PRECISION.div(new BN(2))
+			: MARK_PRICE_PRECISION.mul(new BN(2));
+		await clearingHouse.placeOrder(
+			getLimitOrderParams(
+				marketIndex,
+				direction,
+				AMM_RESERVE_PRECISION,
+				price,
+				reduceOnly
+			)
+		);
+	};
+
+	const getOpenOrders = () =>
+		clearingHouseUser
+			.getUserOrdersAccount()
+			.orders.filter((order) => isVariant(order.status, 'open'));
+
+	const getPositionOpenOrders = (marketIndex: BN) =>
+		clearingHouseUser
+			.getUserPositionsAccount()
+			.positions.find((position) => position.marketIndex.eq(marketIndex))
+			.openOrders;
+
+	it('Cancel reduce only longs in one market', async () => {
+		await placeLimitOrder(marketIndex, PositionDirection.LONG, false);
+		await placeLimitOrder(marketIndex, PositionDirection.SHORT, false);
+		await placeLimitOrder(marketIndex, PositionDirection.LONG, true);
+		await placeLimitOrder(marketIndexEth, PositionDirection.LONG, false);
+
+		await clearingHouse.cancelAllOrders(
+			marketIndex,
+			PositionDirection.LONG,
+			true
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		const openOrders = getOpenOrders();
+		assert(openOrders.length === 3);
+		assert(!openOrders.some((order) => order.reduceOnly));
+		assert(getPositionOpenOrders(marketIndex).eq(new BN(2)));
+		assert(getPositionOpenOrders(marketIndexEth).eq(new BN(1)));
+	});
+
+	it('Cancel shorts in one market', async () => {
+		await clearingHouse.cancelAllOrders(marketIndex, PositionDirection.SHORT);
+
+		await clearingHouseUser.fetchAccounts();
+		const openOrders = getOpenOrders();
+		assert(openOrders.length === 2);
+		assert(openOrders.every((order) => isVariant(order.direction, 'long')));
+		assert(getPositionOpenOrders(marketIndex).eq(new BN(1)));
+	});
+
+	it('Cancel all remaining orders', async () => {
+		await clearingHouse.cancelAllOrders();
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		assert(getOpenOrders().length === 0);
+		assert(getPositionOpenOrders(marketIndex).eq(ZERO));
+		assert(getPositionOpenOrders(marketIndexEth).eq(ZERO));
+
+		// one cancel record per cancelled order
+		const cancelRecords = clearingHouse
+			.getOrderHistoryAccount()
+			.orderRecords.filter((record) => isVariant(record.action, 'cancel'));
+		assert(cancelRecords.length === 4);
+	});
+});
@@ -0,0 +1,261 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import { Keypair, PublicKey } from '@solana/web3.js';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	ClearingHouse,
+	PositionDirection,
+	getUserOrdersAccountPublicKey,
+	ClearingHouseUser,
+	OrderRecord,
+	Wallet,
+	getLimitOrderParams,
+	isVariant,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('expire order', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let userAccountPublicKey: PublicKey;
+	let userOrdersAccountPublicKey: PublicKey;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const fillerKeyPair = new Keypair();
+	let fillerUSDCAccount: Keypair;
+	let fillerClearingHouse: ClearingHouse;
+	let fillerUser: ClearingHouseUser;
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	const expireOrderReward = new BN(10000);
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId,
+			{
+				commitment: 'confirmed',
+			}
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+
+		provider.connection.requestAirdrop(fillerKeyPair.publicKey, 10 ** 9);
+		fillerUSDCAccount = await mockUserUSDCAccount(
+			usdcMint,
+			usdcAmount,
+			provider,
+			fillerKeyPair.publicKey
+		);
+		fillerClearingHouse = ClearingHouse.from(
+			connection,
+			new Wallet(fillerKeyPair),
+			chProgram.programId,
+			{
+				commitment: 'confirmed',
+			}
+		);
+		await fillerClearingHouse.subscribe();
+
+		await fillerClearingHouse.initializeUserAccountAndDepositCollateral(
+			usdcAmount,
+			fillerUSDCAccount.publicKey
+		);
+
+		fillerUser = ClearingHouseUser.from(
+			fillerClearingHouse,
+			fillerKeyPair.publicKey
+		);
+		await fillerUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+		await fillerClearingHouse.unsubscribe();
+		await fillerUser.unsubscribe();
+	});
+
+	it('Update expire order reward', async () => {
+		await clearingHouse.updateExpireOrderReward(expireOrderReward);
+
+		const orderState = await clearingHouse.program.account.orderState.fetch(
+			await clearingHouse.getOrderStatePublicKey()
+		);
+		assert(orderState.expireOrderReward.eq(expireOrderReward));
+	});
+
+	it('Fail to place order that has already expired', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.div(new BN(2)),
+			false
+		);
+		orderParams.maxTs = new BN(1);
+
+		try {
+			await clearingHouse.placeOrder(orderParams);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Fail to expire order before max ts', async () => {
+		const now = await connection.getBlockTime(await connection.getSlot());
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.div(new BN(2)),
+			false
+		);
+		orderParams.maxTs = new BN(now + 2);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(order.maxTs.eq(orderParams.maxTs));
+
+		try {
+			await fillerClearingHouse.expireOrder(
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				order
+			);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Fail to expire order while exchange is paused', async () => {
+		await new Promise((r) => setTimeout(r, 4000)); // wait 4 seconds
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+
+		await clearingHouse.updateExchangePaused(true);
+		try {
+			await fillerClearingHouse.expireOrder(
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				order
+			);
+			assert(false);
+		} catch (e) {
+			assert(e.msg === 'Exchange is paused');
+		}
+		await clearingHouse.updateExchangePaused(false);
+	});
+
+	it('Expire order after max ts', async () => {
+		await new Promise((r) => setTimeout(r, 4000)); // wait 4 seconds
+
+		await clearingHouseUser.fetchAccounts();
+		await fillerUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		const userCollateralBefore = clearingHouseUser.getUserAccount().collateral;
+		const fillerCollateralBefore = fillerUser.getUserAccount().collateral;
+
+		await fillerClearingHouse.expireOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		await fillerUser.fetchAccounts();
+
+		const orderAfter = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(orderAfter.status, 'init'));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.openOrders.eq(ZERO));
+
+		assert(
+			clearingHouseUser
+				.getUserAccount()
+				.collateral.eq(userCollateralBefore.sub(expireOrderReward))
+		);
+		assert(
+			fillerUser
+				.getUserAccount()
+				.collateral.eq(fillerCollateralBefore.add(expireOrderReward))
+		);
+
+		const orderRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[1];
+		assert(isVariant(orderRecord.action, 'expire'));
+		assert(orderRecord.order.orderId.eq(order.orderId));
+		assert(
+			orderRecord.filler.equals(await fillerUser.getUserAccountPublicKey())
+		);
+		assert(orderRecord.fillerReward.eq(expireOrderReward));
+		assert(orderRecord.fee.eq(expireOrderReward));
+	});
+});
@@ -0,0 +1,293 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import { Keypair, PublicKey } from '@solana/web3.js';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	ClearingHouse,
+	PositionDirection,
+	getUserOrdersAccountPublicKey,
+	ClearingHouseUser,
+	OrderRecord,
+	TradeRecord,
+	Wallet,
+	getLimitOrderParams,
+	isVariant,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('fill orders', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let userAccountPublicKey: PublicKey;
+	let userOrdersAccountPublicKey: PublicKey;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const fillerKeyPair = new Keypair();
+	let fillerUSDCAccount: Keypair;
+	let fillerClearingHouse: ClearingHouse;
+	let fillerUser: ClearingHouseUser;
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId,
+			{
+				commitment: 'confirmed',
+			}
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+
+		provider.connection.requestAirdrop(fillerKeyPair.publicKey, 10 ** 9);
+		fillerUSDCAccount = await mockUserUSDCAccount(
+			usdcMint,
+			usdcAmount,
+			provider,
+			fillerKeyPair.publicKey
+		);
+		fillerClearingHouse = ClearingHouse.from(
+			connection,
+			new Wallet(fillerKeyPair),
+			chProgram.programId,
+			{
+				commitment: 'confirmed',
+			}
+		);
+		await fillerClearingHouse.subscribe();
+
+		await fillerClearingHouse.initializeUserAccountAndDepositCollateral(
+			usdcAmount,
+			fillerUSDCAccount.publicKey
+		);
+
+		fillerUser = ClearingHouseUser.from(
+			fillerClearingHouse,
+			fillerKeyPair.publicKey
+		);
+		await fillerUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+		await fillerClearingHouse.unsubscribe();
+		await fillerUser.unsubscribe();
+	});
+
+	it('Fill a batch of orders and skip the unfillable one', async () => {
+		const prices = [
+			MARK_PRICE_PRECISION.mul(new BN(105)).div(new BN(100)),
+			MARK_PRICE_PRECISION.div(new BN(2)),
+			MARK_PRICE_PRECISION.mul(new BN(110)).div(new BN(100)),
+		];
+		for (const price of prices) {
+			const orderParams = getLimitOrderParams(
+				marketIndex,
+				PositionDirection.LONG,
+				AMM_RESERVE_PRECISION,
+				price,
+				false
+			);
+			await clearingHouse.placeOrder(orderParams);
+		}
+
+		await clearingHouseUser.fetchAccounts();
+		await fillerUser.fetchAccounts();
+		const fillerCollateralBefore = fillerUser.getUserAccount().collateral;
+		const orders = clearingHouseUser.getUserOrdersAccount().orders.slice(0, 3);
+
+		await fillerClearingHouse.fillOrders(
+			orders.map((order) => ({
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				order,
+			}))
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		await fillerUser.fetchAccounts();
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(AMM_RESERVE_PRECISION.mul(new BN(2))));
+		assert(position.openOrders.eq(new BN(1)));
+
+		const userOrdersAccount = clearingHouseUser.getUserOrdersAccount();
+		assert(isVariant(userOrdersAccount.orders[0].status, 'init'));
+		assert(isVariant(userOrdersAccount.orders[1].status, 'open'));
+		assert(userOrdersAccount.orders[1].baseAssetAmountFilled.eq(ZERO));
+		assert(isVariant(userOrdersAccount.orders[2].status, 'init'));
+
+		const tradeHistory = clearingHouse.getTradeHistoryAccount();
+		assert(tradeHistory.head.eq(new BN(2)));
+		const tradeRecord: TradeRecord = tradeHistory.tradeRecords[1];
+		assert(tradeRecord.user.equals(userAccountPublicKey));
+		assert(tradeRecord.baseAssetAmount.eq(AMM_RESERVE_PRECISION));
+
+		const orderHistory = clearingHouse.getOrderHistoryAccount();
+		const firstFillRecord: OrderRecord = orderHistory.orderRecords[3];
+		const secondFillRecord: OrderRecord = orderHistory.orderRecords[4];
+		assert(isVariant(firstFillRecord.action, 'fill'));
+		assert(firstFillRecord.order.orderId.eq(orders[0].orderId));
+		assert(isVariant(secondFillRecord.action, 'fill'));
+		assert(secondFillRecord.order.orderId.eq(orders[2].orderId));
+
+		// the filler is paid once for both fills
+		const fillerReward = fillerUser
+			.getUserAccount()
+			.collateral.sub(fillerCollateralBefore);
+		assert(
+			fillerReward.eq(
+				firstFillRecord.fillerReward.add(secondFillRecord.fillerReward)
+			)
+		);
+		assert(fillerReward.gt(ZERO));
+
+		await clearingHouse.cancelOrder(orders[1].orderId);
+	});
+
+	it('Roll back an order that fails its margin check', async () => {
+		// the first order is far too large for the user's collateral
+		const baseAssetAmounts = [
+			AMM_RESERVE_PRECISION.mul(new BN(100)),
+			AMM_RESERVE_PRECISION,
+		];
+		for (const [index, baseAssetAmount] of baseAssetAmounts.entries()) {
+			const orderParams = getLimitOrderParams(
+				marketIndex,
+				PositionDirection.LONG,
+				baseAssetAmount,
+				MARK_PRICE_PRECISION.mul(new BN(2)),
+				false,
+				false,
+				false,
+				index + 1
+			);
+			await clearingHouse.placeOrder(orderParams);
+		}
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		await fillerUser.fetchAccounts();
+		const fillerCollateralBefore = fillerUser.getUserAccount().collateral;
+		const positionBefore =
+			clearingHouseUser.getUserPositionsAccount().positions[0];
+		const marketBefore = clearingHouse.getMarket(marketIndex);
+		const tradeHistoryHeadBefore = clearingHouse.getTradeHistoryAccount().head;
+		const orderHistoryHeadBefore = clearingHouse.getOrderHistoryAccount().head;
+		const orders = [1, 2].map((userOrderId) =>
+			clearingHouseUser.getOrderByUserOrderId(userOrderId)
+		);
+
+		await fillerClearingHouse.fillOrders(
+			orders.map((order) => ({
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				order,
+			}))
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		await fillerUser.fetchAccounts();
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(
+			position.baseAssetAmount
+				.sub(positionBefore.baseAssetAmount)
+				.eq(AMM_RESERVE_PRECISION)
+		);
+		assert(position.openOrders.eq(new BN(1)));
+
+		const market = clearingHouse.getMarket(marketIndex);
+		assert(
+			market.baseAssetAmount
+				.sub(marketBefore.baseAssetAmount)
+				.eq(AMM_RESERVE_PRECISION)
+		);
+
+		const failedOrder = clearingHouseUser.getOrderByUserOrderId(1);
+		assert(isVariant(failedOrder.status, 'open'));
+		assert(failedOrder.baseAssetAmountFilled.eq(ZERO));
+
+		const tradeHistory = clearingHouse.getTradeHistoryAccount();
+		assert(tradeHistory.head.eq(tradeHistoryHeadBefore.add(new BN(1))));
+
+		const orderHistory = clearingHouse.getOrderHistoryAccount();
+		assert(orderHistory.head.eq(orderHistoryHeadBefore.add(new BN(1))));
+		const fillRecord: OrderRecord =
+			orderHistory.orderRecords[orderHistoryHeadBefore.toNumber()];
+		assert(isVariant(fillRecord.action, 'fill'));
+		assert(fillRecord.order.orderId.eq(orders[1].orderId));
+
+		// the filler is only paid for the fill that went through
+		const fillerReward = fillerUser
+			.getUserAccount()
+			.collateral.sub(fillerCollateralBefore);
+		assert(fillerReward.eq(fillRecord.fillerReward));
+
+		await clearingHouse.cancelOrder(failedOrder.orderId);
+	});
+});
@@ -0,0 +1,189 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	ClearingHouseUser,
+	OrderRecord,
+	getLimitOrderParams,
+	isVariant,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('immediate or cancel', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		await clearingHouse.initializeUserAccountAndDepositCollateral(
+			usdcAmount,
+			userUSDCAccount.publicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+	});
+
+	it('Fail to place immediate or cancel order without filling', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(2)),
+			false
+		);
+		orderParams.immediateOrCancel = true;
+
+		try {
+			await clearingHouse.placeOrder(orderParams);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Cancel unfilled remainder of immediate or cancel order', async () => {
+		// user only has enough collateral to fill part of the order
+		const baseAssetAmount = AMM_RESERVE_PRECISION.mul(new BN(100));
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			baseAssetAmount,
+			MARK_PRICE_PRECISION.mul(new BN(2)),
+			false
+		);
+		orderParams.immediateOrCancel = true;
+		await clearingHouse.placeAndFillOrder(orderParams);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.status, 'init'));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.gt(ZERO));
+		assert(position.baseAssetAmount.lt(baseAssetAmount));
+		assert(position.openOrders.eq(ZERO));
+
+		const orderHistoryAccount = clearingHouse.getOrderHistoryAccount();
+		const fillRecord: OrderRecord = orderHistoryAccount.orderRecords[1];
+		assert(isVariant(fillRecord.action, 'fill'));
+
+		const cancelRecord: OrderRecord = orderHistoryAccount.orderRecords[2];
+		assert(isVariant(cancelRecord.action, 'cancel'));
+		assert(cancelRecord.order.immediateOrCancel);
+		assert(
+			cancelRecord.order.baseAssetAmountFilled.eq(position.baseAssetAmount)
+		);
+	});
+
+	it('Fail to fill part of fill or kill order', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION.mul(new BN(100)),
+			MARK_PRICE_PRECISION.mul(new BN(2)),
+			false
+		);
+		orderParams.fillOrKill = true;
+
+		try {
+			await clearingHouse.placeAndFillOrder(orderParams);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Fill entire fill or kill order', async () => {
+		await clearingHouseUser.fetchAccounts();
+		const positionBefore =
+			clearingHouseUser.getUserPositionsAccount().positions[0];
+
+		const baseAssetAmount = AMM_RESERVE_PRECISION;
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			baseAssetAmount,
+			MARK_PRICE_PRECISION.div(new BN(2)),
+			false
+		);
+		orderParams.fillOrKill = true;
+		await clearingHouse.placeAndFillOrder(orderParams);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(
+			position.baseAssetAmount.eq(
+				positionBefore.baseAssetAmount.sub(baseAssetAmount)
+			)
+		);
+		assert(position.openOrders.eq(ZERO));
+
+		const fillRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[4];
+		assert(isVariant(fillRecord.action, 'fill'));
+		assert(fillRecord.order.fillOrKill);
+		assert(fillRecord.baseAssetAmountFilled.eq(baseAssetAmount));
+	});
+});
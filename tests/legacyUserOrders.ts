@@ -0,0 +1,99 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import { Keypair, LAMPORTS_PER_SOL, PublicKey } from '@solana/web3.js';
+
+import {
+	BN,
+	ClearingHouse,
+	MARK_PRICE_PRECISION,
+	Wallet,
+	decodeUserOrdersAccount,
+	getUserAccountPublicKey,
+	getUserOrdersAccountPublicKey,
+	isVariant,
+} from '../sdk/src';
+
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+import authoritySecretKey from './fixtures/legacyUserOrders/authority.json';
+
+// The current program can't make legacy accounts, so the user and its legacy
+// user orders pda are loaded into the validator from tests/fixtures
+describe('legacy user orders', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	const authority = Keypair.fromSecretKey(Uint8Array.from(authoritySecretKey));
+	let clearingHouse: ClearingHouse;
+
+	let userAccountPublicKey: PublicKey;
+	let legacyUserOrdersAccountPublicKey: PublicKey;
+
+	before(async () => {
+		const signature = await connection.requestAirdrop(
+			authority.publicKey,
+			LAMPORTS_PER_SOL
+		);
+		await connection.confirmTransaction(signature);
+
+		clearingHouse = ClearingHouse.from(
+			connection,
+			new Wallet(authority),
+			chProgram.programId
+		);
+
+		userAccountPublicKey = await getUserAccountPublicKey(
+			chProgram.programId,
+			authority.publicKey
+		);
+		legacyUserOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			chProgram.programId,
+			userAccountPublicKey
+		);
+	});
+
+	it('Migrate user orders with legacy layout', async () => {
+		assert(
+			(await clearingHouse.getUserOrdersAccountPublicKey()).equals(
+				legacyUserOrdersAccountPublicKey
+			)
+		);
+
+		const [, userOrdersAccountPublicKey] =
+			await clearingHouse.migrateUserOrders();
+
+		const userAccount: any = await chProgram.account.user.fetch(
+			userAccountPublicKey
+		);
+		assert(userAccount.userOrders.equals(userOrdersAccountPublicKey));
+
+		const userOrdersAccountInfo = await connection.getAccountInfo(
+			userOrdersAccountPublicKey
+		);
+		const userOrdersAccount = decodeUserOrdersAccount(
+			chProgram,
+			userOrdersAccountInfo.data
+		);
+		assert(userOrdersAccount.user.equals(userAccountPublicKey));
+
+		const order = userOrdersAccount.orders[0];
+		assert(isVariant(order.status, 'open'));
+		assert(isVariant(order.orderType, 'limit'));
+		assert(isVariant(order.direction, 'long'));
+		assert(order.orderId.eq(new BN(1)));
+		assert(order.price.eq(MARK_PRICE_PRECISION));
+		assert(order.baseAssetAmount.eq(AMM_RESERVE_PRECISION));
+		assert(order.maxTs.eq(ZERO));
+		assert(isVariant(userOrdersAccount.orders[1].status, 'init'));
+
+		const legacyUserOrdersAccountInfo = await connection.getAccountInfo(
+			legacyUserOrdersAccountPublicKey
+		);
+		assert(legacyUserOrdersAccountInfo === null);
+	});
+});
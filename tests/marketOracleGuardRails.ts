@@ -0,0 +1,108 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import { Admin, BN, MARK_PRICE_PRECISION, OracleGuardRails } from '../sdk/src';
+
+import { mockOracle, mockUSDCMint } from './testHelpers';
+
+describe('market oracle guard rails', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+
+	let usdcMint;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const solMarketIndex = new BN(0);
+	const btcMarketIndex = new BN(1);
+	let solUsd;
+	let btcUsd;
+
+	const oracleGuardRails: OracleGuardRails = {
+		priceDivergence: {
+			markOracleDivergenceNumerator: new BN(1),
+			markOracleDivergenceDenominator: new BN(10),
+		},
+		validity: {
+			slotsBeforeStale: new BN(1000),
+			confidenceIntervalMaxSize: new BN(4),
+			tooVolatileRatio: new BN(5),
+		},
+		useForLiquidations: true,
+	};
+
+	const isOracleValid = async (oracle, marketIndex) => {
+		await clearingHouse.updateOracleStatus(oracle, marketIndex);
+		await clearingHouse.fetchAccounts();
+		const market = clearingHouse.getMarket(marketIndex);
+		return market.consecutiveInvalidOracleReadings.eq(new BN(0));
+	};
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribe();
+		await clearingHouse.updateOracleGuardRails(oracleGuardRails);
+
+		solUsd = await mockOracle(1);
+		btcUsd = await mockOracle(1);
+		const periodicity = new BN(60 * 60); // 1 HOUR
+		await clearingHouse.initializeMarket(
+			solMarketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+		await clearingHouse.initializeMarket(
+			btcMarketIndex,
+			btcUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+	});
+
+	it('Override only changes validity for its market', async () => {
+		assert(await isOracleValid(solUsd, solMarketIndex));
+		assert(await isOracleValid(btcUsd, btcMarketIndex));
+
+		// every reading is stale for sol
+		await clearingHouse.updateMarketOracleGuardRails(solMarketIndex, {
+			...oracleGuardRails,
+			validity: { ...oracleGuardRails.validity, slotsBeforeStale: new BN(0) },
+		});
+
+		assert(!(await isOracleValid(solUsd, solMarketIndex)));
+		assert(await isOracleValid(btcUsd, btcMarketIndex));
+	});
+
+	it('Clearing override falls back to state guard rails', async () => {
+		await clearingHouse.updateMarketOracleGuardRails(solMarketIndex, null);
+
+		assert(await isOracleValid(solUsd, solMarketIndex));
+	});
+});
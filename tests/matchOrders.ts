@@ -0,0 +1,378 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import { Keypair, PublicKey } from '@solana/web3.js';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	ClearingHouse,
+	PositionDirection,
+	getUserOrdersAccountPublicKey,
+	ClearingHouseUser,
+	OrderRecord,
+	Wallet,
+	TradeRecord,
+	calculateMarkPrice,
+	getLimitOrderParams,
+	isVariant,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('match orders', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let userAccountPublicKey: PublicKey;
+	let userOrdersAccountPublicKey: PublicKey;
+
+	let makerAccountPublicKey: PublicKey;
+	let makerOrdersAccountPublicKey: PublicKey;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const makerKeyPair = new Keypair();
+	let makerUSDCAccount: Keypair;
+	let makerClearingHouse: ClearingHouse;
+	let makerUser: ClearingHouseUser;
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId,
+			{
+				commitment: 'confirmed',
+			}
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+
+		provider.connection.requestAirdrop(makerKeyPair.publicKey, 10 ** 9);
+		makerUSDCAccount = await mockUserUSDCAccount(
+			usdcMint,
+			usdcAmount,
+			provider,
+			makerKeyPair.publicKey
+		);
+		makerClearingHouse = ClearingHouse.from(
+			connection,
+			new Wallet(makerKeyPair),
+			chProgram.programId,
+			{
+				commitment: 'confirmed',
+			}
+		);
+		await makerClearingHouse.subscribe();
+
+		[, makerAccountPublicKey] =
+			await makerClearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				makerUSDCAccount.publicKey
+			);
+		makerOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			makerClearingHouse.program.programId,
+			makerAccountPublicKey
+		);
+
+		makerUser = ClearingHouseUser.from(
+			makerClearingHouse,
+			makerKeyPair.publicKey
+		);
+		await makerUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+		await makerClearingHouse.unsubscribe();
+		await makerUser.unsubscribe();
+	});
+
+	it('Match crossing orders and fill remainder against amm', async () => {
+		const makerOrderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(101)).div(new BN(100)),
+			false
+		);
+		await makerClearingHouse.placeOrder(makerOrderParams);
+
+		const takerOrderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION.mul(new BN(2)),
+			MARK_PRICE_PRECISION.mul(new BN(105)).div(new BN(100)),
+			false
+		);
+		await clearingHouse.placeOrder(takerOrderParams);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		await makerUser.fetchAccounts();
+		const baseAssetReserveBefore =
+			clearingHouse.getMarket(marketIndex).amm.baseAssetReserve;
+		const takerOrder = clearingHouseUser.getUserOrdersAccount().orders[0];
+		const makerOrder = makerUser.getUserOrdersAccount().orders[0];
+
+		await makerClearingHouse.matchOrders(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			takerOrder,
+			makerAccountPublicKey,
+			makerOrdersAccountPublicKey,
+			makerOrder
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		await makerUser.fetchAccounts();
+
+		const takerPosition =
+			clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(
+			takerPosition.baseAssetAmount.eq(AMM_RESERVE_PRECISION.mul(new BN(2)))
+		);
+		assert(takerPosition.openOrders.eq(ZERO));
+
+		const makerPosition = makerUser.getUserPositionsAccount().positions[0];
+		assert(makerPosition.baseAssetAmount.eq(AMM_RESERVE_PRECISION.neg()));
+		assert(makerPosition.openOrders.eq(ZERO));
+
+		// only the remainder traded against the amm
+		const baseAssetReserveAfter =
+			clearingHouse.getMarket(marketIndex).amm.baseAssetReserve;
+		assert(
+			baseAssetReserveBefore
+				.sub(baseAssetReserveAfter)
+				.eq(AMM_RESERVE_PRECISION)
+		);
+
+		const tradeHistory = clearingHouse.getTradeHistoryAccount();
+		const takerTradeRecord: TradeRecord = tradeHistory.tradeRecords[0];
+		const makerTradeRecord: TradeRecord = tradeHistory.tradeRecords[1];
+		const expectedQuoteAssetAmount = new BN(1010000);
+		assert(takerTradeRecord.user.equals(userAccountPublicKey));
+		assert(isVariant(takerTradeRecord.direction, 'long'));
+		assert(takerTradeRecord.baseAssetAmount.eq(AMM_RESERVE_PRECISION));
+		assert(takerTradeRecord.quoteAssetAmount.eq(expectedQuoteAssetAmount));
+		assert(makerTradeRecord.user.equals(makerAccountPublicKey));
+		assert(isVariant(makerTradeRecord.direction, 'short'));
+		assert(makerTradeRecord.quoteAssetAmount.eq(expectedQuoteAssetAmount));
+		// only the taker pays, the same fee as a fill against the amm
+		assert(takerTradeRecord.fee.eq(new BN(1010)));
+		assert(makerTradeRecord.fee.eq(ZERO));
+
+		const ammTradeRecord: TradeRecord = tradeHistory.tradeRecords[2];
+		assert(ammTradeRecord.user.equals(userAccountPublicKey));
+		assert(ammTradeRecord.baseAssetAmount.eq(AMM_RESERVE_PRECISION));
+
+		const orderHistory = clearingHouse.getOrderHistoryAccount();
+		const takerOrderRecord: OrderRecord = orderHistory.orderRecords[3];
+		const makerOrderRecord: OrderRecord = orderHistory.orderRecords[4];
+		assert(isVariant(takerOrderRecord.action, 'fill'));
+		assert(takerOrderRecord.order.orderId.eq(takerOrder.orderId));
+		assert(isVariant(makerOrderRecord.action, 'fill'));
+		assert(makerOrderRecord.order.orderId.eq(makerOrder.orderId));
+	});
+
+	it('Fail to match orders that dont cross', async () => {
+		const makerOrderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(2)),
+			false
+		);
+		await makerClearingHouse.placeOrder(makerOrderParams);
+
+		const takerOrderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.div(new BN(2)),
+			false
+		);
+		await clearingHouse.placeOrder(takerOrderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		await makerUser.fetchAccounts();
+		const takerOrder = clearingHouseUser.getUserOrdersAccount().orders[0];
+		const makerOrder = makerUser.getUserOrdersAccount().orders[0];
+
+		try {
+			await makerClearingHouse.matchOrders(
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				takerOrder,
+				makerAccountPublicKey,
+				makerOrdersAccountPublicKey,
+				makerOrder
+			);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Fail to match a resting order as the taker', async () => {
+		await clearingHouse.cancelAllOrders();
+		await makerClearingHouse.cancelAllOrders();
+
+		await clearingHouse.fetchAccounts();
+		const markPrice = calculateMarkPrice(clearingHouse.getMarket(marketIndex));
+		const makerOrderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			AMM_RESERVE_PRECISION,
+			markPrice.mul(new BN(101)).div(new BN(100)),
+			false
+		);
+		await makerClearingHouse.placeOrder(makerOrderParams);
+
+		const takerOrderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			markPrice.mul(new BN(105)).div(new BN(100)),
+			false
+		);
+		await clearingHouse.placeOrder(takerOrderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		await makerUser.fetchAccounts();
+		const takerOrder = clearingHouseUser.getUserOrdersAccount().orders[0];
+		const makerOrder = makerUser.getUserOrdersAccount().orders[0];
+
+		// the filler tries to trade at the later order's price
+		try {
+			await makerClearingHouse.matchOrders(
+				makerAccountPublicKey,
+				makerOrdersAccountPublicKey,
+				makerOrder,
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				takerOrder
+			);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Fill remainder of order from resized user orders account', async () => {
+		await clearingHouse.cancelAllOrders();
+		await makerClearingHouse.cancelAllOrders();
+
+		// the taker's orders no longer fit the default sized account layout
+		[, userOrdersAccountPublicKey] = await clearingHouse.resizeUserOrders(4);
+		await clearingHouseUser.unsubscribe();
+		await clearingHouseUser.subscribe();
+
+		await clearingHouse.fetchAccounts();
+		const markPrice = calculateMarkPrice(clearingHouse.getMarket(marketIndex));
+		const makerOrderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			AMM_RESERVE_PRECISION,
+			markPrice.mul(new BN(101)).div(new BN(100)),
+			false
+		);
+		await makerClearingHouse.placeOrder(makerOrderParams);
+
+		const takerOrderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION.mul(new BN(2)),
+			markPrice.mul(new BN(105)).div(new BN(100)),
+			false
+		);
+		await clearingHouse.placeOrder(takerOrderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		await makerUser.fetchAccounts();
+		const takerPositionBefore =
+			clearingHouseUser.getUserPositionsAccount().positions[0];
+		const takerOrder = clearingHouseUser.getUserOrdersAccount().orders[0];
+		const makerOrder = makerUser.getUserOrdersAccount().orders[0];
+
+		await makerClearingHouse.matchOrders(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			takerOrder,
+			makerAccountPublicKey,
+			makerOrdersAccountPublicKey,
+			makerOrder
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		const takerPosition =
+			clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(
+			takerPosition.baseAssetAmount
+				.sub(takerPositionBefore.baseAssetAmount)
+				.eq(AMM_RESERVE_PRECISION.mul(new BN(2)))
+		);
+		assert(takerPosition.openOrders.eq(ZERO));
+
+		const orderAfter = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(orderAfter.status, 'init'));
+	});
+});
@@ -0,0 +1,156 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	ClearingHouseUser,
+	OrderRecord,
+	getLimitOrderParams,
+	getModifyOrderParams,
+	isVariant,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+import { AMM_RESERVE_PRECISION } from '../sdk';
+
+describe('modify order', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		await clearingHouse.initializeUserAccountAndDepositCollateral(
+			usdcAmount,
+			userUSDCAccount.publicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+	});
+
+	it('Modify price and size', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.div(new BN(2)),
+			false
+		);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+
+		const newPrice = MARK_PRICE_PRECISION.mul(new BN(6)).div(new BN(10));
+		const newBaseAssetAmount = AMM_RESERVE_PRECISION.mul(new BN(2));
+		await clearingHouse.modifyOrder(
+			order,
+			getModifyOrderParams(order, {
+				price: newPrice,
+				baseAssetAmount: newBaseAssetAmount,
+			})
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const modifiedOrder = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(modifiedOrder.orderId.eq(order.orderId));
+		assert(modifiedOrder.price.eq(newPrice));
+		assert(modifiedOrder.baseAssetAmount.eq(newBaseAssetAmount));
+		assert(modifiedOrder.reduceOnly === order.reduceOnly);
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.openOrders.eq(new BN(1)));
+
+		const orderRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[1];
+		assert(isVariant(orderRecord.action, 'modify'));
+		assert(orderRecord.order.orderId.eq(order.orderId));
+		assert(orderRecord.order.price.eq(newPrice));
+	});
+
+	it('Fail to modify order to an invalid price', async () => {
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+
+		try {
+			await clearingHouse.modifyOrder(
+				order,
+				getModifyOrderParams(order, { price: new BN(0) })
+			);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Fail to modify cancelled order', async () => {
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		await clearingHouse.cancelOrder(order.orderId);
+
+		try {
+			await clearingHouse.modifyOrder(
+				order,
+				getModifyOrderParams(order, { reduceOnly: true })
+			);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+});
@@ -0,0 +1,187 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	ClearingHouseUser,
+	OrderRecord,
+	OrderTriggerCondition,
+	QUOTE_PRECISION,
+	getTriggerLimitOrderParams,
+	getTriggerMarketOrderParams,
+	getUserOrdersAccountPublicKey,
+	isVariant,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('one cancels other orders', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	let userAccountPublicKey;
+	let userOrdersAccountPublicKey;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+	});
+
+	it('Fail to place group with one order', async () => {
+		const stopLoss = getTriggerMarketOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(9)).div(new BN(10)),
+			OrderTriggerCondition.BELOW,
+			true
+		);
+
+		try {
+			await clearingHouse.placeOneCancelsOtherOrders([stopLoss]);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Place take profit and stop loss for long', async () => {
+		await clearingHouse.openPosition(
+			PositionDirection.LONG,
+			QUOTE_PRECISION.mul(new BN(5)),
+			marketIndex
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		const baseAssetAmount =
+			clearingHouseUser.getUserPositionsAccount().positions[0].baseAssetAmount;
+
+		// take profit is already triggered so that it can be filled right away
+		const takeProfit = getTriggerLimitOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			baseAssetAmount,
+			MARK_PRICE_PRECISION.mul(new BN(98)).div(new BN(100)),
+			MARK_PRICE_PRECISION.mul(new BN(99)).div(new BN(100)),
+			OrderTriggerCondition.ABOVE,
+			true
+		);
+		const stopLoss = getTriggerMarketOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			baseAssetAmount,
+			MARK_PRICE_PRECISION.mul(new BN(9)).div(new BN(10)),
+			OrderTriggerCondition.BELOW,
+			true
+		);
+		await clearingHouse.placeOneCancelsOtherOrders([takeProfit, stopLoss]);
+
+		await clearingHouseUser.fetchAccounts();
+		const [takeProfitOrder, stopLossOrder] =
+			clearingHouseUser.getUserOrdersAccount().orders;
+		assert(isVariant(takeProfitOrder.status, 'open'));
+		assert(isVariant(stopLossOrder.status, 'open'));
+		assert(takeProfitOrder.groupId.eq(takeProfitOrder.orderId));
+		assert(stopLossOrder.groupId.eq(takeProfitOrder.orderId));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.openOrders.eq(new BN(2)));
+	});
+
+	it('Filling take profit cancels stop loss', async () => {
+		const [takeProfitOrder, stopLossOrder] =
+			clearingHouseUser.getUserOrdersAccount().orders;
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			takeProfitOrder
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const orders = clearingHouseUser.getUserOrdersAccount().orders;
+		assert(!orders.some((order) => isVariant(order.status, 'open')));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(ZERO));
+		assert(position.openOrders.eq(ZERO));
+
+		const orderHistoryAccount = clearingHouse.getOrderHistoryAccount();
+		const fillRecord: OrderRecord = orderHistoryAccount.orderRecords[2];
+		assert(isVariant(fillRecord.action, 'fill'));
+		assert(fillRecord.order.orderId.eq(takeProfitOrder.orderId));
+
+		const cancelRecord: OrderRecord = orderHistoryAccount.orderRecords[3];
+		assert(isVariant(cancelRecord.action, 'cancel'));
+		assert(cancelRecord.order.orderId.eq(stopLossOrder.orderId));
+		assert(cancelRecord.tradeRecordId.eq(fillRecord.tradeRecordId));
+	});
+});
@@ -0,0 +1,225 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	OracleBasketType,
+	OracleSource,
+	PositionDirection,
+	QUOTE_PRECISION,
+} from '../sdk/src';
+
+import {
+	mockOracle,
+	mockUSDCMint,
+	mockUserUSDCAccount,
+	setFeedPrice,
+} from './testHelpers';
+
+describe('oracle basket', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let oracleA;
+	let oracleB;
+	let oracleBasket;
+
+	const ratioMarketIndex = new BN(1);
+	let numeratorOracle;
+	let oracleRatio;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribe();
+
+		oracleA = await mockOracle(10);
+		oracleB = await mockOracle(30);
+
+		await clearingHouse.initializeUserAccountAndDepositCollateral(
+			usdcAmount,
+			userUSDCAccount.publicKey
+		);
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+	});
+
+	it('Fail to initialize basket with duplicate components', async () => {
+		try {
+			await clearingHouse.initializeOracleBasket([
+				{ oracle: oracleA, weight: new BN(500000) },
+				{ oracle: oracleA, weight: new BN(500000) },
+			]);
+			assert(false);
+		} catch (e) {
+			assert(e.msg === 'Invalid oracle basket');
+		}
+	});
+
+	it('Initialize and update basket', async () => {
+		oracleBasket = await clearingHouse.initializeOracleBasket([
+			{ oracle: oracleA, weight: new BN(500000) },
+			{ oracle: oracleB, weight: new BN(500000) },
+		]);
+
+		await clearingHouse.updateOracleBasket(oracleBasket);
+
+		const oracleBasketAccount: any =
+			await clearingHouse.program.account.oracleBasket.fetch(oracleBasket);
+		assert(oracleBasketAccount.numComponents.eq(new BN(2)));
+		assert(oracleBasketAccount.price.eq(new BN(20).mul(MARK_PRICE_PRECISION)));
+	});
+
+	it('Initialize market with basket oracle', async () => {
+		const periodicity = new BN(60 * 60); // 1 HOUR
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			oracleBasket,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity,
+			new BN(20000),
+			OracleSource.BASKET
+		);
+
+		const market = clearingHouse.getMarket(marketIndex);
+		assert(market.amm.lastOraclePrice.eq(new BN(20).mul(MARK_PRICE_PRECISION)));
+		assert(
+			JSON.stringify(market.amm.oracleSource) ===
+				JSON.stringify(OracleSource.BASKET)
+		);
+	});
+
+	it('Open position against basket oracle', async () => {
+		await setFeedPrice(anchor.workspace.Pyth, 12, oracleA);
+		await clearingHouse.updateOracleBasket(oracleBasket);
+
+		const oracleBasketAccount: any =
+			await clearingHouse.program.account.oracleBasket.fetch(oracleBasket);
+		assert(oracleBasketAccount.price.eq(new BN(21).mul(MARK_PRICE_PRECISION)));
+
+		await clearingHouse.openPosition(
+			PositionDirection.LONG,
+			QUOTE_PRECISION,
+			marketIndex
+		);
+
+		await clearingHouse.fetchAccounts();
+		const market = clearingHouse.getMarket(marketIndex);
+		assert(market.baseAssetAmountLong.gt(new BN(0)));
+	});
+
+	it('Initialize and update ratio', async () => {
+		numeratorOracle = await mockOracle(3000);
+		const denominatorOracle = await mockOracle(1500);
+
+		oracleRatio = await clearingHouse.initializeOracleRatio(
+			numeratorOracle,
+			denominatorOracle
+		);
+
+		await clearingHouse.updateOracleBasket(oracleRatio);
+
+		const oracleRatioAccount: any =
+			await clearingHouse.program.account.oracleBasket.fetch(oracleRatio);
+		assert(oracleRatioAccount.numComponents.eq(new BN(2)));
+		assert(
+			JSON.stringify(oracleRatioAccount.basketType) ===
+				JSON.stringify(OracleBasketType.RATIO)
+		);
+		assert(oracleRatioAccount.price.eq(new BN(2).mul(MARK_PRICE_PRECISION)));
+	});
+
+	it('Initialize market with ratio oracle', async () => {
+		const periodicity = new BN(60 * 60); // 1 HOUR
+		await clearingHouse.initializeMarket(
+			ratioMarketIndex,
+			oracleRatio,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity,
+			new BN(2000),
+			OracleSource.RATIO
+		);
+
+		const market = clearingHouse.getMarket(ratioMarketIndex);
+		assert(market.amm.lastOraclePrice.eq(new BN(2).mul(MARK_PRICE_PRECISION)));
+		assert(
+			JSON.stringify(market.amm.oracleSource) ===
+				JSON.stringify(OracleSource.RATIO)
+		);
+	});
+
+	it('Open position against ratio oracle', async () => {
+		await setFeedPrice(anchor.workspace.Pyth, 3300, numeratorOracle);
+		await clearingHouse.updateOracleBasket(oracleRatio);
+
+		const oracleRatioAccount: any =
+			await clearingHouse.program.account.oracleBasket.fetch(oracleRatio);
+		assert(
+			oracleRatioAccount.price.eq(
+				new BN(22).mul(MARK_PRICE_PRECISION).div(new BN(10))
+			)
+		);
+
+		await clearingHouse.openPosition(
+			PositionDirection.LONG,
+			QUOTE_PRECISION,
+			ratioMarketIndex
+		);
+
+		await clearingHouse.fetchAccounts();
+		const market = clearingHouse.getMarket(ratioMarketIndex);
+		assert(market.baseAssetAmountLong.gt(new BN(0)));
+	});
+
+	it('Fail to update ratio with a zero price component', async () => {
+		await setFeedPrice(anchor.workspace.Pyth, 0, numeratorOracle);
+		try {
+			await clearingHouse.updateOracleBasket(oracleRatio);
+			assert(false);
+		} catch (e) {
+			assert(e.msg === 'InvalidOracle');
+		}
+
+		const oracleRatioAccount: any =
+			await clearingHouse.program.account.oracleBasket.fetch(oracleRatio);
+		assert(
+			oracleRatioAccount.price.eq(
+				new BN(22).mul(MARK_PRICE_PRECISION).div(new BN(10))
+			)
+		);
+	});
+});
@@ -0,0 +1,215 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+import { Transaction } from '@solana/web3.js';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	OracleGuardRails,
+	OracleRecordType,
+	PositionDirection,
+	QUOTE_PRECISION,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+
+describe('oracle circuit breaker', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	const oracleGuardRails: OracleGuardRails = {
+		priceDivergence: {
+			markOracleDivergenceNumerator: new BN(1),
+			markOracleDivergenceDenominator: new BN(10),
+		},
+		validity: {
+			slotsBeforeStale: new BN(1000),
+			confidenceIntervalMaxSize: new BN(4),
+			tooVolatileRatio: new BN(5),
+		},
+		useForLiquidations: true,
+	};
+
+	const waitForNextSlot = async () => {
+		const slot = await connection.getSlot();
+		while ((await connection.getSlot()) <= slot) {
+			await new Promise((resolve) => setTimeout(resolve, 100));
+		}
+	};
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribe();
+
+		solUsd = await mockOracle(1);
+		const periodicity = new BN(60 * 60); // 1 HOUR
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		await clearingHouse.initializeUserAccountAndDepositCollateral(
+			usdcAmount,
+			userUSDCAccount.publicKey
+		);
+
+		await clearingHouse.updateOracleCircuitBreakerThreshold(new BN(2));
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+	});
+
+	it('Trip circuit breaker after consecutive invalid readings', async () => {
+		// every reading is stale
+		await clearingHouse.updateOracleGuardRails({
+			...oracleGuardRails,
+			validity: { ...oracleGuardRails.validity, slotsBeforeStale: new BN(0) },
+		});
+
+		await clearingHouse.updateOracleStatus(solUsd, marketIndex);
+		await clearingHouse.fetchAccounts();
+		let market = clearingHouse.getMarket(marketIndex);
+		assert(market.consecutiveInvalidOracleReadings.eq(new BN(1)));
+		assert(!market.reduceOnly);
+
+		// the second reading in the same slot doesn't count
+		await waitForNextSlot();
+		const updateOracleStatusIx = await clearingHouse.getUpdateOracleStatusIx(
+			solUsd,
+			marketIndex
+		);
+		await provider.send(
+			new Transaction().add(updateOracleStatusIx, updateOracleStatusIx)
+		);
+		await clearingHouse.fetchAccounts();
+		market = clearingHouse.getMarket(marketIndex);
+		assert(market.consecutiveInvalidOracleReadings.eq(new BN(2)));
+		assert(market.reduceOnly);
+
+		const state = clearingHouse.getStateAccount();
+		const oracleHistory: any =
+			await clearingHouse.program.account.oracleHistory.fetch(
+				state.oracleHistory
+			);
+		assert(oracleHistory.head.eq(new BN(2)));
+		const oracleRecord = oracleHistory.oracleRecords[1];
+		assert(oracleRecord.oracle.equals(solUsd));
+		assert(
+			JSON.stringify(oracleRecord.recordType) ===
+				JSON.stringify(OracleRecordType.INVALID)
+		);
+		assert(oracleRecord.reduceOnly);
+	});
+
+	it('Block risk increasing trades while reduce only', async () => {
+		// the feed is healthy again but the breaker stays tripped until the next reading
+		await clearingHouse.updateOracleGuardRails(oracleGuardRails);
+
+		try {
+			await clearingHouse.openPosition(
+				PositionDirection.LONG,
+				QUOTE_PRECISION,
+				marketIndex
+			);
+			assert(false);
+		} catch (e) {
+			assert(e.msg === 'Market is reduce only');
+		}
+	});
+
+	it('Reset circuit breaker once the oracle recovers', async () => {
+		await clearingHouse.updateOracleStatus(solUsd, marketIndex);
+		await clearingHouse.fetchAccounts();
+		const market = clearingHouse.getMarket(marketIndex);
+		assert(market.consecutiveInvalidOracleReadings.eq(new BN(0)));
+		assert(!market.reduceOnly);
+
+		const state = clearingHouse.getStateAccount();
+		const oracleHistory: any =
+			await clearingHouse.program.account.oracleHistory.fetch(
+				state.oracleHistory
+			);
+		assert(oracleHistory.head.eq(new BN(3)));
+		assert(
+			JSON.stringify(oracleHistory.oracleRecords[2].recordType) ===
+				JSON.stringify(OracleRecordType.RECOVERED)
+		);
+
+		await clearingHouse.openPosition(
+			PositionDirection.LONG,
+			QUOTE_PRECISION,
+			marketIndex
+		);
+		await clearingHouse.fetchAccounts();
+		assert(
+			clearingHouse.getMarket(marketIndex).baseAssetAmountLong.gt(new BN(0))
+		);
+	});
+
+	it('Trip circuit breaker from trades that read the oracle', async () => {
+		await clearingHouse.updateOracleGuardRails({
+			...oracleGuardRails,
+			validity: { ...oracleGuardRails.validity, slotsBeforeStale: new BN(0) },
+		});
+
+		await waitForNextSlot();
+		await clearingHouse.openPosition(
+			PositionDirection.LONG,
+			QUOTE_PRECISION,
+			marketIndex
+		);
+		await clearingHouse.fetchAccounts();
+		let market = clearingHouse.getMarket(marketIndex);
+		assert(market.consecutiveInvalidOracleReadings.eq(new BN(1)));
+		assert(!market.reduceOnly);
+
+		await waitForNextSlot();
+		await clearingHouse.closePosition(marketIndex);
+		await clearingHouse.fetchAccounts();
+		market = clearingHouse.getMarket(marketIndex);
+		assert(market.consecutiveInvalidOracleReadings.eq(new BN(2)));
+		assert(market.reduceOnly);
+
+		// only update_oracle_status lifts reduce only, so the recovery is recorded
+		await clearingHouse.updateOracleGuardRails(oracleGuardRails);
+		await clearingHouse.updateOracleStatus(solUsd, marketIndex);
+		await clearingHouse.fetchAccounts();
+		assert(!clearingHouse.getMarket(marketIndex).reduceOnly);
+	});
+});
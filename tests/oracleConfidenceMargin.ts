@@ -0,0 +1,128 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	QUOTE_PRECISION,
+} from '../sdk/src';
+
+import {
+	mockOracle,
+	mockUSDCMint,
+	mockUserUSDCAccount,
+	setFeedConfidence,
+} from './testHelpers';
+
+describe('oracle confidence margin', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribe();
+
+		solUsd = await mockOracle(1);
+		const periodicity = new BN(60 * 60); // 1 HOUR
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		await clearingHouse.initializeUserAccountAndDepositCollateral(
+			usdcAmount,
+			userUSDCAccount.publicKey
+		);
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+	});
+
+	it('Value position with amm by default', async () => {
+		await clearingHouse.openPosition(
+			PositionDirection.LONG,
+			QUOTE_PRECISION.mul(new BN(40)),
+			marketIndex
+		);
+
+		// the oracle is unsure, but the amm still values the long at ~40
+		await setFeedConfidence(anchor.workspace.Pyth, 0.1, solUsd);
+
+		await clearingHouse.openPosition(
+			PositionDirection.LONG,
+			QUOTE_PRECISION,
+			marketIndex
+		);
+	});
+
+	it('Value position at the low end of the confidence interval', async () => {
+		await clearingHouse.updateExtendedOracleGuardRails({
+			requireTradingStatus: true,
+			minPublishers: 0,
+			useConfidenceIntervalForMargin: true,
+		});
+
+		// the long is valued at 0.9, which takes the account under initial margin
+		try {
+			await clearingHouse.openPosition(
+				PositionDirection.LONG,
+				QUOTE_PRECISION,
+				marketIndex
+			);
+			assert(false);
+		} catch (e) {
+			assert(e.msg === 'Insufficient collateral');
+		}
+	});
+
+	it('Value position with amm once the oracle is sure again', async () => {
+		await setFeedConfidence(anchor.workspace.Pyth, 0.0001, solUsd);
+
+		await clearingHouse.openPosition(
+			PositionDirection.LONG,
+			QUOTE_PRECISION,
+			marketIndex
+		);
+
+		await clearingHouse.fetchAccounts();
+		const market = clearingHouse.getMarket(marketIndex);
+		assert(market.baseAssetAmountLong.gt(new BN(0)));
+	});
+});
@@ -0,0 +1,157 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	ClearingHouseUser,
+	getOracleOffsetLimitOrderParams,
+	getUserOrdersAccountPublicKey,
+	isVariant,
+} from '../sdk/src';
+
+import {
+	FeedStatus,
+	mockOracle,
+	mockUSDCMint,
+	mockUserUSDCAccount,
+	setFeedPrice,
+	setFeedStatus,
+} from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('oracle offset orders', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	let userAccountPublicKey;
+	let userOrdersAccountPublicKey;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+	});
+
+	it('Place order one cent below oracle', async () => {
+		const orderParams = getOracleOffsetLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.div(new BN(100)).neg(),
+			false
+		);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.status, 'open'));
+		assert(order.price.eq(ZERO));
+		assert(order.oraclePriceOffset.eq(orderParams.oraclePriceOffset));
+	});
+
+	it('Fail to fill when oracle is invalid', async () => {
+		await setFeedPrice(anchor.workspace.Pyth, 1.03, solUsd);
+		await setFeedStatus(anchor.workspace.Pyth, FeedStatus.HALTED, solUsd);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		try {
+			await clearingHouse.fillOrder(
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				order
+			);
+			assert(false);
+		} catch (e) {
+			assert(e.msg === 'CouldNotFillOrder');
+		}
+
+		await clearingHouseUser.fetchAccounts();
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(ZERO));
+		assert(position.openOrders.eq(new BN(1)));
+	});
+
+	it('Fill once oracle moves above mark', async () => {
+		await setFeedStatus(anchor.workspace.Pyth, FeedStatus.TRADING, solUsd);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(AMM_RESERVE_PRECISION));
+		assert(position.openOrders.eq(ZERO));
+	});
+});
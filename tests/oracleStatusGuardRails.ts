@@ -0,0 +1,117 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+import { Keypair } from '@solana/web3.js';
+
+import { Admin, BN, MARK_PRICE_PRECISION } from '../sdk/src';
+
+import {
+	FeedStatus,
+	mockOracle,
+	mockUSDCMint,
+	setFeedComponent,
+	setFeedStatus,
+} from './testHelpers';
+
+describe('oracle status guard rails', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+	const pythProgram = anchor.workspace.Pyth as Program;
+
+	let clearingHouse: Admin;
+
+	let usdcMint;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	const isOracleValid = async () => {
+		await clearingHouse.updateOracleStatus(solUsd, marketIndex);
+		await clearingHouse.fetchAccounts();
+		const market = clearingHouse.getMarket(marketIndex);
+		return market.consecutiveInvalidOracleReadings.eq(new BN(0));
+	};
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribe();
+
+		solUsd = await mockOracle(1);
+		const periodicity = new BN(60 * 60); // 1 HOUR
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+	});
+
+	it('Reject halted feed', async () => {
+		assert(await isOracleValid());
+
+		await setFeedStatus(pythProgram, FeedStatus.HALTED, solUsd);
+		assert(!(await isOracleValid()));
+
+		await setFeedStatus(pythProgram, FeedStatus.TRADING, solUsd);
+		assert(await isOracleValid());
+	});
+
+	it('Accept halted feed when trading status not required', async () => {
+		await clearingHouse.updateExtendedOracleGuardRails({
+			requireTradingStatus: false,
+			minPublishers: 0,
+			useConfidenceIntervalForMargin: false,
+		});
+
+		await setFeedStatus(pythProgram, FeedStatus.HALTED, solUsd);
+		assert(await isOracleValid());
+
+		await setFeedStatus(pythProgram, FeedStatus.TRADING, solUsd);
+	});
+
+	it('Reject feed with too few publishers', async () => {
+		await clearingHouse.updateExtendedOracleGuardRails({
+			requireTradingStatus: true,
+			minPublishers: 1,
+			useConfidenceIntervalForMargin: false,
+		});
+
+		// the mock feed starts without any publishers
+		assert(!(await isOracleValid()));
+
+		await setFeedComponent(
+			pythProgram,
+			0,
+			Keypair.generate().publicKey,
+			1,
+			0.01,
+			FeedStatus.TRADING,
+			solUsd
+		);
+		assert(await isOracleValid());
+	});
+});
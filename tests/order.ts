@@ -306,43 +306,33 @@ describe('orders', () => {
 		assert(orderRecord.authority.equals(clearingHouseUser.authority));
 	});
 
-	it('Fail to fill reduce only order', async () => {
+	it('Cancel reduce only order that cant reduce position', async () => {
 		const userOrdersAccount = clearingHouseUser.getUserOrdersAccount();
 		const order = userOrdersAccount.orders[0];
 
-		try {
-			await fillerClearingHouse.fillOrder(
-				userAccountPublicKey,
-				userOrdersAccountPublicKey,
-				order
-			);
-		} catch (e) {
-			return;
-		}
-
-		assert(false);
-	});
-
-	it('Cancel order', async () => {
-		const orderIndex = new BN(0);
-		const orderId = new BN(1);
-		await clearingHouse.cancelOrder(orderId);
+		await fillerClearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
 
 		await clearingHouse.fetchAccounts();
 		await clearingHouseUser.fetchAccounts();
-		const order =
+		const orderIndex = new BN(0);
+		const cancelledOrder =
 			clearingHouseUser.getUserOrdersAccount().orders[orderIndex.toNumber()];
 
-		assert(order.baseAssetAmount.eq(new BN(0)));
-		assert(order.price.eq(new BN(0)));
-		assert(order.marketIndex.eq(new BN(0)));
-		assert(enumsAreEqual(order.direction, PositionDirection.LONG));
-		assert(enumsAreEqual(order.status, OrderStatus.INIT));
+		assert(cancelledOrder.baseAssetAmount.eq(new BN(0)));
+		assert(cancelledOrder.price.eq(new BN(0)));
+		assert(cancelledOrder.marketIndex.eq(new BN(0)));
+		assert(enumsAreEqual(cancelledOrder.direction, PositionDirection.LONG));
+		assert(enumsAreEqual(cancelledOrder.status, OrderStatus.INIT));
 
 		const userPositionsAccount = clearingHouseUser.getUserPositionsAccount();
 		const position = userPositionsAccount.positions[0];
 		const expectedOpenOrders = new BN(0);
 		assert(position.openOrders.eq(expectedOpenOrders));
+		assert(position.baseAssetAmount.eq(ZERO));
 
 		const orderHistoryAccount = clearingHouse.getOrderHistoryAccount();
 		const orderRecord: OrderRecord = orderHistoryAccount.orderRecords[1];
@@ -351,11 +341,27 @@ describe('orders', () => {
 		assert(orderRecord.recordId.eq(expectedRecordId));
 		assert(orderRecord.ts.gt(ZERO));
 		assert(orderRecord.order.orderId.eq(expectedOrderId));
-		assert(enumsAreEqual(orderRecord.action, OrderAction.CANCEL));
+		assert(enumsAreEqual(orderRecord.action, OrderAction.REDUCE_ONLY_CANCEL));
 		assert(
 			orderRecord.user.equals(await clearingHouseUser.getUserAccountPublicKey())
 		);
 		assert(orderRecord.authority.equals(clearingHouseUser.authority));
+		assert(
+			orderRecord.filler.equals(
+				await fillerClearingHouse.getUserAccountPublicKey()
+			)
+		);
+	});
+
+	it('Fail to cancel order that was already cancelled', async () => {
+		const orderId = new BN(1);
+		try {
+			await clearingHouse.cancelOrder(orderId);
+		} catch (e) {
+			return;
+		}
+
+		assert(false);
 	});
 
 	it('Fill limit long order', async () => {
@@ -1685,4 +1691,44 @@ describe('orders', () => {
 		assert(whaleUserAccount.totalFeePaid.gt(fillerReward.mul(new BN(100))));
 		// ensure whale fee more than x100 filler
 	});
+
+	it('Cancel order', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.div(new BN(2)),
+			false,
+			false,
+			false,
+			1
+		);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		const openOrder = clearingHouseUser.getOrderByUserOrderId(1);
+		await clearingHouse.cancelOrder(openOrder.orderId);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getOrder(openOrder.orderId);
+		assert(order === undefined);
+
+		const position = clearingHouseUser.getUserPosition(marketIndex);
+		const expectedOpenOrders = new BN(0);
+		assert(position.openOrders.eq(expectedOpenOrders));
+
+		const orderHistoryAccount = clearingHouse.getOrderHistoryAccount();
+		const orderRecord: OrderRecord =
+			orderHistoryAccount.orderRecords[
+				orderHistoryAccount.head.toNumber() - 1
+			];
+		assert(orderRecord.ts.gt(ZERO));
+		assert(orderRecord.order.orderId.eq(openOrder.orderId));
+		assert(enumsAreEqual(orderRecord.action, OrderAction.CANCEL));
+		assert(
+			orderRecord.user.equals(await clearingHouseUser.getUserAccountPublicKey())
+		);
+		assert(orderRecord.authority.equals(clearingHouseUser.authority));
+	});
 });
@@ -14,11 +14,13 @@ import {
 	getUserOrdersAccountPublicKey,
 	ClearingHouseUser,
 	Wallet,
+	calculateMarkPrice,
 	getLimitOrderParams,
+	isVariant,
 } from '../sdk/src';
 
 import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
-import { AMM_RESERVE_PRECISION } from '../sdk';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
 import { AccountInfo, Token, TOKEN_PROGRAM_ID } from '@solana/spl-token';
 
 describe('order referrer', () => {
@@ -306,4 +308,74 @@ describe('order referrer', () => {
 		assert(userAccount.totalTokenDiscount.eq(expectedTokenDiscount));
 		assert(userAccount.totalRefereeDiscount.eq(expectedRefereeDiscount));
 	});
+
+	it('match_orders', async () => {
+		await clearingHouse.fetchAccounts();
+		const markPrice = calculateMarkPrice(clearingHouse.getMarket(marketIndex));
+
+		// the filler makes the market so the taker remainder falls back to the amm
+		const makerOrderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			AMM_RESERVE_PRECISION,
+			markPrice.mul(new BN(101)).div(new BN(100)),
+			false
+		);
+		await fillerClearingHouse.placeOrder(makerOrderParams);
+
+		const takerOrderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION.mul(new BN(2)),
+			markPrice.mul(new BN(105)).div(new BN(100)),
+			false,
+			false,
+			true
+		);
+		const referrerUserAccountPublicKey =
+			await referrerUser.getUserAccountPublicKey();
+		await clearingHouse.placeOrder(
+			takerOrderParams,
+			undefined,
+			referrerUserAccountPublicKey
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		await fillerUser.fetchAccounts();
+		await referrerUser.fetchAccounts();
+		const referralRewardBefore =
+			referrerUser.getUserAccount().totalReferralReward;
+		const takerOrder = clearingHouseUser.getUserOrdersAccount().orders[0];
+		const makerOrder = fillerUser.getUserOrdersAccount().orders[0];
+
+		const makerAccountPublicKey = await fillerUser.getUserAccountPublicKey();
+		await fillerClearingHouse.matchOrders(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			takerOrder,
+			makerAccountPublicKey,
+			await getUserOrdersAccountPublicKey(
+				chProgram.programId,
+				makerAccountPublicKey
+			),
+			makerOrder
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		await referrerUser.fetchAccounts();
+		const referrerUserAccount = referrerUser.getUserAccount();
+		assert(referrerUserAccount.totalReferralReward.gt(referralRewardBefore));
+
+		// the matched leg pays the referral reward and discount as well
+		await clearingHouse.fetchAccounts();
+		const tradeHistory = clearingHouse.getTradeHistoryAccount();
+		const matchedTradeRecord =
+			tradeHistory.tradeRecords[tradeHistory.head.toNumber() - 3];
+		assert(matchedTradeRecord.user.equals(userAccountPublicKey));
+		assert(matchedTradeRecord.referrerReward.gt(ZERO));
+		assert(matchedTradeRecord.refereeDiscount.gt(ZERO));
+
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.status, 'init'));
+	});
 });
@@ -0,0 +1,216 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	ClearingHouseUser,
+	OrderRecord,
+	getLimitOrderParams,
+	getMarketOrderParams,
+	getUserOrdersAccountPublicKey,
+	isVariant,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('position limit', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	let userAccountPublicKey;
+	let userOrdersAccountPublicKey;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+	});
+
+	it('Fill long order up to position limit', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION.mul(new BN(3)),
+			MARK_PRICE_PRECISION.mul(new BN(11)).div(new BN(10)),
+			false
+		);
+		orderParams.positionLimit = AMM_RESERVE_PRECISION.mul(new BN(2));
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(order.positionLimit.eq(orderParams.positionLimit));
+
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(orderParams.positionLimit));
+		assert(position.openOrders.eq(ZERO));
+
+		const orderAfter = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(orderAfter.status, 'init'));
+
+		const orderRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[1];
+		assert(isVariant(orderRecord.action, 'fill'));
+		assert(orderRecord.baseAssetAmountFilled.eq(orderParams.positionLimit));
+	});
+
+	it('Fail to fill long order once position is at limit', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(11)).div(new BN(10)),
+			false
+		);
+		orderParams.positionLimit = AMM_RESERVE_PRECISION.mul(new BN(2));
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+
+		let fillFailed = false;
+		try {
+			await clearingHouse.fillOrder(
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				order
+			);
+		} catch (e) {
+			fillFailed = true;
+		}
+		assert(fillFailed);
+
+		// a batch skips the order without shrinking it
+		await clearingHouse.fillOrders([
+			{ userAccountPublicKey, userOrdersAccountPublicKey, order },
+		]);
+
+		await clearingHouseUser.fetchAccounts();
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(orderParams.positionLimit));
+
+		const orderAfter = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(orderAfter.status, 'open'));
+		assert(orderAfter.baseAssetAmount.eq(order.baseAssetAmount));
+
+		await clearingHouse.cancelOrder(order.orderId);
+	});
+
+	it('Fill short order through zero to position limit', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			AMM_RESERVE_PRECISION.mul(new BN(5)),
+			MARK_PRICE_PRECISION.mul(new BN(9)).div(new BN(10)),
+			false
+		);
+		orderParams.positionLimit = AMM_RESERVE_PRECISION;
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(AMM_RESERVE_PRECISION.neg()));
+		assert(position.openOrders.eq(ZERO));
+	});
+
+	it('Fail to place market order with position limit', async () => {
+		const orderParams = getMarketOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			ZERO,
+			AMM_RESERVE_PRECISION,
+			false
+		);
+		orderParams.positionLimit = AMM_RESERVE_PRECISION;
+
+		try {
+			await clearingHouse.placeAndFillOrder(orderParams);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+});
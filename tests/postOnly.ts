@@ -0,0 +1,285 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	ClearingHouseUser,
+	OrderRecord,
+	OrderAction,
+	OrderStatus,
+	getLimitOrderParams,
+	getOracleOffsetLimitOrderParams,
+	getMarketOrderParams,
+	getUserOrdersAccountPublicKey,
+	isVariant,
+} from '../sdk/src';
+
+import {
+	mockOracle,
+	mockUSDCMint,
+	mockUserUSDCAccount,
+	setFeedPrice,
+} from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('post only', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+	let userAccountPublicKey;
+	let userOrdersAccountPublicKey;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+	});
+
+	it('Place post only order below mark', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(9)).div(new BN(10)),
+			false,
+			false,
+			false,
+			0,
+			true
+		);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.status, 'open'));
+		assert(order.postOnly);
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.openOrders.eq(new BN(1)));
+
+		const orderRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[0];
+		assert(isVariant(orderRecord.action, 'place'));
+
+		await clearingHouse.cancelOrder(order.orderId);
+	});
+
+	it('Cancel post only order that would cross the amm', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(11)).div(new BN(10)),
+			false,
+			false,
+			false,
+			0,
+			true
+		);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.status, 'init'));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.openOrders.eq(ZERO));
+
+		const orderRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[2];
+		assert(
+			JSON.stringify(orderRecord.action) ===
+				JSON.stringify(OrderAction.POST_ONLY_CANCEL)
+		);
+		assert(orderRecord.order.postOnly);
+		// the order was rejected before it was given an order id
+		assert(orderRecord.order.orderId.eq(ZERO));
+	});
+
+	it('Place and fill post only order that would cross the amm', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(9)).div(new BN(10)),
+			false,
+			false,
+			false,
+			0,
+			true
+		);
+		await clearingHouse.placeAndFillOrder(orderParams);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(JSON.stringify(order.status) === JSON.stringify(OrderStatus.INIT));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(ZERO));
+
+		const orderRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[3];
+		assert(isVariant(orderRecord.action, 'postOnlyCancel'));
+	});
+
+	it('Fail to place post only market order', async () => {
+		const orderParams = getMarketOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			ZERO,
+			AMM_RESERVE_PRECISION,
+			false
+		);
+		orderParams.postOnly = true;
+
+		try {
+			await clearingHouse.placeAndFillOrder(orderParams);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Keep post only order resting once mark moves through it', async () => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(99)).div(new BN(100)),
+			false,
+			false,
+			false,
+			0,
+			true
+		);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.status, 'open'));
+
+		// filling the order now would take liquidity from the amm
+		await clearingHouse.moveAmmToPrice(
+			marketIndex,
+			MARK_PRICE_PRECISION.mul(new BN(95)).div(new BN(100))
+		);
+		try {
+			await clearingHouse.fillOrder(
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				order
+			);
+			assert(false);
+		} catch (e) {
+			assert(e.msg === 'CouldNotFillOrder');
+		}
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const orderAfter = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(orderAfter.status, 'open'));
+		assert(orderAfter.orderId.eq(order.orderId));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(ZERO));
+		assert(position.openOrders.eq(new BN(1)));
+
+		await clearingHouse.cancelOrder(order.orderId);
+	});
+
+	it('Cancel oracle offset post only order at live price', async () => {
+		await clearingHouse.moveAmmToPrice(marketIndex, MARK_PRICE_PRECISION);
+		// the amm's last oracle price is only updated by trades, so it is still 1
+		await setFeedPrice(anchor.workspace.Pyth, 1.5, solUsd);
+
+		const orderParams = getOracleOffsetLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.div(new BN(10)).neg(),
+			false,
+			false,
+			false,
+			0,
+			true
+		);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.status, 'init'));
+
+		const orderRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[6];
+		assert(isVariant(orderRecord.action, 'postOnlyCancel'));
+	});
+});
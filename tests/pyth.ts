@@ -4,11 +4,18 @@ import { assert } from 'chai';
 import { BN } from '../sdk';
 
 import {
+	FeedStatus,
 	getFeedData,
 	mockOracle,
 	mockUserUSDCAccount,
 	mockUSDCMint,
+	setFeedComponent,
+	setFeedConfidence,
+	setFeedExpo,
 	setFeedPrice,
+	setFeedStatus,
+	setFeedTwac,
+	setFeedValidSlot,
 } from './testHelpers';
 
 import {
@@ -249,6 +256,39 @@ describe('pyth-oracle', () => {
 		assert.ok(feedDataAfter.exponent === expo);
 	});
 
+	it('change feed confidence, twac, valid slot, status and components', async () => {
+		const price = 50000;
+		const expo = -9;
+		const priceFeedAddress = await mockOracle(price, expo);
+
+		await setFeedConfidence(program, 500, priceFeedAddress);
+		await setFeedTwac(program, 250, priceFeedAddress);
+		await setFeedValidSlot(program, 100, priceFeedAddress);
+		await setFeedStatus(program, FeedStatus.HALTED, priceFeedAddress);
+		await setFeedComponent(
+			program,
+			0,
+			Keypair.generate().publicKey,
+			price,
+			500,
+			FeedStatus.TRADING,
+			priceFeedAddress
+		);
+
+		let feedData = await getFeedData(program, priceFeedAddress);
+		assert.ok(feedData.confidence === 500);
+		assert.ok(feedData.twac === 250);
+		assert.ok(feedData.validSlot === BigInt(100));
+		assert.ok(feedData.status === 2);
+		assert.ok(feedData.numComponentPrices === 1);
+		assert.ok(feedData.numQt === 1);
+		assert.ok(feedData.priceComponents.length === 1);
+
+		await setFeedExpo(program, -6, priceFeedAddress);
+		feedData = await getFeedData(program, priceFeedAddress);
+		assert.ok(feedData.exponent === -6);
+	});
+
 	it('oracle/vamm: funding rate calc 0hour periodicity', async () => {
 		const priceFeedAddress = await mockOracle(40, -10);
 		const periodicity = new BN(0); // 1 HOUR
@@ -0,0 +1,231 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import { PublicKey } from '@solana/web3.js';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	ClearingHouseUser,
+	OrderRecord,
+	getLimitOrderParams,
+	getMarketOrderParams,
+	getUserOrdersAccountPublicKey,
+	isVariant,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('reduce only orders', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let userAccountPublicKey: PublicKey;
+	let userOrdersAccountPublicKey: PublicKey;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	const openLong = async () => {
+		const orderParams = getMarketOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			ZERO,
+			AMM_RESERVE_PRECISION,
+			false
+		);
+		await clearingHouse.placeAndFillOrder(orderParams);
+	};
+
+	const placeReduceOnlyShort = async (
+		baseAssetAmount: BN,
+		price: BN
+	): Promise<void> => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			baseAssetAmount,
+			price,
+			true
+		);
+		await clearingHouse.placeOrder(orderParams);
+	};
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+	});
+
+	it('Clamp reduce only order to position', async () => {
+		await openLong();
+
+		await placeReduceOnlyShort(
+			AMM_RESERVE_PRECISION.mul(new BN(2)),
+			MARK_PRICE_PRECISION.mul(new BN(9)).div(new BN(10))
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(ZERO));
+		assert(position.openOrders.eq(ZERO));
+
+		const filledOrder = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(filledOrder.status, 'init'));
+
+		const orderRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[3];
+		assert(isVariant(orderRecord.action, 'fill'));
+		assert(orderRecord.order.orderId.eq(order.orderId));
+		assert(orderRecord.baseAssetAmountFilled.eq(AMM_RESERVE_PRECISION));
+		assert(orderRecord.order.baseAssetAmount.eq(AMM_RESERVE_PRECISION));
+	});
+
+	it('Cancel reduce only orders when position is closed', async () => {
+		await openLong();
+
+		await placeReduceOnlyShort(
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(2))
+		);
+		await placeReduceOnlyShort(
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(9)).div(new BN(10))
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		const restingOrder = clearingHouseUser.getUserOrdersAccount().orders[0];
+		const crossingOrder = clearingHouseUser.getUserOrdersAccount().orders[1];
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			crossingOrder
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(ZERO));
+		assert(position.openOrders.eq(ZERO));
+
+		const orders = clearingHouseUser.getUserOrdersAccount().orders;
+		assert(isVariant(orders[0].status, 'init'));
+		assert(isVariant(orders[1].status, 'init'));
+
+		const orderHistory = clearingHouse.getOrderHistoryAccount();
+		const fillRecord: OrderRecord = orderHistory.orderRecords[8];
+		assert(isVariant(fillRecord.action, 'fill'));
+		assert(fillRecord.order.orderId.eq(crossingOrder.orderId));
+
+		const cancelRecord: OrderRecord = orderHistory.orderRecords[9];
+		assert(isVariant(cancelRecord.action, 'reduceOnlyCancel'));
+		assert(cancelRecord.order.orderId.eq(restingOrder.orderId));
+		assert(cancelRecord.tradeRecordId.eq(fillRecord.tradeRecordId));
+	});
+
+	it('Cancel reduce only order instead of failing fill', async () => {
+		await placeReduceOnlyShort(
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.mul(new BN(9)).div(new BN(10))
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(ZERO));
+		assert(position.openOrders.eq(ZERO));
+
+		const cancelledOrder = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(cancelledOrder.status, 'init'));
+
+		const orderRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[11];
+		assert(isVariant(orderRecord.action, 'reduceOnlyCancel'));
+		assert(orderRecord.order.orderId.eq(order.orderId));
+		assert(orderRecord.baseAssetAmountFilled.eq(ZERO));
+	});
+});
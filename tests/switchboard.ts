@@ -0,0 +1,139 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	OracleSource,
+	PositionDirection,
+	QUOTE_PRECISION,
+} from '../sdk/src';
+
+import {
+	mockSwitchboardOracle,
+	mockUSDCMint,
+	mockUserUSDCAccount,
+	setSwitchboardFeedMinResponses,
+	setSwitchboardFeedValue,
+} from './testHelpers';
+
+describe('switchboard', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+	const switchboardProgram = anchor.workspace.Switchboard as Program;
+
+	let clearingHouse: Admin;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let aggregator;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribe();
+
+		aggregator = await mockSwitchboardOracle(1);
+
+		await clearingHouse.initializeUserAccountAndDepositCollateral(
+			usdcAmount,
+			userUSDCAccount.publicKey
+		);
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+	});
+
+	it('Initialize market with switchboard oracle', async () => {
+		const periodicity = new BN(60 * 60); // 1 HOUR
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			aggregator,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity,
+			undefined,
+			OracleSource.SWITCHBOARD
+		);
+
+		const market = clearingHouse.getMarket(marketIndex);
+		assert(market.amm.lastOraclePrice.eq(MARK_PRICE_PRECISION));
+		assert(
+			JSON.stringify(market.amm.oracleSource) ===
+				JSON.stringify(OracleSource.SWITCHBOARD)
+		);
+	});
+
+	it('Open position against switchboard oracle', async () => {
+		await setSwitchboardFeedValue(switchboardProgram, 1.01, aggregator);
+
+		await clearingHouse.openPosition(
+			PositionDirection.LONG,
+			QUOTE_PRECISION,
+			marketIndex
+		);
+
+		await clearingHouse.fetchAccounts();
+		const market = clearingHouse.getMarket(marketIndex);
+		assert(market.baseAssetAmountLong.gt(new BN(0)));
+	});
+
+	it('Flag round without enough responses as invalid', async () => {
+		await setSwitchboardFeedMinResponses(switchboardProgram, 3, 1, aggregator);
+
+		await clearingHouse.updateOracleStatus(aggregator, marketIndex);
+		await clearingHouse.fetchAccounts();
+		let market = clearingHouse.getMarket(marketIndex);
+		assert(market.consecutiveInvalidOracleReadings.eq(new BN(1)));
+
+		await setSwitchboardFeedMinResponses(switchboardProgram, 3, 3, aggregator);
+
+		await clearingHouse.updateOracleStatus(aggregator, marketIndex);
+		await clearingHouse.fetchAccounts();
+		market = clearingHouse.getMarket(marketIndex);
+		assert(market.consecutiveInvalidOracleReadings.eq(new BN(0)));
+	});
+
+	it('Flag round far from the amm twap as too volatile', async () => {
+		// ten times the twap is past the too volatile ratio of 5
+		await setSwitchboardFeedValue(switchboardProgram, 10, aggregator);
+
+		await clearingHouse.updateOracleStatus(aggregator, marketIndex);
+		await clearingHouse.fetchAccounts();
+		let market = clearingHouse.getMarket(marketIndex);
+		assert(market.consecutiveInvalidOracleReadings.eq(new BN(1)));
+
+		await setSwitchboardFeedValue(switchboardProgram, 1.01, aggregator);
+
+		await clearingHouse.updateOracleStatus(aggregator, marketIndex);
+		await clearingHouse.fetchAccounts();
+		market = clearingHouse.getMarket(marketIndex);
+		assert(market.consecutiveInvalidOracleReadings.eq(new BN(0)));
+	});
+});
@@ -47,6 +47,82 @@ export async function mockOracle(
 	return priceFeedAddress;
 }
 
+const SWITCHBOARD_SCALE = 9;
+
+export async function mockSwitchboardOracle(
+	price: number,
+	minOracleResults = 1
+): Promise<PublicKey> {
+	const program = anchor.workspace.Switchboard;
+
+	const aggregator = Keypair.generate();
+	await program.rpc.initialize(
+		new BN(price * 10 ** SWITCHBOARD_SCALE),
+		SWITCHBOARD_SCALE,
+		minOracleResults,
+		{
+			accounts: { aggregator: aggregator.publicKey },
+			signers: [aggregator],
+			instructions: [
+				await program.account.aggregatorAccountData.createInstruction(
+					aggregator
+				),
+			],
+		}
+	);
+
+	return aggregator.publicKey;
+}
+
+export const setSwitchboardFeedValue = async (
+	switchboardProgram: Program,
+	newPrice: number,
+	aggregator: PublicKey
+) => {
+	await switchboardProgram.rpc.setValue(
+		new BN(newPrice * 10 ** SWITCHBOARD_SCALE),
+		SWITCHBOARD_SCALE,
+		{
+			accounts: { aggregator },
+		}
+	);
+};
+
+export const setSwitchboardFeedStdDeviation = async (
+	switchboardProgram: Program,
+	stdDeviation: number,
+	aggregator: PublicKey
+) => {
+	await switchboardProgram.rpc.setStdDeviation(
+		new BN(stdDeviation * 10 ** SWITCHBOARD_SCALE),
+		SWITCHBOARD_SCALE,
+		{
+			accounts: { aggregator },
+		}
+	);
+};
+
+export const setSwitchboardFeedRoundOpenSlot = async (
+	switchboardProgram: Program,
+	roundOpenSlot: number,
+	aggregator: PublicKey
+) => {
+	await switchboardProgram.rpc.setRoundOpenSlot(new BN(roundOpenSlot), {
+		accounts: { aggregator },
+	});
+};
+
+export const setSwitchboardFeedMinResponses = async (
+	switchboardProgram: Program,
+	minOracleResults: number,
+	numSuccess: number,
+	aggregator: PublicKey
+) => {
+	await switchboardProgram.rpc.setMinResponses(minOracleResults, numSuccess, {
+		accounts: { aggregator },
+	});
+};
+
 export async function mockUSDCMint(provider: Provider): Promise<Keypair> {
 	const fakeUSDCMint = anchor.web3.Keypair.generate();
 	const createUSDCMintAccountIx = SystemProgram.createAccount({
@@ -247,7 +323,7 @@ const PKorNull = (data) =>
 export const createPriceFeed = async ({
 	oracleProgram,
 	initPrice,
-	confidence = undefined,
+	confidence = 0,
 	expo = -4,
 }: {
 	oracleProgram: Program;
@@ -255,7 +331,7 @@ export const createPriceFeed = async ({
 	confidence?: number;
 	expo?: number;
 }): Promise<PublicKey> => {
-	const conf = confidence || new BN((initPrice / 10) * 10 ** -expo);
+	const conf = new BN(confidence * 10 ** -expo);
 	const collateralTokenFeed = new anchor.web3.Account();
 	await oracleProgram.rpc.initialize(
 		new BN(initPrice * 10 ** -expo),
@@ -307,6 +383,92 @@ export const setFeedTwap = async (
 		accounts: { price: priceFeed },
 	});
 };
+export const setFeedConfidence = async (
+	oracleProgram: Program,
+	newConfidence: number,
+	priceFeed: PublicKey
+) => {
+	const info = await oracleProgram.provider.connection.getAccountInfo(
+		priceFeed
+	);
+	const data = parsePriceData(info.data);
+	await oracleProgram.rpc.setConf(
+		new BN(newConfidence * 10 ** -data.exponent),
+		{
+			accounts: { price: priceFeed },
+		}
+	);
+};
+export const setFeedTwac = async (
+	oracleProgram: Program,
+	newTwac: number,
+	priceFeed: PublicKey
+) => {
+	const info = await oracleProgram.provider.connection.getAccountInfo(
+		priceFeed
+	);
+	const data = parsePriceData(info.data);
+	await oracleProgram.rpc.setTwac(new BN(newTwac * 10 ** -data.exponent), {
+		accounts: { price: priceFeed },
+	});
+};
+export const setFeedValidSlot = async (
+	oracleProgram: Program,
+	validSlot: number,
+	priceFeed: PublicKey
+) => {
+	await oracleProgram.rpc.setValidSlot(new BN(validSlot), {
+		accounts: { price: priceFeed },
+	});
+};
+export class FeedStatus {
+	static readonly UNKNOWN = { unknown: {} };
+	static readonly TRADING = { trading: {} };
+	static readonly HALTED = { halted: {} };
+	static readonly AUCTION = { auction: {} };
+}
+export const setFeedStatus = async (
+	oracleProgram: Program,
+	status: FeedStatus,
+	priceFeed: PublicKey
+) => {
+	await oracleProgram.rpc.setStatus(status, {
+		accounts: { price: priceFeed },
+	});
+};
+export const setFeedExpo = async (
+	oracleProgram: Program,
+	expo: number,
+	priceFeed: PublicKey
+) => {
+	await oracleProgram.rpc.setExpo(expo, {
+		accounts: { price: priceFeed },
+	});
+};
+export const setFeedComponent = async (
+	oracleProgram: Program,
+	index: number,
+	publisher: PublicKey,
+	price: number,
+	confidence: number,
+	status: FeedStatus,
+	priceFeed: PublicKey
+) => {
+	const info = await oracleProgram.provider.connection.getAccountInfo(
+		priceFeed
+	);
+	const data = parsePriceData(info.data);
+	await oracleProgram.rpc.setComponent(
+		index,
+		publisher,
+		new BN(price * 10 ** -data.exponent),
+		new BN(confidence * 10 ** -data.exponent),
+		status,
+		{
+			accounts: { price: priceFeed },
+		}
+	);
+};
 export const getFeedData = async (
 	oracleProgram: Program,
 	priceFeed: PublicKey
@@ -399,8 +561,8 @@ const parsePriceData = (data) => {
 	const exponent = data.readInt32LE(20);
 	// Number of component prices.
 	const numComponentPrices = data.readUInt32LE(24);
-	// unused
-	// const unused = accountInfo.data.readUInt32LE(28)
+	// Number of quoters that make up aggregate.
+	const numQt = data.readUInt32LE(28);
 	// Currently accumulating price slot.
 	const currentSlot = readBigUInt64LE(data, 32);
 	// Valid on-chain slot of aggregate price.
@@ -416,6 +578,8 @@ const parsePriceData = (data) => {
 	const drv0 = Number(drv0Component) * 10 ** exponent;
 	const drv1Component = readBigInt64LE(data, 72);
 	const drv1 = Number(drv1Component) * 10 ** exponent;
+	// Time-weighted average confidence interval.
+	const twac = drv1;
 	const drv2Component = readBigInt64LE(data, 80);
 	const drv2 = Number(drv2Component) * 10 ** exponent;
 	const drv3Component = readBigInt64LE(data, 88);
@@ -463,10 +627,12 @@ const parsePriceData = (data) => {
 				priceType,
 				exponent,
 				numComponentPrices,
+				numQt,
 				currentSlot,
 				validSlot,
 				twapComponent,
 				twap,
+				twac,
 				avolComponent,
 				avol,
 				drv0Component,
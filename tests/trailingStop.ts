@@ -0,0 +1,309 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	ClearingHouseUser,
+	OrderTriggerPriceSource,
+	TrailingStopOffsetType,
+	calculateMarkPrice,
+	getTrailingStopOrderParams,
+	getUserOrdersAccountPublicKey,
+	isVariant,
+} from '../sdk/src';
+
+import {
+	mockOracle,
+	mockUSDCMint,
+	mockUserUSDCAccount,
+	setFeedPrice,
+} from './testHelpers';
+import { AMM_RESERVE_PRECISION, TEN_THOUSAND, ZERO } from '../sdk';
+
+describe('trailing stop', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	let userAccountPublicKey;
+	let userOrdersAccountPublicKey;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+	});
+
+	it('Place trailing stop five percent below mark', async () => {
+		const orderParams = getTrailingStopOrderParams(
+			marketIndex,
+			PositionDirection.SHORT,
+			AMM_RESERVE_PRECISION,
+			new BN(500),
+			TrailingStopOffsetType.PERCENTAGE,
+			false
+		);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const markPrice = calculateMarkPrice(clearingHouse.getMarket(marketIndex));
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.status, 'open'));
+		assert(isVariant(order.orderType, 'trailingStop'));
+		assert(isVariant(order.triggerCondition, 'below'));
+		assert(
+			order.triggerPrice.eq(
+				markPrice.sub(markPrice.mul(new BN(500)).div(TEN_THOUSAND))
+			)
+		);
+	});
+
+	it('Trigger price follows mark up', async () => {
+		await clearingHouseUser.fetchAccounts();
+		const orderBefore = clearingHouseUser.getUserOrdersAccount().orders[0];
+
+		await clearingHouse.moveAmmToPrice(
+			marketIndex,
+			MARK_PRICE_PRECISION.mul(new BN(11)).div(new BN(10))
+		);
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			orderBefore
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const markPrice = calculateMarkPrice(clearingHouse.getMarket(marketIndex));
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.status, 'open'));
+		assert(order.triggerPrice.gt(orderBefore.triggerPrice));
+		assert(
+			order.triggerPrice.eq(
+				markPrice.sub(markPrice.mul(new BN(500)).div(TEN_THOUSAND))
+			)
+		);
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(ZERO));
+	});
+
+	it('Fail to ratchet trigger price on a small mark move', async () => {
+		await clearingHouseUser.fetchAccounts();
+		const orderBefore = clearingHouseUser.getUserOrdersAccount().orders[0];
+
+		await clearingHouse.moveAmmToPrice(
+			marketIndex,
+			MARK_PRICE_PRECISION.mul(new BN(1101)).div(new BN(1000))
+		);
+		try {
+			await clearingHouse.fillOrder(
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				orderBefore
+			);
+			assert(false);
+		} catch (e) {
+			assert(e.msg === 'CouldNotFillOrder');
+		}
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(order.triggerPrice.eq(orderBefore.triggerPrice));
+	});
+
+	it('Trigger price holds when mark pulls back', async () => {
+		await clearingHouseUser.fetchAccounts();
+		const orderBefore = clearingHouseUser.getUserOrdersAccount().orders[0];
+
+		await clearingHouse.moveAmmToPrice(
+			marketIndex,
+			MARK_PRICE_PRECISION.mul(new BN(105)).div(new BN(100))
+		);
+		try {
+			await clearingHouse.fillOrder(
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				orderBefore
+			);
+			assert(false);
+		} catch (e) {
+			assert(e.msg === 'CouldNotFillOrder');
+		}
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.status, 'open'));
+		assert(order.triggerPrice.eq(orderBefore.triggerPrice));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(ZERO));
+	});
+
+	it('Fill once mark falls through trigger price', async () => {
+		await clearingHouse.fetchAccounts();
+		await clearingHouse.moveAmmToPrice(marketIndex, MARK_PRICE_PRECISION);
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
+
+		await clearingHouseUser.fetchAccounts();
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(AMM_RESERVE_PRECISION.neg()));
+		assert(position.openOrders.eq(ZERO));
+
+		const orderAfter = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(orderAfter.status, 'init'));
+	});
+
+	it('Trigger price follows oracle instead of mark', async () => {
+		const orderParams = getTrailingStopOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.div(new BN(20)),
+			TrailingStopOffsetType.PRICE,
+			false,
+			false,
+			false,
+			0,
+			OrderTriggerPriceSource.ORACLE
+		);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		const orderBefore = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(orderBefore.triggerCondition, 'above'));
+
+		await setFeedPrice(anchor.workspace.Pyth, 0.9, solUsd);
+		await clearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			orderBefore
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		const markPrice = calculateMarkPrice(clearingHouse.getMarket(marketIndex));
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.status, 'open'));
+		assert(order.triggerPrice.lt(orderBefore.triggerPrice));
+		// trailing the mark would have left the trigger price above it
+		assert(order.triggerPrice.lt(markPrice));
+
+		// the mark moving through the trigger price doesnt fill the order
+		await clearingHouse.moveAmmToPrice(
+			marketIndex,
+			MARK_PRICE_PRECISION.mul(new BN(11)).div(new BN(10))
+		);
+		try {
+			await clearingHouse.fillOrder(
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				order
+			);
+			assert(false);
+		} catch (e) {
+			assert(e.msg === 'CouldNotFillOrder');
+		}
+
+		await clearingHouseUser.fetchAccounts();
+		const orderAfter = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(orderAfter.status, 'open'));
+		assert(orderAfter.triggerPrice.eq(order.triggerPrice));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(AMM_RESERVE_PRECISION.neg()));
+
+		await clearingHouse.cancelOrder(orderAfter.orderId);
+	});
+
+	it('Fail to place trailing stop without offset', async () => {
+		const orderParams = getTrailingStopOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			ZERO,
+			TrailingStopOffsetType.PRICE,
+			false
+		);
+
+		try {
+			await clearingHouse.placeOrder(orderParams);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+});
@@ -0,0 +1,271 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import { Keypair, PublicKey } from '@solana/web3.js';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	ClearingHouse,
+	PositionDirection,
+	getUserOrdersAccountPublicKey,
+	ClearingHouseUser,
+	OrderRecord,
+	Wallet,
+	getTwapOrderParams,
+	isVariant,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('twap orders', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let userAccountPublicKey: PublicKey;
+	let userOrdersAccountPublicKey: PublicKey;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const fillerKeyPair = new Keypair();
+	let fillerUSDCAccount: Keypair;
+	let fillerClearingHouse: ClearingHouse;
+	let fillerUser: ClearingHouseUser;
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId,
+			{
+				commitment: 'confirmed',
+			}
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+
+		provider.connection.requestAirdrop(fillerKeyPair.publicKey, 10 ** 9);
+		fillerUSDCAccount = await mockUserUSDCAccount(
+			usdcMint,
+			usdcAmount,
+			provider,
+			fillerKeyPair.publicKey
+		);
+		fillerClearingHouse = ClearingHouse.from(
+			connection,
+			new Wallet(fillerKeyPair),
+			chProgram.programId,
+			{
+				commitment: 'confirmed',
+			}
+		);
+		await fillerClearingHouse.subscribe();
+
+		await fillerClearingHouse.initializeUserAccountAndDepositCollateral(
+			usdcAmount,
+			fillerUSDCAccount.publicKey
+		);
+
+		fillerUser = ClearingHouseUser.from(
+			fillerClearingHouse,
+			fillerKeyPair.publicKey
+		);
+		await fillerUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+		await fillerClearingHouse.unsubscribe();
+		await fillerUser.unsubscribe();
+	});
+
+	it('Fill first slice as soon as twap is placed', async () => {
+		const orderParams = getTwapOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION.mul(new BN(2)),
+			new BN(2),
+			new BN(10),
+			false,
+			MARK_PRICE_PRECISION.mul(new BN(2))
+		);
+		await clearingHouse.placeOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		await fillerUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(order.orderType, 'twap'));
+		assert(order.twapSliceCount.eq(new BN(2)));
+		assert(order.twapInterval.eq(new BN(10)));
+		assert(order.twapLastSliceTs.eq(ZERO));
+		const fillerCollateralBefore = fillerUser.getUserAccount().collateral;
+
+		await fillerClearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+		await fillerUser.fetchAccounts();
+
+		const orderAfter = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(orderAfter.status, 'open'));
+		assert(orderAfter.baseAssetAmountFilled.eq(AMM_RESERVE_PRECISION));
+		assert(orderAfter.twapLastSliceTs.gt(ZERO));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(AMM_RESERVE_PRECISION));
+
+		const orderRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[1];
+		assert(isVariant(orderRecord.action, 'fill'));
+		assert(orderRecord.baseAssetAmountFilled.eq(AMM_RESERVE_PRECISION));
+		assert(orderRecord.fillerReward.gt(ZERO));
+		assert(
+			fillerUser
+				.getUserAccount()
+				.collateral.eq(fillerCollateralBefore.add(orderRecord.fillerReward))
+		);
+	});
+
+	it('Fail to fill second slice before interval', async () => {
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+
+		try {
+			await fillerClearingHouse.fillOrder(
+				userAccountPublicKey,
+				userOrdersAccountPublicKey,
+				order
+			);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Fill second slice after interval', async () => {
+		await new Promise((r) => setTimeout(r, 12000)); // wait 12 seconds
+
+		await clearingHouseUser.fetchAccounts();
+		const order = clearingHouseUser.getUserOrdersAccount().orders[0];
+
+		await fillerClearingHouse.fillOrder(
+			userAccountPublicKey,
+			userOrdersAccountPublicKey,
+			order
+		);
+
+		await clearingHouse.fetchAccounts();
+		await clearingHouseUser.fetchAccounts();
+
+		const orderAfter = clearingHouseUser.getUserOrdersAccount().orders[0];
+		assert(isVariant(orderAfter.status, 'init'));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(AMM_RESERVE_PRECISION.mul(new BN(2))));
+		assert(position.openOrders.eq(ZERO));
+
+		const orderRecord: OrderRecord =
+			clearingHouse.getOrderHistoryAccount().orderRecords[2];
+		assert(isVariant(orderRecord.action, 'fill'));
+		assert(orderRecord.baseAssetAmountFilled.eq(AMM_RESERVE_PRECISION));
+		assert(orderRecord.fillerReward.gt(ZERO));
+	});
+
+	it('Fail to place twap with slices below minimum trade size', async () => {
+		const orderParams = getTwapOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			AMM_RESERVE_PRECISION,
+			new BN(10),
+			false,
+			MARK_PRICE_PRECISION.mul(new BN(2))
+		);
+
+		try {
+			await clearingHouse.placeOrder(orderParams);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Fail to place twap without a limit price', async () => {
+		const orderParams = getTwapOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION.mul(new BN(2)),
+			new BN(2),
+			new BN(10),
+			false,
+			ZERO
+		);
+
+		try {
+			await clearingHouse.placeOrder(orderParams);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+});
@@ -0,0 +1,224 @@
+import * as anchor from '@project-serum/anchor';
+import { assert } from 'chai';
+
+import { Program } from '@project-serum/anchor';
+
+import { PublicKey } from '@solana/web3.js';
+
+import {
+	Admin,
+	BN,
+	MARK_PRICE_PRECISION,
+	PositionDirection,
+	ClearingHouseUser,
+	getLimitOrderParams,
+	getMarketOrderParams,
+	getUserOrdersAccountPublicKey,
+	getUserOrdersAccountSize,
+	isVariant,
+} from '../sdk/src';
+
+import { mockOracle, mockUSDCMint, mockUserUSDCAccount } from './testHelpers';
+import { AMM_RESERVE_PRECISION, ZERO } from '../sdk';
+
+describe('user orders capacity', () => {
+	const provider = anchor.Provider.local();
+	const connection = provider.connection;
+	anchor.setProvider(provider);
+	const chProgram = anchor.workspace.ClearingHouse as Program;
+
+	let clearingHouse: Admin;
+	let clearingHouseUser: ClearingHouseUser;
+
+	let userAccountPublicKey: PublicKey;
+	let userOrdersAccountPublicKey: PublicKey;
+
+	let usdcMint;
+	let userUSDCAccount;
+
+	// ammInvariant == k == x * y
+	const mantissaSqrtScale = new BN(Math.sqrt(MARK_PRICE_PRECISION.toNumber()));
+	const ammInitialQuoteAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+	const ammInitialBaseAssetReserve = new anchor.BN(5 * 10 ** 13).mul(
+		mantissaSqrtScale
+	);
+
+	const usdcAmount = new BN(10 * 10 ** 6);
+
+	const marketIndex = new BN(0);
+	let solUsd;
+
+	const placeLimitOrder = async (userOrderId: number) => {
+		const orderParams = getLimitOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			AMM_RESERVE_PRECISION,
+			MARK_PRICE_PRECISION.div(new BN(2)),
+			false,
+			false,
+			false,
+			userOrderId
+		);
+		await clearingHouse.placeOrder(orderParams);
+	};
+
+	before(async () => {
+		usdcMint = await mockUSDCMint(provider);
+		userUSDCAccount = await mockUserUSDCAccount(usdcMint, usdcAmount, provider);
+
+		clearingHouse = Admin.from(
+			connection,
+			provider.wallet,
+			chProgram.programId
+		);
+		await clearingHouse.initialize(usdcMint.publicKey, true);
+		await clearingHouse.subscribeToAll();
+		solUsd = await mockOracle(1);
+
+		const periodicity = new BN(60 * 60); // 1 HOUR
+
+		await clearingHouse.initializeMarket(
+			marketIndex,
+			solUsd,
+			ammInitialBaseAssetReserve,
+			ammInitialQuoteAssetReserve,
+			periodicity
+		);
+
+		[, userAccountPublicKey] =
+			await clearingHouse.initializeUserAccountAndDepositCollateral(
+				usdcAmount,
+				userUSDCAccount.publicKey
+			);
+
+		userOrdersAccountPublicKey = await getUserOrdersAccountPublicKey(
+			clearingHouse.program.programId,
+			userAccountPublicKey
+		);
+
+		clearingHouseUser = ClearingHouseUser.from(
+			clearingHouse,
+			provider.wallet.publicKey
+		);
+		await clearingHouseUser.subscribe();
+	});
+
+	after(async () => {
+		await clearingHouse.unsubscribe();
+		await clearingHouseUser.unsubscribe();
+	});
+
+	it('Resize user orders to a smaller account', async () => {
+		await placeLimitOrder(1);
+		await placeLimitOrder(2);
+
+		const [, newUserOrdersAccountPublicKey] =
+			await clearingHouse.resizeUserOrders(4);
+
+		const oldUserOrdersAccount = await connection.getAccountInfo(
+			userOrdersAccountPublicKey
+		);
+		assert(oldUserOrdersAccount === null);
+
+		const newUserOrdersAccount = await connection.getAccountInfo(
+			newUserOrdersAccountPublicKey
+		);
+		assert(newUserOrdersAccount.data.length === getUserOrdersAccountSize(4));
+
+		// resubscribe so the user subscriber follows the new orders account
+		await clearingHouseUser.unsubscribe();
+		await clearingHouseUser.subscribe();
+
+		const userAccount = clearingHouseUser.getUserAccount();
+		assert(userAccount.userOrders.equals(newUserOrdersAccountPublicKey));
+		assert(
+			(await clearingHouseUser.getUserOrdersAccountPublicKey()).equals(
+				newUserOrdersAccountPublicKey
+			)
+		);
+
+		const userOrdersAccount = clearingHouseUser.getUserOrdersAccount();
+		assert(userOrdersAccount.user.equals(userAccountPublicKey));
+		assert(userOrdersAccount.orders.length === 4);
+		assert(userOrdersAccount.orders[0].userOrderId === 1);
+		assert(userOrdersAccount.orders[1].userOrderId === 2);
+		assert(isVariant(userOrdersAccount.orders[2].status, 'init'));
+	});
+
+	it('Place orders up to capacity', async () => {
+		await placeLimitOrder(3);
+		await placeLimitOrder(4);
+
+		await clearingHouseUser.fetchAccounts();
+		const orders = clearingHouseUser.getUserOrdersAccount().orders;
+		assert(orders.every((order) => isVariant(order.status, 'open')));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.openOrders.eq(new BN(4)));
+
+		try {
+			await placeLimitOrder(5);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Fail to resize below the number of open orders', async () => {
+		try {
+			await clearingHouse.resizeUserOrders(3);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Cancel and fill orders in resized account', async () => {
+		for (const order of clearingHouseUser.getUserOrdersAccount().orders) {
+			await clearingHouse.cancelOrder(order.orderId);
+		}
+
+		const orderParams = getMarketOrderParams(
+			marketIndex,
+			PositionDirection.LONG,
+			ZERO,
+			AMM_RESERVE_PRECISION,
+			false
+		);
+		await clearingHouse.placeAndFillOrder(orderParams);
+
+		await clearingHouseUser.fetchAccounts();
+		const orders = clearingHouseUser.getUserOrdersAccount().orders;
+		assert(orders.every((order) => isVariant(order.status, 'init')));
+
+		const position = clearingHouseUser.getUserPositionsAccount().positions[0];
+		assert(position.baseAssetAmount.eq(AMM_RESERVE_PRECISION));
+		assert(position.openOrders.eq(ZERO));
+	});
+
+	it('Fail to initialize user orders pda after resize', async () => {
+		try {
+			await clearingHouse.txSender.send(
+				new anchor.web3.Transaction().add(
+					await clearingHouse.getInitializeUserOrdersInstruction()
+				),
+				[],
+				clearingHouse.opts
+			);
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+
+	it('Fail to migrate user orders with current layout', async () => {
+		try {
+			await clearingHouse.migrateUserOrders();
+		} catch (e) {
+			return;
+		}
+		assert(false);
+	});
+});